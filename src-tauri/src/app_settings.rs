@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::login_env;
+
+/// App-wide preferences; per-project values live in the project profile.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct AppSettings {
+    /// Absolute path of the shell whose login environment tools run with.
+    /// Empty means `$SHELL`, then `/bin/zsh`.
+    pub login_shell: String,
+    /// The external `bootstrap_fastlane.sh` for the configFile and
+    /// interactive modes. Empty means search the default locations.
+    pub bootstrap_script_path: String,
+}
+
+impl AppSettings {
+    pub fn login_shell(&self) -> String {
+        let shell = self.login_shell.trim();
+        if shell.is_empty() {
+            login_env::default_shell()
+        } else {
+            shell.to_string()
+        }
+    }
+
+    fn validate(&self) -> AppResult<()> {
+        let shell = self.login_shell.trim();
+        let path = Path::new(shell);
+        if !shell.is_empty() && (!path.is_absolute() || !path.is_file()) {
+            return Err(
+                AppError::new(ErrorKind::InvalidInput, format!("Login shell not found: {}", shell))
+                    .with_hint("Use an absolute path such as /bin/zsh, /bin/bash or /opt/homebrew/bin/fish.")
+                    .with_path(path),
+            );
+        }
+        let script = self.bootstrap_script_path.trim();
+        if !script.is_empty() && !Path::new(script).is_file() {
+            return Err(AppError::new(
+                ErrorKind::BootstrapScriptNotFound,
+                format!("Bootstrap script not found: {}", script),
+            )
+            .with_hint("Choose ios-fastlane-skill/scripts/bootstrap_fastlane.sh, or leave it empty to search the default locations.")
+            .with_path(script));
+        }
+        Ok(())
+    }
+}
+
+fn settings_path(app: &AppHandle) -> AppResult<PathBuf> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Resolve app config dir failed: {}", e)))?;
+    Ok(dir.join("settings.json"))
+}
+
+pub fn load(app: &AppHandle) -> AppResult<AppSettings> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(AppSettings::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| AppError::io("Read settings", &path, e))?;
+    serde_json::from_str::<AppSettings>(&content).map_err(|e| AppError::parse("settings", &path, e))
+}
+
+pub fn save(app: &AppHandle, settings: &AppSettings) -> AppResult<()> {
+    settings.validate()?;
+    let path = settings_path(app)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| AppError::io("Create app config dir", dir, e))?;
+    }
+    let payload = serde_json::to_string_pretty(settings)
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Serialize settings failed: {}", e)))?;
+    fs::write(&path, payload).map_err(|e| AppError::io("Write settings", &path, e))
+}
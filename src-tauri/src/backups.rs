@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::dotenv::Document;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::generation::{self, FileChange, FilePreview};
+use crate::redact;
+use crate::runtime_env;
+
+const MANIFEST: &str = "backup.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFile {
+    /// Relative to the project root.
+    pub path: String,
+    /// False when the write created the file; restoring removes it again.
+    pub existed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub backup_id: String,
+    /// What was about to write, e.g. `apply-generation` or `bootstrap`.
+    pub reason: String,
+    pub created_at_ms: u64,
+    pub files: Vec<BackupFile>,
+}
+
+fn backups_dir(project_root: &Path) -> PathBuf {
+    project_root.join(".fastlane-desktop").join("backups")
+}
+
+/// Missing files are recorded so a restore removes them. Secret values in
+/// `.env*` files are blanked in the copy.
+pub fn snapshot<P: AsRef<Path>>(project_root: &Path, reason: &str, paths: &[P]) -> AppResult<BackupSummary> {
+    let created_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let root = backups_dir(project_root);
+    let mut backup_id = format!("{}-{}", created_at_ms, reason);
+    let mut suffix = 1;
+    while root.join(&backup_id).exists() {
+        suffix += 1;
+        backup_id = format!("{}-{}-{}", created_at_ms, reason, suffix);
+    }
+    let dir = root.join(&backup_id);
+    let secret_keys = runtime_env::secret_keys(project_root);
+
+    let mut files = Vec::new();
+    for path in paths {
+        let relative = path.as_ref();
+        let source = project_root.join(relative);
+        let existed = source.is_file();
+        if existed {
+            let target = dir.join("files").join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| AppError::io("Create backup dir", parent, e))?;
+            }
+            if is_env_file(relative) {
+                let content = fs::read_to_string(&source).map_err(|e| AppError::io("Back up file", &source, e))?;
+                fs::write(&target, without_secrets(&content, &secret_keys))
+                    .map_err(|e| AppError::io("Back up file", &target, e))?;
+            } else {
+                fs::copy(&source, &target).map_err(|e| AppError::io("Back up file", &source, e))?;
+            }
+        }
+        files.push(BackupFile {
+            path: relative.to_string_lossy().to_string(),
+            existed,
+        });
+    }
+
+    let summary = BackupSummary {
+        backup_id,
+        reason: reason.to_string(),
+        created_at_ms,
+        files,
+    };
+    fs::create_dir_all(&dir).map_err(|e| AppError::io("Create backup dir", &dir, e))?;
+    let manifest = dir.join(MANIFEST);
+    let payload = serde_json::to_string_pretty(&summary)
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Serialize backup manifest failed: {}", e)))?;
+    fs::write(&manifest, payload).map_err(|e| AppError::io("Write backup manifest", &manifest, e))?;
+    Ok(summary)
+}
+
+pub fn list_backups(project_root: &Path) -> AppResult<Vec<BackupSummary>> {
+    let dir = backups_dir(project_root);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let entries = fs::read_dir(&dir).map_err(|e| AppError::io("Read backups dir", &dir, e))?;
+    let mut backups = entries
+        .filter_map(Result::ok)
+        // Skip directories that are not backup sets instead of failing the whole listing.
+        .filter_map(|entry| read_manifest(&entry.path().join(MANIFEST)).ok())
+        .collect::<Vec<_>>();
+    backups.sort_by(|a, b| {
+        b.created_at_ms
+            .cmp(&a.created_at_ms)
+            .then(b.backup_id.cmp(&a.backup_id))
+    });
+    Ok(backups)
+}
+
+pub fn diff_backup(project_root: &Path, backup_id: &str) -> AppResult<Vec<FilePreview>> {
+    let (dir, summary) = load_backup(project_root, backup_id)?;
+    summary
+        .files
+        .iter()
+        .map(|file| {
+            let current = read_text(&project_root.join(&file.path))?;
+            let backed_up = if file.existed {
+                read_text(&dir.join("files").join(&file.path))?
+            } else {
+                None
+            };
+            let change = match (&current, &backed_up) {
+                (None, None) => FileChange::Unchanged,
+                (None, Some(_)) => FileChange::New,
+                (Some(_), None) => FileChange::Deleted,
+                (Some(current), Some(backed_up)) if current == backed_up => FileChange::Unchanged,
+                (Some(_), Some(_)) => FileChange::Changed,
+            };
+            let diff = match change {
+                FileChange::Unchanged => String::new(),
+                _ => generation::unified_diff(&file.path, current.as_deref(), backed_up.as_deref()),
+            };
+            Ok(FilePreview {
+                path: file.path.clone(),
+                change,
+                diff,
+            })
+        })
+        .collect()
+}
+
+/// The current files are backed up first, so a restore can be undone too.
+pub fn restore_backup(project_root: &Path, backup_id: &str) -> AppResult<BackupSummary> {
+    let (dir, summary) = load_backup(project_root, backup_id)?;
+    let paths = summary.files.iter().map(|file| file.path.as_str()).collect::<Vec<_>>();
+    let undo = snapshot(project_root, "restore", &paths)?;
+
+    for file in &summary.files {
+        let target = project_root.join(&file.path);
+        if file.existed {
+            let source = dir.join("files").join(&file.path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| AppError::io("Create directory", parent, e))?;
+            }
+            fs::copy(&source, &target).map_err(|e| AppError::io("Restore file", &target, e))?;
+        } else if target.exists() {
+            fs::remove_file(&target).map_err(|e| AppError::io("Remove file", &target, e))?;
+        }
+    }
+    Ok(undo)
+}
+
+fn load_backup(project_root: &Path, backup_id: &str) -> AppResult<(PathBuf, BackupSummary)> {
+    let valid = !backup_id.is_empty()
+        && backup_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid backupId: {}", backup_id),
+        ));
+    }
+    let dir = backups_dir(project_root).join(backup_id);
+    let manifest = dir.join(MANIFEST);
+    if !manifest.exists() {
+        return Err(
+            AppError::new(ErrorKind::BackupNotFound, format!("Backup not found: {}", backup_id)).with_path(&dir),
+        );
+    }
+    let summary = read_manifest(&manifest)?;
+    // The manifest is a plain file in the project; never follow it outside.
+    if let Some(file) = summary.files.iter().find(|file| !is_project_relative(&file.path)) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Backup {} lists a path outside the project: {}", backup_id, file.path),
+        )
+        .with_path(&manifest));
+    }
+    Ok((dir, summary))
+}
+
+fn read_manifest(path: &Path) -> AppResult<BackupSummary> {
+    let content = fs::read_to_string(path).map_err(|e| AppError::io("Read backup manifest", path, e))?;
+    serde_json::from_str::<BackupSummary>(&content).map_err(|e| AppError::parse("backup manifest", path, e))
+}
+
+fn read_text(path: &Path) -> AppResult<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let bytes = fs::read(path).map_err(|e| AppError::io("Read file", path, e))?;
+    Ok(Some(String::from_utf8_lossy(&bytes).to_string()))
+}
+
+fn is_env_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(".env"))
+}
+
+fn without_secrets(content: &str, secret_keys: &BTreeSet<String>) -> String {
+    let mut document = Document::parse(content);
+    for (key, value) in document.pairs() {
+        if !value.is_empty() && (secret_keys.contains(&key) || redact::is_secret_key(&key)) {
+            document.set(&key, "");
+        }
+    }
+    document.render()
+}
+
+fn is_project_relative(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    fn backed_up(root: &Path, backup: &BackupSummary, path: &str) -> String {
+        fs::read_to_string(backups_dir(root).join(&backup.backup_id).join("files").join(path)).unwrap()
+    }
+
+    #[test]
+    fn secret_env_values_are_blanked_in_the_copy() {
+        let env_file =
+            "SCHEME=MyApp\nPGYER_API_KEY=pgyer-key-123 # set by app\nDEPLOY_HOOK=https://hooks.example.com/abc\n";
+        let root = TestDir::with_files(&[
+            ("fastlane/.env.fastlane", env_file),
+            ("fastlane/Fastfile", "PGYER_API_KEY = \"kept\"\n"),
+            (
+                ".fastlane-desktop/runtime-env.json",
+                r#"{ "files": { "fastlane/.env.fastlane": { "DEPLOY_HOOK": "", "SCHEME": "0123456789abcdef" } } }"#,
+            ),
+        ]);
+
+        let backup = snapshot(&root, "test", &["fastlane/.env.fastlane", "fastlane/Fastfile"]).unwrap();
+
+        assert_eq!(
+            backed_up(&root, &backup, "fastlane/.env.fastlane"),
+            "SCHEME=MyApp\nPGYER_API_KEY= # set by app\nDEPLOY_HOOK=\n"
+        );
+        assert_eq!(
+            backed_up(&root, &backup, "fastlane/Fastfile"),
+            "PGYER_API_KEY = \"kept\"\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("fastlane/.env.fastlane")).unwrap(),
+            env_file
+        );
+    }
+
+    #[test]
+    fn snapshot_copies_existing_files_and_records_missing_ones() {
+        let root = TestDir::with_files(&[("fastlane/Fastfile", "lane :beta do\nend\n")]);
+
+        let backup = snapshot(&root, "generate", &["fastlane/Fastfile", "fastlane/Appfile"]).unwrap();
+        let again = snapshot(&root, "generate", &["fastlane/Fastfile"]).unwrap();
+
+        assert!(backup.backup_id.ends_with("-generate"), "{}", backup.backup_id);
+        assert_ne!(backup.backup_id, again.backup_id);
+        let files = backup
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.existed))
+            .collect::<Vec<_>>();
+        assert_eq!(files, [("fastlane/Fastfile", true), ("fastlane/Appfile", false)]);
+        assert_eq!(backed_up(&root, &backup, "fastlane/Fastfile"), "lane :beta do\nend\n");
+        assert!(!backups_dir(&root)
+            .join(&backup.backup_id)
+            .join("files/fastlane/Appfile")
+            .exists());
+
+        let listed = list_backups(&root).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.iter().any(|summary| summary.backup_id == backup.backup_id));
+    }
+
+    #[test]
+    fn restore_puts_files_back_and_can_be_undone() {
+        let root = TestDir::with_files(&[("fastlane/Fastfile", "old\n")]);
+        let backup = snapshot(&root, "generate", &["fastlane/Fastfile", "fastlane/Appfile"]).unwrap();
+        root.write("fastlane/Fastfile", "new\n");
+        root.write("fastlane/Appfile", "app_identifier \"com.example\"\n");
+
+        let changes = diff_backup(&root, &backup.backup_id).unwrap();
+        let changes = changes
+            .iter()
+            .map(|file| (file.path.as_str(), file.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                ("fastlane/Fastfile", FileChange::Changed),
+                ("fastlane/Appfile", FileChange::Deleted)
+            ]
+        );
+
+        let undo = restore_backup(&root, &backup.backup_id).unwrap();
+        assert_eq!(fs::read_to_string(root.join("fastlane/Fastfile")).unwrap(), "old\n");
+        assert!(!root.join("fastlane/Appfile").exists());
+
+        assert_eq!(undo.reason, "restore");
+        assert!(undo.files.iter().all(|file| file.existed));
+        assert_eq!(backed_up(&root, &undo, "fastlane/Fastfile"), "new\n");
+        restore_backup(&root, &undo.backup_id).unwrap();
+        assert_eq!(fs::read_to_string(root.join("fastlane/Fastfile")).unwrap(), "new\n");
+        assert_eq!(
+            fs::read_to_string(root.join("fastlane/Appfile")).unwrap(),
+            "app_identifier \"com.example\"\n"
+        );
+    }
+
+    #[test]
+    fn manifests_pointing_outside_the_project_are_rejected() {
+        let root = TestDir::with_files(&[]);
+        for (backup_id, path) in [("1-parent", "../outside.txt"), ("2-absolute", "/etc/hosts")] {
+            let manifest = format!(
+                r#"{{ "backupId": "{}", "reason": "test", "createdAtMs": 1, "files": [{{ "path": "{}", "existed": true }}] }}"#,
+                backup_id, path
+            );
+            root.write(
+                &format!(".fastlane-desktop/backups/{}/{}", backup_id, MANIFEST),
+                &manifest,
+            );
+
+            let error = restore_backup(&root, backup_id).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::InvalidInput);
+            assert!(error.message.ends_with(path), "{}", error.message);
+            assert_eq!(diff_backup(&root, backup_id).unwrap_err().kind, ErrorKind::InvalidInput);
+        }
+        assert_eq!(
+            restore_backup(&root, "../1-parent").unwrap_err().kind,
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            restore_backup(&root, "3-missing").unwrap_err().kind,
+            ErrorKind::BackupNotFound
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::app_settings;
+use crate::command_runner::{CommandRunner, CommandSpec};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::login_env::LoginEnv;
+
+/// Overrides the bootstrap script location when the app setting is empty.
+pub const SCRIPT_ENV: &str = "FASTLANE_DESKTOP_BOOTSTRAP_SCRIPT";
+const SKILL_SCRIPT: &str = "ios-fastlane-skill/scripts/bootstrap_fastlane.sh";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScriptSource {
+    Setting,
+    EnvVar,
+    CodexSkills,
+    Bundled,
+}
+
+impl ScriptSource {
+    fn label(self) -> &'static str {
+        match self {
+            ScriptSource::Setting => "app setting",
+            ScriptSource::EnvVar => SCRIPT_ENV,
+            ScriptSource::CodexSkills => "Codex skills",
+            ScriptSource::Bundled => "bundled resource",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BootstrapScript {
+    pub path: PathBuf,
+    source: ScriptSource,
+    version: Option<String>,
+    flags: BTreeSet<String>,
+}
+
+/// A location that is configured explicitly but missing is an error rather
+/// than a reason to keep looking.
+pub fn discover(app: &AppHandle, runner: &dyn CommandRunner, env: &LoginEnv) -> AppResult<BootstrapScript> {
+    let (path, source) = locate(app, env)?;
+    inspect(runner, env, path, source)
+}
+
+fn locate(app: &AppHandle, env: &LoginEnv) -> AppResult<(PathBuf, ScriptSource)> {
+    let configured = [
+        (app_settings::load(app)?.bootstrap_script_path, ScriptSource::Setting),
+        (std::env::var(SCRIPT_ENV).unwrap_or_default(), ScriptSource::EnvVar),
+    ];
+    for (value, source) in configured {
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        let path = PathBuf::from(value);
+        if !path.is_file() {
+            return Err(AppError::new(
+                ErrorKind::BootstrapScriptNotFound,
+                format!("Bootstrap script from {} not found: {}", source.label(), path.display()),
+            )
+            .with_hint("Point it at ios-fastlane-skill/scripts/bootstrap_fastlane.sh, or clear it to search the default locations.")
+            .with_path(&path));
+        }
+        return Ok((path, source));
+    }
+
+    let codex_home = env
+        .get("CODEX_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| app.path().home_dir().ok().map(|home| home.join(".codex")));
+    let candidates = [
+        codex_home.map(|dir| (dir.join("skills").join(SKILL_SCRIPT), ScriptSource::CodexSkills)),
+        app.path()
+            .resource_dir()
+            .ok()
+            .map(|dir| (dir.join(SKILL_SCRIPT), ScriptSource::Bundled)),
+    ];
+    let searched = candidates
+        .iter()
+        .flatten()
+        .map(|(path, _)| path.display().to_string())
+        .collect::<Vec<_>>();
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|(path, _)| path.is_file())
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorKind::BootstrapScriptNotFound,
+                format!("Skill bootstrap script not found. Searched: {}", searched.join(", ")),
+            )
+            .with_hint(format!(
+                "Install the ios-fastlane-skill, set the script in the Doctor panel or {}, or use the standard or dryRun mode, which need no script.",
+                SCRIPT_ENV
+            ))
+        })
+}
+
+fn inspect(
+    runner: &dyn CommandRunner,
+    env: &LoginEnv,
+    path: PathBuf,
+    source: ScriptSource,
+) -> AppResult<BootstrapScript> {
+    let run = |flag: &str| {
+        let mut cmd = env.command("bash");
+        cmd.arg(path.to_string_lossy()).arg(flag);
+        runner
+            .output(&cmd)
+            .map(|output| format!("{}\n{}", output.stdout, output.stderr))
+            .map_err(|e| AppError::spawn(cmd.display(), e).with_path(&path))
+    };
+
+    let help = run("--help")?;
+    let flags = parse_flags(&help);
+    if flags.is_empty() {
+        return Err(AppError::new(
+            ErrorKind::BootstrapScriptIncompatible,
+            format!("Bootstrap script does not list its flags in --help: {}", path.display()),
+        )
+        .with_hint("Update the ios-fastlane-skill to a version whose --help prints its options.")
+        .with_path(&path));
+    }
+    let version = if flags.contains("--version") {
+        run("--version")?
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string)
+    } else {
+        help.lines().find_map(parse_version_line)
+    };
+
+    Ok(BootstrapScript {
+        path,
+        source,
+        version,
+        flags,
+    })
+}
+
+impl BootstrapScript {
+    /// Fails when `cmd` passes a flag the script's `--help` does not list.
+    pub fn ensure_supports(&self, cmd: &CommandSpec) -> AppResult<()> {
+        let unsupported = cmd
+            .args
+            .iter()
+            .filter(|arg| arg.starts_with("--") && !self.flags.contains(arg.as_str()))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if unsupported.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::new(
+            ErrorKind::BootstrapScriptIncompatible,
+            format!(
+                "Bootstrap script {} does not support: {}",
+                self.version.as_deref().unwrap_or("(unknown version)"),
+                unsupported.join(", ")
+            ),
+        )
+        .with_hint("Update the ios-fastlane-skill, or use the standard or dryRun mode, which need no script.")
+        .with_path(&self.path)
+        .with_command(cmd.display()))
+    }
+
+    /// Where the script came from, for logs and the doctor report.
+    pub fn describe(&self) -> String {
+        format!(
+            "{} ({}, version {}, {} flags)",
+            self.path.display(),
+            self.source.label(),
+            self.version.as_deref().unwrap_or("unknown"),
+            self.flags.len()
+        )
+    }
+}
+
+fn parse_flags(help: &str) -> BTreeSet<String> {
+    help.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .filter_map(|token| token.strip_prefix("--"))
+        .filter(|name| name.chars().next().is_some_and(|c| c.is_ascii_alphabetic()))
+        .map(|name| format!("--{}", name.trim_end_matches('-')))
+        .collect()
+}
+
+/// `bootstrap_fastlane.sh v1.4.0` or `Version: 1.4.0` in the help header.
+fn parse_version_line(line: &str) -> Option<String> {
+    let lower = line.to_ascii_lowercase();
+    let tokens = lower.split(|c: char| c.is_whitespace() || c == ':' || c == '(' || c == ')');
+    let mut previous_was_version = false;
+    for token in tokens.filter(|token| !token.is_empty()) {
+        let candidate = token.strip_prefix('v').unwrap_or(token);
+        let looks_like_version = candidate.contains('.')
+            && candidate.chars().all(|c| c.is_ascii_digit() || c == '.')
+            && candidate.starts_with(|c: char| c.is_ascii_digit());
+        if looks_like_version && (previous_was_version || token.starts_with('v')) {
+            return Some(candidate.to_string());
+        }
+        previous_was_version = token == "version";
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELP: &str = "\
+bootstrap_fastlane.sh v1.4.0 - generate a fastlane setup for an iOS project
+
+Usage: bootstrap_fastlane.sh --project-name <name> [options]
+
+Options:
+  --project-name <name>     Xcode project name
+  --workspace=<path>        .xcworkspace to build
+  --scheme-dev <name>       Development scheme
+  --scheme-dis <name>       Distribution scheme (default: --scheme-dev)
+  --dry-run                 Print the files instead of writing them
+  --enable-match-           Trailing dash is a typo in older scripts
+  -h, --help                Show this help
+";
+
+    fn script(help: &str) -> BootstrapScript {
+        BootstrapScript {
+            path: PathBuf::from("/skills/ios-fastlane-skill/scripts/bootstrap_fastlane.sh"),
+            source: ScriptSource::Setting,
+            version: help.lines().find_map(parse_version_line),
+            flags: parse_flags(help),
+        }
+    }
+
+    #[test]
+    fn flags_are_read_from_help_text() {
+        let flags = parse_flags(HELP);
+        assert_eq!(
+            flags.into_iter().collect::<Vec<_>>(),
+            [
+                "--dry-run",
+                "--enable-match",
+                "--help",
+                "--project-name",
+                "--scheme-dev",
+                "--scheme-dis",
+                "--workspace"
+            ]
+        );
+        assert!(parse_flags("usage: script -h\n  --- \n  --1x\n").is_empty());
+    }
+
+    #[test]
+    fn version_comes_from_the_help_header() {
+        assert_eq!(
+            parse_version_line("bootstrap_fastlane.sh v1.4.0 - generate").as_deref(),
+            Some("1.4.0")
+        );
+        assert_eq!(parse_version_line("Version: 1.4.0").as_deref(), Some("1.4.0"));
+        assert_eq!(parse_version_line("(version 2.0.1)").as_deref(), Some("2.0.1"));
+        assert_eq!(parse_version_line("Requires Ruby 3.1.0 or newer"), None);
+        assert_eq!(parse_version_line("--verbose"), None);
+    }
+
+    #[test]
+    fn unlisted_flags_are_rejected() {
+        let script = script(HELP);
+        let mut cmd = CommandSpec::new("bash");
+        cmd.args([
+            script.path.to_string_lossy().as_ref(),
+            "--project-name",
+            "MyApp",
+            "--scheme-dev",
+            "MyApp Dev",
+        ]);
+        assert!(script.ensure_supports(&cmd).is_ok());
+
+        cmd.args([
+            "--match-git-url",
+            "git@example.com:certs.git",
+            "--xcodeproj",
+            "MyApp.xcodeproj",
+        ]);
+        let error = script.ensure_supports(&cmd).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::BootstrapScriptIncompatible);
+        assert_eq!(
+            error.message,
+            "Bootstrap script 1.4.0 does not support: --match-git-url, --xcodeproj"
+        );
+        assert!(script.describe().contains("app setting, version 1.4.0, 7 flags"));
+    }
+}
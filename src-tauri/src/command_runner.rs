@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::lane_run::LaneStream;
+use crate::redact;
+
+/// Directory of recorded command fixtures. When set, processes are replayed
+/// from it instead of being spawned.
+pub const FIXTURES_ENV: &str = "FASTLANE_DESKTOP_COMMAND_FIXTURES";
+/// `record` runs the real processes and writes their output to the fixture
+/// directory; anything else replays.
+pub const MODE_ENV: &str = "FASTLANE_DESKTOP_COMMAND_MODE";
+
+/// Arguments are passed to the program as-is; no shell is involved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub current_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Replaces the inherited environment (the captured login environment).
+    /// Not part of a fixture's identity, so recordings replay on other machines
+    /// (see `fixture_key`).
+    #[serde(skip)]
+    pub base_env: Option<Arc<BTreeMap<String, String>>>,
+}
+
+impl CommandSpec {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![],
+            current_dir: None,
+            env: BTreeMap::new(),
+            base_env: None,
+        }
+    }
+
+    pub fn arg(&mut self, arg: impl AsRef<str>) -> &mut Self {
+        self.args.push(arg.as_ref().to_string());
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_string()));
+        self
+    }
+
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.current_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn env(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn display(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn to_command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args);
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+        if let Some(base) = &self.base_env {
+            cmd.env_clear().envs(base.iter());
+        }
+        cmd.envs(&self.env);
+        cmd
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessOutput {
+    /// `-1` when the process was terminated by a signal.
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ProcessOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+/// Everything the backend spawns goes through this trait so scan, doctor and
+/// lane logic can run against recorded output.
+pub trait CommandRunner: Send + Sync {
+    fn output(&self, spec: &CommandSpec) -> io::Result<ProcessOutput>;
+
+    /// Runs `spec`, passing each output line to `on_line` as it arrives, and
+    /// returns the exit code. `on_spawn` receives the child's pid; the child
+    /// leads its own process group so the whole tree can be signalled.
+    fn stream(
+        &self,
+        spec: &CommandSpec,
+        on_spawn: &mut dyn FnMut(u32),
+        on_line: &mut dyn FnMut(LaneStream, String),
+    ) -> io::Result<i32>;
+}
+
+pub type SharedRunner = Arc<dyn CommandRunner>;
+
+pub fn runner_from_env() -> SharedRunner {
+    let Some(dir) = std::env::var_os(FIXTURES_ENV).map(PathBuf::from) else {
+        return Arc::new(SystemRunner);
+    };
+    match std::env::var(MODE_ENV).as_deref() {
+        Ok("record") => Arc::new(RecordingRunner { dir }),
+        _ => Arc::new(ReplayRunner::new(dir)),
+    }
+}
+
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, spec: &CommandSpec) -> io::Result<ProcessOutput> {
+        let output = spec.to_command().stdin(Stdio::null()).output()?;
+        Ok(ProcessOutput {
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+
+    fn stream(
+        &self,
+        spec: &CommandSpec,
+        on_spawn: &mut dyn FnMut(u32),
+        on_line: &mut dyn FnMut(LaneStream, String),
+    ) -> io::Result<i32> {
+        let mut cmd = spec.to_command();
+        cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        let mut child = cmd.spawn()?;
+        on_spawn(child.id());
+
+        let (tx, rx) = mpsc::channel();
+        let readers = [
+            spawn_line_reader(child.stdout.take(), LaneStream::Stdout, tx.clone()),
+            spawn_line_reader(child.stderr.take(), LaneStream::Stderr, tx),
+        ];
+        for (stream, line) in rx {
+            on_line(stream, line);
+        }
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+
+        Ok(child.wait()?.code().unwrap_or(-1))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Fixture {
+    command: CommandSpec,
+    output: ProcessOutput,
+}
+
+/// A command without a fixture fails as if the program were missing.
+pub struct ReplayRunner {
+    dir: PathBuf,
+}
+
+impl ReplayRunner {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn load(&self, spec: &CommandSpec) -> io::Result<ProcessOutput> {
+        let path = fixture_path(&self.dir, &fixture_key(spec));
+        let content = fs::read_to_string(&path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "no recorded output for `{}` at {}: {}",
+                    spec.display(),
+                    path.display(),
+                    e
+                ),
+            )
+        })?;
+        let fixture = serde_json::from_str::<Fixture>(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+        Ok(fixture.output)
+    }
+}
+
+impl CommandRunner for ReplayRunner {
+    fn output(&self, spec: &CommandSpec) -> io::Result<ProcessOutput> {
+        self.load(spec)
+    }
+
+    fn stream(
+        &self,
+        spec: &CommandSpec,
+        _on_spawn: &mut dyn FnMut(u32),
+        on_line: &mut dyn FnMut(LaneStream, String),
+    ) -> io::Result<i32> {
+        // Interleaving is not recorded; stdout is replayed before stderr.
+        let output = self.load(spec)?;
+        for line in output.stdout.lines() {
+            on_line(LaneStream::Stdout, line.to_string());
+        }
+        for line in output.stderr.lines() {
+            on_line(LaneStream::Stderr, line.to_string());
+        }
+        Ok(output.exit_code)
+    }
+}
+
+pub struct RecordingRunner {
+    dir: PathBuf,
+}
+
+impl RecordingRunner {
+    fn save(&self, spec: &CommandSpec, output: &ProcessOutput) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let fixture = Fixture {
+            command: fixture_key(spec),
+            output: output.clone(),
+        };
+        let payload = serde_json::to_string_pretty(&fixture).map_err(io::Error::other)?;
+        fs::write(fixture_path(&self.dir, &fixture.command), payload)
+    }
+}
+
+impl CommandRunner for RecordingRunner {
+    fn output(&self, spec: &CommandSpec) -> io::Result<ProcessOutput> {
+        let output = SystemRunner.output(spec)?;
+        self.save(spec, &output)?;
+        Ok(output)
+    }
+
+    fn stream(
+        &self,
+        spec: &CommandSpec,
+        on_spawn: &mut dyn FnMut(u32),
+        on_line: &mut dyn FnMut(LaneStream, String),
+    ) -> io::Result<i32> {
+        let mut output = ProcessOutput::default();
+        output.exit_code = SystemRunner.stream(spec, on_spawn, &mut |stream, line| {
+            let buffer = match stream {
+                LaneStream::Stderr => &mut output.stderr,
+                _ => &mut output.stdout,
+            };
+            buffer.push_str(&line);
+            buffer.push('\n');
+            on_line(stream, line);
+        })?;
+        self.save(spec, &output)?;
+        Ok(output.exit_code)
+    }
+}
+
+fn fixture_path(dir: &Path, key: &CommandSpec) -> PathBuf {
+    let key = serde_json::to_vec(key).unwrap_or_default();
+    let digest = format!("{:x}", Sha256::digest(&key));
+    dir.join(format!("{}.json", &digest[..16]))
+}
+
+/// `spec` without what differs between machines: the program is reduced to
+/// its file name, the working directory becomes `$PROJECT` and the home
+/// directory `$HOME` wherever they appear in arguments and variables, the
+/// login PATH a variable extends becomes `$PATH`, and values of secret-looking
+/// variables are masked.
+fn fixture_key(spec: &CommandSpec) -> CommandSpec {
+    let base_var = |key: &str| {
+        spec.base_env
+            .as_ref()
+            .and_then(|base| base.get(key))
+            .filter(|value| !value.is_empty())
+            .cloned()
+    };
+    let project = spec.current_dir.as_ref().map(|dir| dir.to_string_lossy().to_string());
+    let home = base_var("HOME").or_else(|| spec.env.get("HOME").cloned());
+    let login_path = base_var("PATH");
+    let normalize = |value: &str| {
+        let mut value = value.to_string();
+        if let Some(project) = project.as_deref().filter(|project| !project.is_empty()) {
+            value = value.replace(project, "$PROJECT");
+        }
+        if let Some(home) = home.as_deref() {
+            value = value.replace(home, "$HOME");
+        }
+        value
+    };
+    let program = Path::new(&spec.program)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| spec.program.clone());
+    let env = spec
+        .env
+        .iter()
+        .map(|(key, value)| {
+            let value = match &login_path {
+                _ if redact::is_secret_key(key) => redact::MASK.to_string(),
+                Some(path) if value.ends_with(path.as_str()) => {
+                    format!("{}$PATH", normalize(&value[..value.len() - path.len()]))
+                }
+                _ => normalize(value),
+            };
+            (key.clone(), value)
+        })
+        .collect();
+    CommandSpec {
+        program,
+        args: spec.args.iter().map(|arg| normalize(arg)).collect(),
+        current_dir: project.map(|_| PathBuf::from("$PROJECT")),
+        env,
+        base_env: None,
+    }
+}
+
+fn spawn_line_reader<R: Read + Send + 'static>(
+    reader: Option<R>,
+    stream: LaneStream,
+    tx: Sender<(LaneStream, String)>,
+) -> Option<JoinHandle<()>> {
+    let reader = reader?;
+    Some(thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buf);
+                    let line = line.trim_end_matches(['\n', '\r']).to_string();
+                    if tx.send((stream, line)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login_env(home: &str) -> Arc<BTreeMap<String, String>> {
+        Arc::new(BTreeMap::from([
+            ("HOME".to_string(), home.to_string()),
+            ("PATH".to_string(), format!("{}/.rbenv/bin:/usr/bin:/bin", home)),
+        ]))
+    }
+
+    fn bundle_install(home: &str, project: &str) -> CommandSpec {
+        let base_env = login_env(home);
+        let mut cmd = CommandSpec::new(format!("{}/.rbenv/shims/bundle", home));
+        cmd.args(["install", "--gemfile", &format!("{}/Gemfile", project)])
+            .current_dir(project)
+            .env("PATH", format!("{}/.rbenv/shims:{}", home, base_env["PATH"]))
+            .env("MATCH_PASSWORD", format!("secret-of-{}", home));
+        cmd.base_env = Some(base_env);
+        cmd
+    }
+
+    #[test]
+    fn fixture_keys_do_not_depend_on_home_or_project_location() {
+        let a = bundle_install("/Users/alice", "/Users/alice/dev/App");
+        let b = bundle_install("/home/bob", "/tmp/checkout/App");
+        assert_eq!(fixture_key(&a), fixture_key(&b));
+
+        let key = fixture_key(&a);
+        assert_eq!(key.program, "bundle");
+        assert_eq!(key.args, ["install", "--gemfile", "$PROJECT/Gemfile"]);
+        assert_eq!(key.current_dir, Some(PathBuf::from("$PROJECT")));
+        assert_eq!(key.env["PATH"], "$HOME/.rbenv/shims:$PATH");
+        assert_eq!(key.env["MATCH_PASSWORD"], redact::MASK);
+    }
+
+    #[test]
+    fn fixture_keys_still_tell_different_commands_apart() {
+        let install = bundle_install("/Users/alice", "/Users/alice/dev/App");
+        let mut update = install.clone();
+        update.args[0] = "update".to_string();
+        assert_ne!(
+            fixture_path(Path::new("fixtures"), &fixture_key(&install)),
+            fixture_path(Path::new("fixtures"), &fixture_key(&update))
+        );
+    }
+}
@@ -1,11 +1,44 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use walkdir::WalkDir;
-
-#[derive(Debug, Serialize, Deserialize)]
+use tauri::{AppHandle, Manager, State};
+
+use crate::app_settings::{self, AppSettings};
+use crate::asc_api_key::{self, AscToken};
+use crate::backups::{self, BackupSummary};
+use crate::bootstrap_script;
+use crate::command_runner::{CommandRunner, CommandSpec, SharedRunner};
+use crate::dotenv;
+use crate::environments::{self, EffectiveEnv, LaneEnvironment};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::fastfile_constants::{self, FastfileConstant};
+use crate::fastfile_lanes::{self, FastfileLanes, Lane};
+use crate::generation::{self, FilePreview, GenerationPreview, GenerationPreviews};
+use crate::lane_run::{CommandExit, LaneRun, RunningLanes};
+use crate::login_env::{LoginEnv, LoginEnvCache, LoginEnvSummary};
+use crate::mobileprovision::{self, ProvisioningProfile};
+use crate::pbxproj::{self, PbxProject};
+use crate::redact::{self, Redactor};
+use crate::ruby_env::{self, RubyResolution};
+use crate::run_history::{self, RunRecord, RunStart, RunSummary};
+use crate::runtime_env::{self, EnvConflict, ManagedFile, RuntimeEnv};
+use crate::secret_store::{self, SecretInfo, SecretList, SecretRef, SecretStore};
+use crate::templates::{self, RenderedFile, TemplateInput};
+use crate::xcconfig::{self, ResolvedSetting};
+use crate::xcscheme::{self, Scheme};
+use crate::xcworkspace::{self, ContainerCandidate};
+
+const RUNTIME_ENV_PATH: &str = "fastlane/.env.fastlane";
+
+const APP_LANE_ENV: [(&str, &str); 3] = [
+    ("FASTLANE_SKIP_UPDATE_CHECK", "1"),
+    ("FASTLANE_DISABLE_COLORS", "1"),
+    ("CI", "1"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct ProjectConfig {
@@ -42,6 +75,32 @@ pub struct ProjectConfig {
     pub ci_cocoapods_deployment: bool,
     pub bootstrap_mode: String,
     pub bootstrap_config_path: String,
+    pub environments: Vec<LaneEnvironment>,
+    /// Variables whose values come from the secret store instead of the fields above.
+    pub secret_refs: Vec<SecretRef>,
+}
+
+impl ProjectConfig {
+    pub fn is_secret(&self, key: &str) -> bool {
+        redact::is_secret_key(key) || self.secret_refs.iter().any(|reference| reference.key == key)
+    }
+
+    /// Never saved to the profile: the match git URL may carry a token.
+    pub fn without_secret_values(mut self) -> Self {
+        self.pgyer_api_key.clear();
+        self.match_git_url.clear();
+        let mut environments = std::mem::take(&mut self.environments);
+        for value in environments
+            .iter_mut()
+            .flat_map(|environment| environment.values.iter_mut())
+        {
+            if self.is_secret(&value.key) {
+                value.value.clear();
+            }
+        }
+        self.environments = environments;
+        self
+    }
 }
 
 impl Default for ProjectConfig {
@@ -80,6 +139,8 @@ impl Default for ProjectConfig {
             ci_cocoapods_deployment: true,
             bootstrap_mode: "standard".to_string(),
             bootstrap_config_path: String::new(),
+            environments: Vec::new(),
+            secret_refs: Vec::new(),
         }
     }
 }
@@ -88,20 +149,50 @@ impl Default for ProjectConfig {
 #[serde(rename_all = "camelCase")]
 pub struct ScanResult {
     pub project_name: String,
-    pub workspace: Option<String>,
-    pub xcodeproj: Option<String>,
+    pub containers: Vec<ContainerCandidate>,
     pub schemes: Vec<String>,
+    pub scheme_details: Vec<Scheme>,
     pub bundle_id_dev: Option<String>,
     pub bundle_id_dis: Option<String>,
     pub team_id: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FastfileConstantsUpdate {
+    pub fastfile_path: String,
+    pub updated: Vec<String>,
+    /// Constants the Fastfile does not assign with a literal value.
+    pub missing: Vec<String>,
+    pub backup_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum LaneOptionValue {
+    Bool(bool),
+    Number(serde_json::Number),
+    Text(String),
+}
+
+impl LaneOptionValue {
+    fn to_arg_value(&self) -> String {
+        match self {
+            LaneOptionValue::Bool(value) => value.to_string(),
+            LaneOptionValue::Number(value) => value.to_string(),
+            LaneOptionValue::Text(value) => value.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LaneRunResult {
+    pub run_id: String,
     pub status: String,
     pub exit_code: i32,
     pub output: String,
+    pub redactions: usize,
     pub lane: String,
 }
 
@@ -120,9 +211,11 @@ pub struct GenerateResult {
     pub mode: String,
     pub runtime_env_path: Option<String>,
     pub runtime_env_written: bool,
+    pub env_conflicts: Vec<EnvConflict>,
     pub files: Vec<GeneratedFileStatus>,
     pub stdout: String,
     pub stderr: String,
+    pub redactions: usize,
 }
 
 #[derive(Debug, Serialize)]
@@ -131,6 +224,17 @@ pub struct IdentityResult {
     pub bundle_id_dev: Option<String>,
     pub bundle_id_dis: Option<String>,
     pub team_id: Option<String>,
+    pub sources: Vec<IdentitySource>,
+}
+
+/// `setting` is `None` when the value had to be read from `xcodebuild -showBuildSettings`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentitySource {
+    pub scheme: String,
+    pub key: String,
+    pub value: String,
+    pub setting: Option<ResolvedSetting>,
 }
 
 #[derive(Debug, Serialize)]
@@ -149,72 +253,125 @@ pub struct DoctorReport {
 }
 
 #[tauri::command]
-pub fn doctor_check(project_path: Option<String>) -> Result<DoctorReport, String> {
+pub fn doctor_check(
+    app: AppHandle,
+    project_path: Option<String>,
+    app_store_connect_api_key_path: Option<String>,
+) -> AppResult<DoctorReport> {
     let root = project_path
         .filter(|p| !p.trim().is_empty())
         .map(PathBuf::from)
         .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
 
-    let checks = vec![
-        check_cmd("Xcode CLI", "/bin/zsh", &["-lc", "xcode-select -p"], None),
-        check_cmd("Xcode Build", "/bin/zsh", &["-lc", "xcodebuild -version"], None),
-        check_cmd("Ruby", "/bin/zsh", &["-lc", "ruby -v"], Some("Install Ruby and ensure it is in PATH.")),
+    let (runner, env) = process_tools(&app);
+    let mut checks = tool_checks(runner.as_ref(), &env, &root);
+    checks.push(check_bootstrap_script(&app, runner.as_ref(), &env));
+    if let Some(key_path) = app_store_connect_api_key_path.filter(|p| !p.trim().is_empty()) {
+        let key_path = to_absolute_from_project(&root, &key_path);
+        checks.extend(asc_api_key::doctor_checks(Path::new(&key_path)));
+    }
+
+    Ok(DoctorReport { checks })
+}
+
+fn tool_checks(runner: &dyn CommandRunner, env: &LoginEnv, root: &Path) -> Vec<DoctorCheck> {
+    let ruby = ruby_env::resolve(env, root);
+    let ruby_command = |program: &str| {
+        let mut cmd = ruby.command(env, program);
+        cmd.current_dir(root);
+        cmd
+    };
+    vec![
+        check_cmd(runner, "Xcode CLI", env.command("xcode-select").arg("-p"), None),
+        check_cmd(runner, "Xcode Build", env.command("xcodebuild").arg("-version"), None),
+        check_cmd(runner, "Ruby", ruby_command("ruby").arg("-v"), Some("Install Ruby and ensure it is in PATH.")),
+        ruby.doctor_check(),
         check_cmd(
+            runner,
             "Ruby Compatibility",
-            "/bin/zsh",
-            &[
-                "-lc",
-                "ruby -e 'v=RUBY_VERSION.split(\".\").map(&:to_i); abort(\"Ruby >= 4.0 is not supported by this fastlane setup\") if v[0] >= 4; puts \"Ruby #{RUBY_VERSION} compatible\"'",
-            ],
-            Some("Use Ruby 3.1~3.3 via rbenv/rvm/asdf, then run with `bundle exec fastlane ...`."),
+            ruby_command("ruby").args([
+                "-e",
+                "v=RUBY_VERSION.split(\".\").map(&:to_i); abort(\"Ruby >= 4.0 is not supported by this fastlane setup\") if v[0] >= 4; puts \"Ruby #{RUBY_VERSION} compatible\"",
+            ]),
+            Some("Use Ruby 3.1~3.3 via rbenv/rvm/asdf/mise/chruby/frum, then run with `bundle exec fastlane ...`."),
         ),
         check_cmd(
+            runner,
             "Bundler",
-            "/bin/zsh",
-            &["-lc", "bundle -v"],
+            ruby_command("bundle").arg("-v"),
             Some("Run `gem install bundler` or ensure Bundler is available."),
         ),
         check_cmd(
+            runner,
             "Fastlane",
-            "/bin/zsh",
-            &["-lc", "fastlane --version"],
+            env.command("fastlane").arg("--version"),
             Some("Run `bundle install` or install fastlane."),
         ),
         check_cmd(
+            runner,
             "CocoaPods",
-            "/bin/zsh",
-            &["-lc", "pod --version"],
+            env.command("pod").arg("--version"),
             Some("Install CocoaPods if your project depends on Pods."),
         ),
-        check_cmd(
-            "Gemfile",
-            "/bin/zsh",
-            &["-lc", &format!("cd '{}' && test -f Gemfile && echo ok", escape_single_quote(&root.to_string_lossy()))],
-            Some("Create Gemfile to manage fastlane gems consistently."),
-        ),
-    ];
+        check_gemfile(root),
+    ]
+}
 
-    Ok(DoctorReport { checks })
+#[tauri::command]
+pub fn app_store_connect_token(project_path: String, key_path: String) -> AppResult<AscToken> {
+    let root = normalize_project_root(&PathBuf::from(&project_path));
+    let key_path = to_absolute_from_project(&root, &key_path);
+    if key_path.is_empty() {
+        return Err(
+            AppError::new(ErrorKind::InvalidInput, "appStoreConnectApiKeyPath is empty")
+                .with_hint("Set the App Store Connect API key JSON path first."),
+        );
+    }
+    let invalid_key = |err: String| {
+        AppError::new(ErrorKind::InvalidApiKey, err)
+            .with_hint("Run Doctor to see every problem with the key file.")
+            .with_path(&key_path)
+    };
+    asc_api_key::load_api_key(Path::new(&key_path))
+        .map_err(invalid_key)?
+        .token()
+        .map_err(invalid_key)
 }
 
 #[tauri::command]
-pub fn scan_project(project_path: String) -> Result<ScanResult, String> {
-    let input_path = PathBuf::from(project_path.clone());
+pub fn scan_project(app: AppHandle, project_path: String) -> AppResult<ScanResult> {
+    let (runner, env) = process_tools(&app);
+    scan(runner.as_ref(), &env, &PathBuf::from(project_path))
+}
+
+fn scan(runner: &dyn CommandRunner, env: &LoginEnv, input_path: &Path) -> AppResult<ScanResult> {
     if !input_path.exists() {
-        return Err(format!("Project path not found: {}", project_path));
+        return Err(AppError::project_not_found(input_path));
     }
-    let root = normalize_project_root(&input_path);
-    let (container_workspace, container_xcodeproj) = container_hint_from_input_path(&input_path);
+    let root = normalize_project_root(input_path);
 
-    let workspace = container_workspace.or_else(|| find_first_with_ext(&root, "xcworkspace"));
-    let xcodeproj = container_xcodeproj.or_else(|| find_first_with_ext(&root, "xcodeproj"));
-
-    let schemes = match parse_schemes_from_xcodebuild(&root, workspace.as_deref(), xcodeproj.as_deref()) {
-        Ok(list) if !list.is_empty() => list,
-        _ => vec![],
-    };
+    let mut containers = ranked_containers(&root, input_path);
+    for candidate in &mut containers {
+        candidate.schemes = container_scheme_names(&root, candidate.workspace(), candidate.primary_project());
+    }
+    let (workspace, xcodeproj) = selected_container(&containers);
+
+    let scheme_details =
+        discover_container_schemes(&scheme_containers(&root, workspace.as_deref(), xcodeproj.as_deref()));
+    let mut schemes = containers
+        .first()
+        .map(|candidate| candidate.schemes.clone())
+        .unwrap_or_default();
+    if schemes.is_empty() {
+        schemes = match parse_schemes_from_xcodebuild(runner, env, &root, workspace.as_deref(), xcodeproj.as_deref()) {
+            Ok(list) if !list.is_empty() => list,
+            _ => vec![],
+        };
+    }
     let (scheme_dev, scheme_dis) = pick_dev_dis_schemes(&schemes);
     let identity = resolve_identity_internal(
+        runner,
+        env,
         &root,
         workspace.as_deref(),
         xcodeproj.as_deref(),
@@ -229,9 +386,9 @@ pub fn scan_project(project_path: String) -> Result<ScanResult, String> {
 
     Ok(ScanResult {
         project_name,
-        workspace,
-        xcodeproj,
+        containers,
         schemes,
+        scheme_details,
         bundle_id_dev: identity.bundle_id_dev,
         bundle_id_dis: identity.bundle_id_dis,
         team_id: identity.team_id,
@@ -240,29 +397,27 @@ pub fn scan_project(project_path: String) -> Result<ScanResult, String> {
 
 #[tauri::command]
 pub fn resolve_identity(
+    app: AppHandle,
     project_path: String,
     workspace: Option<String>,
     xcodeproj: Option<String>,
     scheme_dev: String,
     scheme_dis: String,
-) -> Result<IdentityResult, String> {
+) -> AppResult<IdentityResult> {
     let input_path = PathBuf::from(&project_path);
     if !input_path.exists() {
-        return Err(format!("Project path not found: {}", project_path));
+        return Err(AppError::project_not_found(&input_path));
     }
     let root = normalize_project_root(&input_path);
-    let (container_workspace, container_xcodeproj) = container_hint_from_input_path(&input_path);
+    let (detected_workspace, detected_xcodeproj) = selected_container(&ranked_containers(&root, &input_path));
 
-    let resolved_workspace = workspace
-        .filter(|v| !v.trim().is_empty())
-        .or(container_workspace)
-        .or_else(|| find_first_with_ext(&root, "xcworkspace"));
-    let resolved_xcodeproj = xcodeproj
-        .filter(|v| !v.trim().is_empty())
-        .or(container_xcodeproj)
-        .or_else(|| find_first_with_ext(&root, "xcodeproj"));
+    let resolved_workspace = workspace.filter(|v| !v.trim().is_empty()).or(detected_workspace);
+    let resolved_xcodeproj = xcodeproj.filter(|v| !v.trim().is_empty()).or(detected_xcodeproj);
 
+    let (runner, env) = process_tools(&app);
     Ok(resolve_identity_internal(
+        runner.as_ref(),
+        &env,
         &root,
         resolved_workspace.as_deref(),
         resolved_xcodeproj.as_deref(),
@@ -272,96 +427,78 @@ pub fn resolve_identity(
 }
 
 #[tauri::command]
-pub fn save_profile(config: ProjectConfig) -> Result<String, String> {
+pub fn save_profile(config: ProjectConfig) -> AppResult<String> {
     let project_root = normalize_project_root(&PathBuf::from(&config.project_path));
     if !project_root.exists() {
-        return Err(format!("projectPath does not exist: {}", config.project_path));
+        return Err(AppError::project_not_found(&config.project_path));
     }
 
     let profile_dir = project_root.join(".fastlane-desktop");
-    fs::create_dir_all(&profile_dir).map_err(|e| format!("Create profile dir failed: {}", e))?;
+    fs::create_dir_all(&profile_dir).map_err(|e| AppError::io("Create profile dir", &profile_dir, e))?;
     let profile_path = profile_dir.join("profile.json");
-    let payload = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Serialize profile failed: {}", e))?;
-    fs::write(&profile_path, payload).map_err(|e| format!("Write profile failed: {}", e))?;
+    let payload = serde_json::to_string_pretty(&config.without_secret_values())
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Serialize profile failed: {}", e)))?;
+    fs::write(&profile_path, payload).map_err(|e| AppError::io("Write profile", &profile_path, e))?;
 
     Ok(format!("Profile saved: {}", profile_path.display()))
 }
 
 #[tauri::command]
-pub fn load_profile(project_path: String) -> Result<ProjectConfig, String> {
+pub fn load_profile(project_path: String) -> AppResult<ProjectConfig> {
     let root = normalize_project_root(&PathBuf::from(&project_path));
     let profile_path = root.join(".fastlane-desktop").join("profile.json");
     if !profile_path.exists() {
-        return Err(format!("Profile not found: {}", profile_path.display()));
+        return Err(AppError::new(
+            ErrorKind::ProfileNotFound,
+            format!("Profile not found: {}", profile_path.display()),
+        )
+        .with_hint("Save a profile for this project first.")
+        .with_path(&profile_path));
     }
 
-    let content = fs::read_to_string(&profile_path)
-        .map_err(|e| format!("Read profile failed: {}", e))?;
-    serde_json::from_str::<ProjectConfig>(&content)
-        .map_err(|e| format!("Parse profile failed: {}", e))
+    let content = fs::read_to_string(&profile_path).map_err(|e| AppError::io("Read profile", &profile_path, e))?;
+    serde_json::from_str::<ProjectConfig>(&content).map_err(|e| AppError::parse("profile", &profile_path, e))
 }
 
 #[tauri::command]
-pub fn generate_fastlane_files(config: ProjectConfig) -> Result<GenerateResult, String> {
-    let input_path = PathBuf::from(&config.project_path);
-    let project_root = normalize_project_root(&input_path);
-    if !project_root.exists() {
-        return Err(format!("projectPath does not exist: {}", config.project_path));
-    }
-    let (container_workspace, container_xcodeproj) = container_hint_from_input_path(&input_path);
-
-    let skill_script = PathBuf::from("/Users/newdroid/.codex/skills/ios-fastlane-skill/scripts/bootstrap_fastlane.sh");
-    if !skill_script.exists() {
-        return Err(format!("Skill bootstrap script not found: {}", skill_script.display()));
-    }
-
-    let project_name = project_root
-        .file_name()
-        .and_then(OsStr::to_str)
-        .unwrap_or("iOSProject")
-        .to_string();
-    let resolved_workspace = if !config.workspace.trim().is_empty() {
-        to_absolute_from_project(&project_root, config.workspace.trim())
-    } else if let Some(ws) = container_workspace {
-        to_absolute_from_project(&project_root, &ws)
-    } else {
-        to_absolute_from_project(
-            &project_root,
-            &find_first_with_ext(&project_root, "xcworkspace").unwrap_or_default(),
-        )
-    };
-    let resolved_xcodeproj = if !config.xcodeproj.trim().is_empty() {
-        to_absolute_from_project(&project_root, config.xcodeproj.trim())
-    } else if let Some(proj) = container_xcodeproj {
-        to_absolute_from_project(&project_root, &proj)
-    } else {
-        to_absolute_from_project(
-            &project_root,
-            &find_first_with_ext(&project_root, "xcodeproj").unwrap_or_default(),
-        )
-    };
-
-    if resolved_workspace.is_empty() && resolved_xcodeproj.is_empty() {
-        return Err(
-            "No .xcworkspace/.xcodeproj found under projectPath. Please set Workspace or Xcodeproj manually."
-                .to_string(),
-        );
+pub fn generate_fastlane_files(app: AppHandle, config: ProjectConfig) -> AppResult<GenerateResult> {
+    let target = resolve_generation_target(&config)?;
+    let mode = normalize_bootstrap_mode(&config.bootstrap_mode)?;
+    let project_root = target.project_root.clone();
+    let secrets = resolve_secret_refs(&app, &config)?;
+
+    let (runner, env) = process_tools(&app);
+    if mode == "standard" || mode == "dryRun" {
+        let (rendered, mut env_conflicts) = render_generation(&config, &target, &secrets)?;
+        let mut redactor = output_redactor(&env, &project_root, Some(&config), &secrets);
+        redact_conflicts(&mut redactor, Some(&config), &mut env_conflicts);
+        if mode == "dryRun" {
+            return Ok(dry_run_result(&project_root, rendered, env_conflicts));
+        }
+        let written = generation::write(&project_root, "generate", rendered)?;
+        return Ok(GenerateResult {
+            env_conflicts,
+            ..template_generate_result(&project_root, mode, &written.paths)
+        });
     }
 
-    let mut cmd = Command::new("bash");
-    cmd.arg(skill_script);
+    // configFile and interactive modes are handled by the external skill script.
+    let skill_script = bootstrap_script::discover(&app, runner.as_ref(), &env)?;
+    let mut cmd = env.command("bash");
+    cmd.arg(skill_script.path.to_string_lossy());
     cmd.current_dir(&project_root);
-    cmd.arg("--project-name").arg(project_name);
-    let mode = normalize_bootstrap_mode(&config.bootstrap_mode)?;
+    cmd.arg("--project-name").arg(&target.project_name);
     if mode == "configFile" {
         if config.bootstrap_config_path.trim().is_empty() {
-            return Err("bootstrapConfigPath is required when bootstrapMode=configFile".to_string());
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                "bootstrapConfigPath is required when bootstrapMode=configFile",
+            ));
         }
         cmd.arg("--config").arg(config.bootstrap_config_path.trim());
     }
-    push_optional_arg(&mut cmd, "--workspace", &resolved_workspace);
-    push_optional_arg(&mut cmd, "--xcodeproj", &resolved_xcodeproj);
+    push_optional_arg(&mut cmd, "--workspace", &target.workspace);
+    push_optional_arg(&mut cmd, "--xcodeproj", &target.xcodeproj);
     push_optional_arg(&mut cmd, "--scheme-dev", &config.scheme_dev);
     push_optional_arg(&mut cmd, "--scheme-dis", &config.scheme_dis);
     push_optional_arg(&mut cmd, "--bundle-id-dev", &config.bundle_id_dev);
@@ -374,16 +511,14 @@ pub fn generate_fastlane_files(config: ProjectConfig) -> Result<GenerateResult,
     push_optional_arg(&mut cmd, "--match-git-branch", &config.match_git_branch);
     cmd.arg("--enable-quality-gate")
         .arg(bool_to_string(config.enable_quality_gate));
-    cmd.arg("--enable-tests")
-        .arg(bool_to_string(config.enable_tests));
+    cmd.arg("--enable-tests").arg(bool_to_string(config.enable_tests));
     cmd.arg("--enable-swiftlint")
         .arg(bool_to_string(config.enable_swiftlint));
     cmd.arg("--enable-slack-notify")
         .arg(bool_to_string(config.enable_slack_notify));
     cmd.arg("--enable-wechat-notify")
         .arg(bool_to_string(config.enable_wechat_notify));
-    cmd.arg("--enable-snapshot")
-        .arg(bool_to_string(config.enable_snapshot));
+    cmd.arg("--enable-snapshot").arg(bool_to_string(config.enable_snapshot));
     push_optional_arg(&mut cmd, "--snapshot-scheme", &config.snapshot_scheme);
     push_optional_arg(&mut cmd, "--snapshot-devices", &config.snapshot_devices);
     push_optional_arg(&mut cmd, "--snapshot-languages", &config.snapshot_languages);
@@ -392,47 +527,57 @@ pub fn generate_fastlane_files(config: ProjectConfig) -> Result<GenerateResult,
         .arg(bool_to_string(config.enable_metadata_upload));
     cmd.arg("--enable-screenshot-upload")
         .arg(bool_to_string(config.enable_screenshot_upload));
-    cmd.arg("--gym-skip-clean")
-        .arg(bool_to_string(config.gym_skip_clean));
+    cmd.arg("--gym-skip-clean").arg(bool_to_string(config.gym_skip_clean));
     push_optional_arg(&mut cmd, "--derived-data-path", &config.derived_data_path);
     cmd.arg("--ci-bundle-install")
         .arg(bool_to_string(config.ci_bundle_install));
     cmd.arg("--ci-cocoapods-deployment")
         .arg(bool_to_string(config.ci_cocoapods_deployment));
-    if mode == "dryRun" {
-        cmd.arg("--dry-run");
-    }
     if mode == "interactive" {
         cmd.arg("--interactive");
     }
+    skill_script.ensure_supports(&cmd)?;
+    let mut runtime_env = runtime_env_files(&project_root, &config, &secrets)?;
+    runtime_env.files.push(gitignore_file(&project_root)?);
+    // The script writes on its own, so back up everything it may touch first.
+    let script_outputs = templates::output_paths()
+        .map(PathBuf::from)
+        .chain(runtime_env.files.iter().map(|file| file.path.clone()))
+        .collect::<Vec<_>>();
+    let backup = backups::snapshot(&project_root, "bootstrap", &script_outputs)?;
+
+    let output = runner
+        .output(&cmd)
+        .map_err(|e| AppError::spawn("skill bootstrap", e).with_path(&skill_script.path))?;
+    let mut redactor = output_redactor(&env, &project_root, Some(&config), &secrets);
+    let stdout = format!(
+        "[bootstrap] script={}\n[bootstrap] backup={}\n{}",
+        skill_script.describe(),
+        backup.backup_id,
+        redactor.text(&output.stdout)
+    );
+    let stderr = redactor.text(&output.stderr);
 
-    let output = cmd
-        .output()
-        .map_err(|e| format!("Run skill bootstrap failed: {}", e))?;
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-
-    if !output.status.success() {
+    if !output.success() {
         return Ok(GenerateResult {
             status: "failed".to_string(),
             mode: mode.to_string(),
             runtime_env_path: None,
             runtime_env_written: false,
+            env_conflicts: vec![],
             files: expected_generated_files(&project_root, &[]),
-            stdout: stdout.to_string(),
-            stderr: stderr.to_string(),
+            stdout,
+            stderr,
+            redactions: redactor.count(),
         });
     }
 
-    let runtime_env_path = project_root.join("fastlane").join(".env.fastlane");
-    let mut runtime_env_written = false;
-    if mode != "dryRun" {
-        fs::write(&runtime_env_path, render_runtime_env(&config))
-            .map_err(|e| format!("Write runtime env failed: {}", e))?;
-        ensure_fastlane_plugin_gemfile(&project_root)?;
-        patch_generated_doctor_script_for_bash3(&project_root)?;
-        runtime_env_written = true;
-    }
+    redact_conflicts(&mut redactor, Some(&config), &mut runtime_env.conflicts);
+    let mut files = runtime_env.files;
+    files.extend(gemfile_fix(&project_root)?);
+    files.extend(doctor_script_fix(&project_root)?);
+    generation::write(&project_root, "bootstrap-fixes", files)?;
+    let runtime_env_path = project_root.join(RUNTIME_ENV_PATH);
 
     let generated_from_stdout = parse_generated_paths(&project_root, &stdout);
     let files = expected_generated_files(&project_root, &generated_from_stdout);
@@ -440,382 +585,1080 @@ pub fn generate_fastlane_files(config: ProjectConfig) -> Result<GenerateResult,
     Ok(GenerateResult {
         status: "success".to_string(),
         mode: mode.to_string(),
-        runtime_env_path: if mode == "dryRun" {
-            None
-        } else {
-            Some(runtime_env_path.display().to_string())
-        },
-        runtime_env_written,
+        runtime_env_path: Some(runtime_env_path.display().to_string()),
+        runtime_env_written: true,
+        env_conflicts: runtime_env.conflicts,
         files,
-        stdout: stdout.to_string(),
-        stderr: stderr.to_string(),
+        stdout,
+        stderr,
+        redactions: redactor.count(),
     })
 }
 
 #[tauri::command]
-pub fn bundle_install_and_validate(project_path: String) -> Result<LaneRunResult, String> {
-    let normalized_project_root = normalize_project_root(&PathBuf::from(&project_path));
-    ensure_fastlane_plugin_gemfile(&normalized_project_root)?;
-    let normalized_project_path = normalized_project_root.to_string_lossy().to_string();
-    let output = Command::new("/bin/zsh")
-        .arg("-lc")
-        .arg(ruby_aware_shell_command(
-            &normalized_project_path,
-            "FASTLANE_SKIP_UPDATE_CHECK=1 FASTLANE_DISABLE_COLORS=1 CI=1 bundle install && FASTLANE_SKIP_UPDATE_CHECK=1 FASTLANE_DISABLE_COLORS=1 CI=1 bundle exec fastlane ios validate_config",
-        ))
-        .output()
-        .map_err(|e| format!("Failed to run bundle install + validate_config: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let exit_code = output.status.code().unwrap_or(-1);
-    let status = if output.status.success() { "success" } else { "failed" };
+pub fn read_fastfile_constants(project_path: String) -> AppResult<Vec<FastfileConstant>> {
+    let fastfile = existing_fastfile(&normalize_project_root(&PathBuf::from(&project_path)))?;
+    let content = fs::read_to_string(&fastfile).map_err(|e| AppError::io("Read Fastfile", &fastfile, e))?;
+    Ok(fastfile_constants::read(&content))
+}
 
-    Ok(LaneRunResult {
-        status: status.to_string(),
-        exit_code,
-        output: format!("{}\n{}", stdout, stderr),
-        lane: "bundle_install_and_validate".to_string(),
+#[tauri::command]
+pub fn update_fastfile_constants(config: ProjectConfig) -> AppResult<FastfileConstantsUpdate> {
+    let target = resolve_generation_target(&config)?;
+    let fastfile = existing_fastfile(&target.project_root)?;
+    let content = fs::read_to_string(&fastfile).map_err(|e| AppError::io("Read Fastfile", &fastfile, e))?;
+    let values = templates::fastfile_constants(&TemplateInput {
+        config: &config,
+        project_name: &target.project_name,
+        workspace: &target.workspace,
+        xcodeproj: &target.xcodeproj,
+        existing_gemfile: None,
+    });
+    let values = values
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .collect::<Vec<_>>();
+    let update = fastfile_constants::update(&content, &values);
+
+    let written = generation::write(
+        &target.project_root,
+        "fastfile-constants",
+        vec![RenderedFile {
+            path: PathBuf::from("fastlane").join("Fastfile"),
+            content: update.content,
+            executable: false,
+        }],
+    )?;
+    Ok(FastfileConstantsUpdate {
+        fastfile_path: fastfile.display().to_string(),
+        updated: update.updated,
+        missing: update.missing,
+        backup_id: written.backup_id,
     })
 }
 
+fn existing_fastfile(project_root: &Path) -> AppResult<PathBuf> {
+    let fastfile = project_root.join("fastlane").join("Fastfile");
+    if !fastfile.is_file() {
+        return Err(
+            AppError::new(ErrorKind::InvalidInput, "fastlane/Fastfile does not exist yet.")
+                .with_hint("Generate Files first.")
+                .with_path(&fastfile),
+        );
+    }
+    Ok(fastfile)
+}
+
+/// Nothing is written until `apply_generation`.
 #[tauri::command]
-pub fn run_lane(project_path: String, lane: String) -> Result<LaneRunResult, String> {
-    let normalized_project_path = normalize_project_root(&PathBuf::from(&project_path))
-        .to_string_lossy()
-        .to_string();
+pub fn preview_generation(
+    app: AppHandle,
+    previews: State<'_, GenerationPreviews>,
+    config: ProjectConfig,
+) -> AppResult<GenerationPreview> {
+    let target = resolve_generation_target(&config)?;
+    let secrets = resolve_secret_refs(&app, &config)?;
+    let (rendered, env_conflicts) = render_generation(&config, &target, &secrets)?;
+    let mut preview = previews.prepare(&target.project_root, rendered)?;
+    preview.env_conflicts = env_conflicts;
+
+    let (_, env) = process_tools(&app);
+    let mut redactor = output_redactor(&env, &target.project_root, Some(&config), &secrets);
+    redact_diffs(&mut redactor, Some(&config), &mut preview.files);
+    redact_conflicts(&mut redactor, Some(&config), &mut preview.env_conflicts);
+    Ok(preview)
+}
 
-    if lane_requires_project_container(&lane) {
-        // Force-sync WORKSPACE/XCODEPROJ in Fastfile to avoid stale or invalid
-        // values from previous generations or incorrect UI states.
-        sync_fastfile_container_config(&normalized_project_path)?;
-        validate_fastfile_container_config(&normalized_project_path, &lane)?;
-    }
+#[tauri::command]
+pub fn apply_generation(previews: State<'_, GenerationPreviews>, preview_id: String) -> AppResult<GenerateResult> {
+    let (project_root, written) = previews.apply(&preview_id)?;
+    Ok(template_generate_result(&project_root, "standard", &written.paths))
+}
 
-    let (test_override, test_override_note) =
-        detect_test_action_and_maybe_disable_tests(&normalized_project_path, &lane);
-    let preflight = lane_preflight_report(
-        &normalized_project_path,
-        test_override_note.as_deref(),
-    );
-    let mut env_prefix = String::from("FASTLANE_SKIP_UPDATE_CHECK=1 FASTLANE_DISABLE_COLORS=1 CI=1");
-    if test_override {
-        env_prefix.push_str(" ENABLE_TESTS=false");
-    }
+struct GenerationTarget {
+    project_root: PathBuf,
+    project_name: String,
+    workspace: String,
+    xcodeproj: String,
+}
 
-    let mut output = run_lane_shell(&normalized_project_path, &lane, &env_prefix)?;
-    let mut retry_note = String::new();
+fn resolve_generation_target(config: &ProjectConfig) -> AppResult<GenerationTarget> {
+    let input_path = PathBuf::from(&config.project_path);
+    let project_root = normalize_project_root(&input_path);
+    if !project_root.exists() {
+        return Err(AppError::project_not_found(&config.project_path));
+    }
 
-    if !output.status.success()
-        && lane_runs_quality_gate(&lane)
-        && !test_override
-        && lane_failed_for_missing_test_action(&output)
-    {
-        let retry_env_prefix = format!("{env_prefix} ENABLE_TESTS=false");
-        output = run_lane_shell(&normalized_project_path, &lane, &retry_env_prefix)?;
-        retry_note = "[preflight] Retry with ENABLE_TESTS=false due to missing test action in scheme.\n".to_string();
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let exit_code = output.status.code().unwrap_or(-1);
-    let status = if output.status.success() { "success" } else { "failed" };
-    let command_output = format!("{}\n{}", stdout, stderr);
-    let full_output = if retry_note.is_empty() {
-        format!("{}\n{}", preflight, command_output)
+    let project_name = project_root
+        .file_name()
+        .and_then(OsStr::to_str)
+        .unwrap_or("iOSProject")
+        .to_string();
+    let (detected_workspace, detected_xcodeproj) = selected_container(&ranked_containers(&project_root, &input_path));
+    let resolved_workspace = if !config.workspace.trim().is_empty() {
+        to_absolute_from_project(&project_root, config.workspace.trim())
+    } else {
+        to_absolute_from_project(&project_root, &detected_workspace.unwrap_or_default())
+    };
+    let resolved_xcodeproj = if !config.xcodeproj.trim().is_empty() {
+        to_absolute_from_project(&project_root, config.xcodeproj.trim())
     } else {
-        format!("{}\n{}\n{}", preflight, retry_note.trim_end(), command_output)
+        to_absolute_from_project(&project_root, &detected_xcodeproj.unwrap_or_default())
     };
 
-    Ok(LaneRunResult {
-        status: status.to_string(),
-        exit_code,
-        output: full_output,
-        lane,
+    if resolved_workspace.is_empty() && resolved_xcodeproj.is_empty() {
+        return Err(AppError::new(
+            ErrorKind::ContainerNotFound,
+            "No .xcworkspace/.xcodeproj found under projectPath.",
+        )
+        .with_hint("Set Workspace or Xcodeproj manually.")
+        .with_path(&project_root));
+    }
+    Ok(GenerationTarget {
+        project_root,
+        project_name,
+        workspace: resolved_workspace,
+        xcodeproj: resolved_xcodeproj,
     })
 }
 
-fn run_lane_shell(project_path: &str, lane: &str, env_prefix: &str) -> Result<std::process::Output, String> {
-    let lane_escaped = escape_single_quote(lane);
-    let body = format!("{} bundle exec fastlane ios '{}'", env_prefix, lane_escaped);
-    Command::new("/bin/zsh")
-        .arg("-lc")
-        .arg(ruby_aware_shell_command(project_path, &body))
-        .output()
-        .map_err(|e| format!("Failed to run lane: {}", e))
-}
-
-fn ruby_aware_shell_command(project_path: &str, body: &str) -> String {
-    let project = escape_single_quote(project_path);
-    let body_escaped = body.replace('\'', "'\\''");
-    format!(
-        "cd '{}' && RUBY_VER=$(tr -d '[:space:]' < .ruby-version 2>/dev/null || true) && \
-if command -v rbenv >/dev/null 2>&1; then \
-  eval \"$(rbenv init - zsh)\" >/dev/null 2>&1 || true; \
-  if [ -n \"$RUBY_VER\" ]; then rbenv shell \"$RUBY_VER\" >/dev/null 2>&1 || true; fi; \
-  /bin/zsh -lc '{}'; \
-elif [ -x \"$HOME/.rvm/bin/rvm\" ] && [ -n \"$RUBY_VER\" ]; then \
-  \"$HOME/.rvm/bin/rvm\" \"$RUBY_VER\" do /bin/zsh -lc '{}'; \
-elif command -v asdf >/dev/null 2>&1 && [ -n \"$RUBY_VER\" ]; then \
-  asdf shell ruby \"$RUBY_VER\" >/dev/null 2>&1 || true; \
-  /bin/zsh -lc '{}'; \
-else \
-  /bin/zsh -lc '{}'; \
-fi",
-        project, body_escaped, body_escaped, body_escaped, body_escaped
-    )
+fn render_generation(
+    config: &ProjectConfig,
+    target: &GenerationTarget,
+    secrets: &[(String, String)],
+) -> AppResult<(Vec<RenderedFile>, Vec<EnvConflict>)> {
+    let gemfile = target.project_root.join("Gemfile");
+    let existing_gemfile = if gemfile.is_file() {
+        Some(fs::read_to_string(&gemfile).map_err(|e| AppError::io("Read Gemfile", &gemfile, e))?)
+    } else {
+        None
+    };
+    let mut files = templates::render(&TemplateInput {
+        config,
+        project_name: &target.project_name,
+        workspace: &target.workspace,
+        xcodeproj: &target.xcodeproj,
+        existing_gemfile: existing_gemfile.as_deref(),
+    });
+    let runtime_env = runtime_env_files(&target.project_root, config, secrets)?;
+    files.extend(runtime_env.files);
+    files.push(gitignore_file(&target.project_root)?);
+    Ok((files, runtime_env.conflicts))
 }
 
-fn lane_failed_for_missing_test_action(output: &std::process::Output) -> bool {
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let merged = format!("{stdout}\n{stderr}").to_lowercase();
-    merged.contains("not currently configured for the test action")
-        || merged.contains("error building/testing the application")
+fn runtime_env_files(
+    project_root: &Path,
+    config: &ProjectConfig,
+    secrets: &[(String, String)],
+) -> AppResult<RuntimeEnv> {
+    environments::validate(&config.environments)?;
+    let mut managed = vec![ManagedFile {
+        path: PathBuf::from(RUNTIME_ENV_PATH),
+        header: "Written by iOS Fastlane Desktop; keys it does not manage are kept as they are.".to_string(),
+        values: runtime_env_values(config, secrets),
+    }];
+    managed.extend(environments::managed_files(&config.environments));
+    runtime_env::render(project_root, &managed, |key| config.is_secret(key))
 }
 
-fn find_first_with_ext(root: &Path, ext: &str) -> Option<String> {
-    for entry in WalkDir::new(root)
-        .max_depth(4)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(Result::ok)
-    {
-        let path = entry.path();
-        if path.is_dir() && path.extension().and_then(OsStr::to_str) == Some(ext) {
-            // Ignore internal workspace metadata located inside *.xcodeproj bundles,
-            // e.g. Foo.xcodeproj/project.xcworkspace.
-            if ext == "xcworkspace" && is_inside_xcodeproj_bundle(root, path) {
-                continue;
-            }
-            return path
-                .strip_prefix(root)
-                .ok()
-                .map(|p| p.to_string_lossy().to_string());
-        }
-    }
-    None
+fn gitignore_file(project_root: &Path) -> AppResult<RenderedFile> {
+    let path = project_root.join(".gitignore");
+    let existing = if path.is_file() {
+        Some(fs::read_to_string(&path).map_err(|e| AppError::io("Read .gitignore", &path, e))?)
+    } else {
+        None
+    };
+    Ok(RenderedFile {
+        path: PathBuf::from(".gitignore"),
+        content: templates::gitignore_with_entries(existing.as_deref()),
+        executable: false,
+    })
 }
 
-fn is_inside_xcodeproj_bundle(root: &Path, path: &Path) -> bool {
-    path.strip_prefix(root)
-        .ok()
-        .map(|rel| {
-            rel.components().any(|component| {
-                component
-                    .as_os_str()
-                    .to_str()
-                    .map(|s| s.ends_with(".xcodeproj"))
-                    .unwrap_or(false)
-            })
+fn dry_run_result(project_root: &Path, rendered: Vec<RenderedFile>, env_conflicts: Vec<EnvConflict>) -> GenerateResult {
+    let stdout = rendered
+        .iter()
+        .filter(|file| templates::output_paths().any(|path| file.path == Path::new(path)))
+        .map(|file| {
+            format!(
+                "Would generate: {}\n{}",
+                project_root.join(&file.path).display(),
+                file.content
+            )
         })
-        .unwrap_or(false)
+        .collect::<Vec<_>>()
+        .join("\n");
+    GenerateResult {
+        status: "success".to_string(),
+        mode: "dryRun".to_string(),
+        runtime_env_path: None,
+        runtime_env_written: false,
+        env_conflicts,
+        files: expected_generated_files(project_root, &[]),
+        stdout,
+        stderr: String::new(),
+        redactions: 0,
+    }
 }
 
-fn normalize_project_root(path: &Path) -> PathBuf {
-    if let Some(ext) = path.extension().and_then(OsStr::to_str) {
-        if ext == "xcodeproj" || ext == "xcworkspace" {
-            return path
-                .parent()
-                .map(Path::to_path_buf)
-                .unwrap_or_else(|| path.to_path_buf());
-        }
+fn template_generate_result(project_root: &Path, mode: &str, written: &[PathBuf]) -> GenerateResult {
+    let runtime_env_path = project_root.join(RUNTIME_ENV_PATH);
+    let written = written
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    GenerateResult {
+        status: "success".to_string(),
+        mode: mode.to_string(),
+        runtime_env_written: runtime_env_path.is_file(),
+        runtime_env_path: Some(runtime_env_path.display().to_string()),
+        env_conflicts: vec![],
+        files: expected_generated_files(project_root, &written),
+        stdout: written.iter().map(|path| format!("Generated: {}\n", path)).collect(),
+        stderr: String::new(),
+        redactions: 0,
     }
-    path.to_path_buf()
 }
 
-fn container_hint_from_input_path(path: &Path) -> (Option<String>, Option<String>) {
-    let file_name = path.file_name().and_then(OsStr::to_str).map(|s| s.to_string());
-    match path.extension().and_then(OsStr::to_str) {
-        Some("xcworkspace") => (file_name, None),
-        Some("xcodeproj") => (None, file_name),
-        _ => (None, None),
-    }
+#[tauri::command]
+pub async fn bundle_install_and_validate(
+    app: AppHandle,
+    project_path: String,
+    run_id: String,
+    config: Option<ProjectConfig>,
+) -> AppResult<LaneRunResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let errors = error_redactor(&app, &project_path, config.as_ref());
+        bundle_install_and_validate_blocking(app, project_path, run_id, config).map_err(|e| errors.error(e))
+    })
+    .await
+    .map_err(|e| {
+        AppError::new(
+            ErrorKind::Internal,
+            format!("bundle install + validate_config task failed: {}", e),
+        )
+    })?
 }
 
-fn escape_single_quote(value: &str) -> String {
-    value.replace('\'', "'\\''")
+fn bundle_install_and_validate_blocking(
+    app: AppHandle,
+    project_path: String,
+    run_id: String,
+    config: Option<ProjectConfig>,
+) -> AppResult<LaneRunResult> {
+    let (runner, env) = process_tools(&app);
+    let project_root = normalize_project_root(&PathBuf::from(&project_path));
+    let start = RunStart::now(runner.as_ref(), &env, &project_root);
+    let redactor = output_redactor(&env, &project_root, config.as_ref(), &[]);
+    let mut run = LaneRun::new(app, run_id, redactor)?;
+    let exit = bundle_install_and_validate_steps(&mut run, &env, &project_root);
+    finish_run(
+        run,
+        &project_root,
+        start,
+        "bundle_install_and_validate",
+        config.as_ref(),
+        exit,
+    )
 }
 
-fn push_optional_arg(cmd: &mut Command, name: &str, value: &str) {
-    if !value.trim().is_empty() {
-        cmd.arg(name).arg(value.trim());
+fn bundle_install_and_validate_steps(run: &mut LaneRun, env: &LoginEnv, project_root: &Path) -> AppResult<CommandExit> {
+    if let Some(gemfile) = gemfile_fix(project_root)? {
+        let written = generation::write(project_root, "gemfile", vec![gemfile])?;
+        report_fix(run, "Gemfile updated to load fastlane/Pluginfile", &written);
     }
+    let ruby = ruby_env::resolve(env, project_root);
+    run.preflight(&format!("[preflight] ruby={}", ruby.describe()));
+    let mut exit = run
+        .run_command(&bundle_command(env, &ruby, project_root, &["install"]))
+        .map_err(|e| AppError::spawn("bundle install", e))?;
+    if exit.success {
+        exit = run
+            .run_command(&bundle_command(
+                env,
+                &ruby,
+                project_root,
+                &["exec", "fastlane", "ios", "validate_config"],
+            ))
+            .map_err(|e| AppError::spawn("fastlane ios validate_config", e))?;
+    }
+    Ok(exit)
 }
 
-fn bool_to_string(value: bool) -> &'static str {
-    if value { "true" } else { "false" }
+#[tauri::command]
+pub async fn run_lane(
+    app: AppHandle,
+    project_path: String,
+    lane: String,
+    run_id: String,
+    config: Option<ProjectConfig>,
+    options: Option<BTreeMap<String, LaneOptionValue>>,
+    environment: Option<String>,
+) -> AppResult<LaneRunResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let errors = error_redactor(&app, &project_path, config.as_ref());
+        run_lane_blocking(
+            app,
+            project_path,
+            lane,
+            run_id,
+            config,
+            options.unwrap_or_default(),
+            environment,
+        )
+        .map_err(|e| errors.error(e))
+    })
+    .await
+    .map_err(|e| AppError::new(ErrorKind::Internal, format!("Lane task failed: {}", e)))?
 }
 
-fn ensure_fastlane_plugin_gemfile(project_root: &Path) -> Result<(), String> {
-    let pluginfile = project_root.join("fastlane").join("Pluginfile");
-    if !pluginfile.exists() {
-        return Ok(());
-    }
-
-    let gemfile = project_root.join("Gemfile");
-    let mut content = if gemfile.exists() {
-        fs::read_to_string(&gemfile).map_err(|e| format!("Read Gemfile failed: {}", e))?
-    } else {
-        "source \"https://rubygems.org\"\n\ngem \"fastlane\"\n".to_string()
+fn run_lane_blocking(
+    app: AppHandle,
+    project_path: String,
+    lane: String,
+    run_id: String,
+    config: Option<ProjectConfig>,
+    options: BTreeMap<String, LaneOptionValue>,
+    environment: Option<String>,
+) -> AppResult<LaneRunResult> {
+    let project_root = normalize_project_root(&PathBuf::from(&project_path));
+    let (runner, env) = process_tools(&app);
+    let start = RunStart::now(runner.as_ref(), &env, &project_root);
+    let redactor = output_redactor(&env, &project_root, config.as_ref(), &[]);
+    let mut run = LaneRun::new(app.clone(), run_id, redactor)?;
+    let request = LaneRequest {
+        lane: &lane,
+        config: config.as_ref(),
+        options: &options,
+        environment: selected_environment(&environment),
     };
+    let exit = run_lane_steps(&app, &mut run, runner.as_ref(), &env, &project_root, request);
+    finish_run(run, &project_root, start, &lane, config.as_ref(), exit)
+}
 
-    if content.contains("eval_gemfile(plugins_path)") {
-        return Ok(());
+#[derive(Clone, Copy)]
+struct LaneRequest<'a> {
+    lane: &'a str,
+    config: Option<&'a ProjectConfig>,
+    options: &'a BTreeMap<String, LaneOptionValue>,
+    environment: Option<&'a str>,
+}
+
+/// Errors before the command starts (unknown options, a missing env file, a
+/// locked secret store, ...) end the run like a failed command would.
+fn run_lane_steps(
+    app: &AppHandle,
+    run: &mut LaneRun,
+    runner: &dyn CommandRunner,
+    env: &LoginEnv,
+    project_root: &Path,
+    request: LaneRequest,
+) -> AppResult<CommandExit> {
+    let lane = request.lane;
+    let normalized_project_path = project_root.to_string_lossy().to_string();
+    let lanes = fastfile_lanes::list(project_root)?;
+    let lane_info = lanes.find(lane);
+    if let Some(info) = lane_info.filter(|info| info.private) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Lane '{}' is private and can only be called from other lanes.", lane),
+        )
+        .with_path(project_root.join(&info.file)));
+    }
+    // Lanes that are not in the parsed files (e.g. from a remote import) run
+    // on the iOS platform without container or test preflight.
+    let platform = lane_info.map_or(Some("ios"), |info| info.platform.as_deref());
+    let option_args = lane_option_args(project_root, lane, lane_info, request.options)?;
+    let env_arg = environments::env_arg(project_root, request.environment)?;
+    let secrets = match request.config {
+        Some(config) => resolve_secret_refs(app, config)?,
+        None => vec![],
+    };
+    run.redact_values(secrets.iter().map(|(_, value)| value.clone()));
+    let uses_container = lane_info.is_some_and(|info| info.uses_container);
+    let runs_tests = lane_info.is_some_and(|info| info.runs_tests);
+
+    if uses_container {
+        if let Some(fastfile) = fastfile_container_fix(project_root)? {
+            let written = generation::write(project_root, "fastfile-sync", vec![fastfile])?;
+            report_fix(
+                run,
+                "Fastfile WORKSPACE/XCODEPROJ pointed at missing paths; set to the detected containers",
+                &written,
+            );
+        }
+        validate_fastfile_container_config(&normalized_project_path)?;
     }
 
-    if !content.ends_with('\n') {
-        content.push('\n');
+    let test_decision = detect_test_action_and_maybe_disable_tests(runner, env, &normalized_project_path, lane_info);
+    run.preflight(&lane_preflight_report(
+        &normalized_project_path,
+        test_decision.note.as_deref(),
+    ));
+    run.preflight(&lane_metadata_report(&lanes, lane));
+    if !option_args.is_empty() {
+        run.preflight(&format!("[preflight] options={}", option_args.join(" ")));
     }
-    content.push_str("\nplugins_path = File.join(File.dirname(__FILE__), \"fastlane\", \"Pluginfile\")\n");
-    content.push_str("eval_gemfile(plugins_path) if File.exist?(plugins_path)\n");
+    if let Some(env_arg) = &env_arg {
+        run.preflight(&format!("[preflight] env={}", env_arg));
+    }
+    if !secrets.is_empty() {
+        run.preflight(&secrets_report(project_root, &secrets));
+    }
+    let ruby = ruby_env::resolve(env, project_root);
+    run.preflight(&format!("[preflight] ruby={}", ruby.describe()));
 
-    fs::write(&gemfile, content).map_err(|e| format!("Write Gemfile failed: {}", e))?;
-    Ok(())
-}
+    let invocation = LaneInvocation {
+        platform,
+        lane,
+        options: &option_args,
+        env_arg: env_arg.as_deref(),
+        secrets: &secrets,
+    };
+    let title = match platform {
+        Some(platform) => format!("fastlane {} {}", platform, lane),
+        None => format!("fastlane {}", lane),
+    };
+    let mut exit = run
+        .run_command(&lane_command(
+            env,
+            &ruby,
+            project_root,
+            invocation,
+            test_decision.disable_tests,
+        ))
+        .map_err(|e| AppError::spawn(&title, e))?;
 
-fn patch_generated_doctor_script_for_bash3(project_root: &Path) -> Result<(), String> {
-    let doctor_script = project_root
-        .join("scripts")
-        .join("doctor_fastlane_env.sh");
-    if !doctor_script.exists() {
-        return Ok(());
+    if !run.is_cancelled() && retry_without_tests(&exit, runs_tests, &test_decision) {
+        run.preflight("[preflight] Retry with ENABLE_TESTS=false due to missing test action in scheme.");
+        exit = run
+            .run_command(&lane_command(env, &ruby, project_root, invocation, true))
+            .map_err(|e| AppError::spawn(&title, e))?;
     }
+    Ok(exit)
+}
 
-    let content = fs::read_to_string(&doctor_script)
-        .map_err(|e| format!("Read doctor script failed: {}", e))?;
-    if !content.contains("${IS_CI,,}") {
-        return Ok(());
+/// Runs that failed before their command started are saved too; their
+/// (redacted) error is returned after saving.
+fn finish_run(
+    mut run: LaneRun,
+    project_root: &Path,
+    start: RunStart,
+    lane: &str,
+    config: Option<&ProjectConfig>,
+    exit: AppResult<CommandExit>,
+) -> AppResult<LaneRunResult> {
+    let (result, error) = match exit {
+        Ok(exit) => (run.finish(lane.to_string(), exit.success, exit.exit_code), None),
+        Err(error) => {
+            let error = run.fail(error);
+            (run.finish(lane.to_string(), false, -1), Some(error))
+        }
+    };
+    // History is best effort: failing to persist it must not fail the run itself.
+    let _ = run_history::save_run(project_root, start, &result, config);
+    match error {
+        Some(error) => Err(error),
+        None => Ok(result),
     }
-
-    let patched = content.replace(
-        "${IS_CI,,}",
-        "$(printf '%s' \"$IS_CI\" | tr '[:upper:]' '[:lower:]')",
-    );
-    fs::write(&doctor_script, patched)
-        .map_err(|e| format!("Patch doctor script compatibility failed: {}", e))?;
-    Ok(())
 }
 
-fn normalize_bootstrap_mode(raw: &str) -> Result<&'static str, String> {
-    match raw.trim() {
-        "" | "standard" => Ok("standard"),
-        "dryRun" => Ok("dryRun"),
-        "configFile" => Ok("configFile"),
-        "interactive" => Ok("interactive"),
-        value => Err(format!("Unsupported bootstrapMode: {}", value)),
+/// Secret values are masked, including those of the config's secret references.
+#[tauri::command]
+pub fn effective_lane_env(
+    app: AppHandle,
+    project_path: String,
+    environment: Option<String>,
+    config: Option<ProjectConfig>,
+) -> AppResult<EffectiveEnv> {
+    let (_, env) = process_tools(&app);
+    let project_root = normalize_project_root(&PathBuf::from(&project_path));
+    let mut effective = environments::effective(
+        &project_root,
+        selected_environment(&environment),
+        &APP_LANE_ENV,
+        |key| env.get(key).map(str::to_string),
+    )?;
+    let mut redactor = output_redactor(&env, &project_root, config.as_ref(), &[]);
+    for value in &mut effective.values {
+        value.value = if is_secret(config.as_ref(), &value.key) && !value.value.is_empty() {
+            redact::MASK.to_string()
+        } else {
+            redactor.line(&value.value)
+        };
     }
+    Ok(effective)
 }
 
-fn render_runtime_env(config: &ProjectConfig) -> String {
-    [
-        format!("PGYER_API_KEY={}", config.pgyer_api_key),
-        format!("MATCH_GIT_URL={}", config.match_git_url),
-        format!("MATCH_GIT_BRANCH={}", config.match_git_branch),
-        format!(
-            "APP_STORE_CONNECT_API_KEY_PATH={}",
-            config.app_store_connect_api_key_path
-        ),
-        format!("ENABLE_QUALITY_GATE={}", bool_to_string(config.enable_quality_gate)),
-        format!("ENABLE_TESTS={}", bool_to_string(config.enable_tests)),
-        format!("ENABLE_SWIFTLINT={}", bool_to_string(config.enable_swiftlint)),
-        format!(
-            "ENABLE_SLACK_NOTIFY={}",
-            bool_to_string(config.enable_slack_notify)
-        ),
-        format!(
-            "ENABLE_WECHAT_NOTIFY={}",
-            bool_to_string(config.enable_wechat_notify)
-        ),
-        format!("ENABLE_SNAPSHOT={}", bool_to_string(config.enable_snapshot)),
-        format!("SNAPSHOT_SCHEME={}", config.snapshot_scheme),
-        format!("SNAPSHOT_DEVICES={}", config.snapshot_devices),
-        format!("SNAPSHOT_LANGUAGES={}", config.snapshot_languages),
-        format!("METADATA_PATH={}", config.metadata_path),
-        format!(
-            "ENABLE_METADATA_UPLOAD={}",
-            bool_to_string(config.enable_metadata_upload)
-        ),
-        format!(
-            "ENABLE_SCREENSHOT_UPLOAD={}",
-            bool_to_string(config.enable_screenshot_upload)
-        ),
-        format!("GYM_SKIP_CLEAN={}", bool_to_string(config.gym_skip_clean)),
-        format!("DERIVED_DATA_PATH={}", config.derived_data_path),
-        format!("CI_BUNDLE_INSTALL={}", bool_to_string(config.ci_bundle_install)),
-        format!(
-            "CI_COCOAPODS_DEPLOYMENT={}",
-            bool_to_string(config.ci_cocoapods_deployment)
-        ),
-    ]
-    .join("\n")
+fn output_redactor(
+    env: &LoginEnv,
+    project_root: &Path,
+    config: Option<&ProjectConfig>,
+    secrets: &[(String, String)],
+) -> Redactor {
+    let mut values = secrets.iter().map(|(_, value)| value.clone()).collect::<Vec<_>>();
+    values.extend(config.map(|config| config.pgyer_api_key.clone()));
+    values.extend(
+        config
+            .and_then(|config| redact::url_userinfo(&config.match_git_url))
+            .map(str::to_string),
+    );
+    let mut variables = env
+        .vars()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<Vec<_>>();
+    for environment in config.iter().flat_map(|config| &config.environments) {
+        variables.extend(
+            environment
+                .values
+                .iter()
+                .map(|value| (value.key.clone(), value.value.clone())),
+        );
+    }
+    let fastlane_dir = project_root.join("fastlane");
+    for entry in fs::read_dir(&fastlane_dir).into_iter().flatten().filter_map(Result::ok) {
+        if !entry.file_name().to_string_lossy().starts_with(".env") {
+            continue;
+        }
+        if let Ok(content) = fs::read_to_string(entry.path()) {
+            variables.extend(dotenv::parse(&content));
+        }
+    }
+    values.extend(
+        variables
+            .into_iter()
+            .filter(|(key, _)| is_secret(config, key))
+            .map(|(_, value)| value),
+    );
+    Redactor::new(values)
 }
 
-fn parse_generated_paths(project_root: &Path, stdout: &str) -> Vec<String> {
-    let mut paths = Vec::new();
-    for line in stdout.lines() {
-        let trimmed = line.trim();
-        if let Some(path) = trimmed.strip_prefix("Generated: ") {
-            let path_value = path.trim().to_string();
-            if !path_value.is_empty() {
-                paths.push(path_value);
-            }
+/// Values of secret keys on changed `.env*` lines are masked too, which covers
+/// values that are no longer current (e.g. in a backup from before a rotation).
+fn redact_diffs(redactor: &mut Redactor, config: Option<&ProjectConfig>, files: &mut [FilePreview]) {
+    for file in files {
+        let is_dotenv = Path::new(&file.path)
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(".env"));
+        if is_dotenv {
+            let values = file
+                .diff
+                .lines()
+                .filter_map(|line| line.get(1..))
+                .flat_map(dotenv::parse)
+                .filter(|(key, _)| is_secret(config, key))
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>();
+            redactor.add(values);
         }
+        file.diff = redactor.text(&file.diff);
     }
+}
 
-    // If script output format changes, fall back to expected output paths.
-    if paths.is_empty() {
-        let fastlane_dir = project_root.join("fastlane");
-        paths.push(fastlane_dir.join("Fastfile").display().to_string());
-        paths.push(fastlane_dir.join("Appfile").display().to_string());
-        paths.push(fastlane_dir.join("Pluginfile").display().to_string());
-        paths.push(fastlane_dir.join(".env.fastlane.example").display().to_string());
-        paths.push(fastlane_dir.join(".env.fastlane.staging.example").display().to_string());
-        paths.push(fastlane_dir.join(".env.fastlane.prod.example").display().to_string());
-        paths.push(project_root.join("Gemfile").display().to_string());
-        paths.push(
-            project_root
-                .join("scripts")
-                .join("doctor_fastlane_env.sh")
-                .display()
-                .to_string(),
-        );
-        paths.push(
-            project_root
-                .join("scripts")
-                .join("fastlane_run.sh")
-                .display()
-                .to_string(),
-        );
+fn redact_conflicts(redactor: &mut Redactor, config: Option<&ProjectConfig>, conflicts: &mut [EnvConflict]) {
+    for conflict in conflicts {
+        if is_secret(config, &conflict.key) {
+            conflict.file_value = redact::MASK.to_string();
+            conflict.app_value = redact::MASK.to_string();
+        } else {
+            conflict.file_value = redactor.line(&conflict.file_value);
+            conflict.app_value = redactor.line(&conflict.app_value);
+        }
+    }
+}
+
+fn is_secret(config: Option<&ProjectConfig>, key: &str) -> bool {
+    config.map_or_else(|| redact::is_secret_key(key), |config| config.is_secret(key))
+}
+
+/// Secret references are resolved when the store is unlocked; their
+/// variables are masked by name either way.
+fn error_redactor(app: &AppHandle, project_path: &str, config: Option<&ProjectConfig>) -> Redactor {
+    let (_, env) = process_tools(app);
+    let secrets = config
+        .map(|config| resolve_secret_refs(app, config).unwrap_or_default())
+        .unwrap_or_default();
+    output_redactor(
+        &env,
+        &normalize_project_root(&PathBuf::from(project_path)),
+        config,
+        &secrets,
+    )
+}
+
+/// `--env` files override the process environment, so a value in
+/// `.env.fastlane` that no longer matches its secret (e.g. after a rotation)
+/// wins and is flagged.
+fn secrets_report(project_root: &Path, secrets: &[(String, String)]) -> String {
+    let keys = secrets.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>();
+    let mut report = format!("[preflight] secrets={}", keys.join(","));
+    let runtime_env = fs::read_to_string(project_root.join(RUNTIME_ENV_PATH)).unwrap_or_default();
+    let document = dotenv::Document::parse(&runtime_env);
+    let stale = secrets
+        .iter()
+        .filter(|(key, value)| document.get(key).is_some_and(|current| current != value))
+        .map(|(key, _)| key.as_str())
+        .collect::<Vec<_>>();
+    if !stale.is_empty() {
+        report.push_str(&format!(
+            "\n[preflight] {} overrides the secret store for {}; Generate Files to update it.",
+            RUNTIME_ENV_PATH,
+            stale.join(", ")
+        ));
+    }
+    report
+}
+
+/// No unlock is needed when the config has no secret references.
+fn resolve_secret_refs(app: &AppHandle, config: &ProjectConfig) -> AppResult<Vec<(String, String)>> {
+    if config.secret_refs.is_empty() {
+        return Ok(vec![]);
+    }
+    secret_store::validate_refs(&config.secret_refs)?;
+    app.state::<SecretStore>()
+        .resolve(&secret_store::store_path(app)?, &config.secret_refs)
+}
+
+fn selected_environment(environment: &Option<String>) -> Option<&str> {
+    environment.as_deref().map(str::trim).filter(|name| !name.is_empty())
+}
+
+#[tauri::command]
+pub fn list_lanes(project_path: String) -> AppResult<FastfileLanes> {
+    fastfile_lanes::list(&normalize_project_root(&PathBuf::from(&project_path)))
+}
+
+#[tauri::command]
+pub fn cancel_lane(running_lanes: State<'_, RunningLanes>, run_id: String) -> AppResult<()> {
+    if running_lanes.cancel(&run_id) {
+        Ok(())
+    } else {
+        Err(AppError::new(
+            ErrorKind::LaneNotRunning,
+            format!("No running lane found for runId: {}", run_id),
+        ))
+    }
+}
+
+#[tauri::command]
+pub fn list_runs(project_path: String) -> AppResult<Vec<RunSummary>> {
+    run_history::list_runs(&normalize_project_root(&PathBuf::from(&project_path)))
+}
+
+#[tauri::command]
+pub fn get_run(app: AppHandle, project_path: String, run_id: String) -> AppResult<RunRecord> {
+    let project_root = normalize_project_root(&PathBuf::from(&project_path));
+    let mut record = run_history::load_run(&project_root, &run_id)?;
+    // Records from before output was redacted, or with secrets added since.
+    let (_, env) = process_tools(&app);
+    let mut redactor = output_redactor(&env, &project_root, None, &[]);
+    record.output = redactor.text(&record.output);
+    record.summary.redactions += redactor.count();
+    Ok(record)
+}
+
+#[tauri::command]
+pub fn delete_runs(project_path: String, run_ids: Vec<String>) -> AppResult<usize> {
+    run_history::delete_runs(&normalize_project_root(&PathBuf::from(&project_path)), &run_ids)
+}
+
+#[tauri::command]
+pub fn list_backups(project_path: String) -> AppResult<Vec<BackupSummary>> {
+    backups::list_backups(&normalize_project_root(&PathBuf::from(&project_path)))
+}
+
+#[tauri::command]
+pub fn diff_backup(
+    app: AppHandle,
+    project_path: String,
+    backup_id: String,
+    config: Option<ProjectConfig>,
+) -> AppResult<Vec<FilePreview>> {
+    let project_root = normalize_project_root(&PathBuf::from(&project_path));
+    let mut files = backups::diff_backup(&project_root, &backup_id)?;
+    let secrets = match &config {
+        Some(config) => resolve_secret_refs(&app, config).unwrap_or_default(),
+        None => vec![],
+    };
+    let (_, env) = process_tools(&app);
+    let mut redactor = output_redactor(&env, &project_root, config.as_ref(), &secrets);
+    redact_diffs(&mut redactor, config.as_ref(), &mut files);
+    Ok(files)
+}
+
+#[tauri::command]
+pub fn restore_backup(project_path: String, backup_id: String) -> AppResult<BackupSummary> {
+    backups::restore_backup(&normalize_project_root(&PathBuf::from(&project_path)), &backup_id)
+}
+
+#[tauri::command]
+pub fn unlock_secret_store(
+    app: AppHandle,
+    store: State<'_, SecretStore>,
+    passphrase: String,
+) -> AppResult<Vec<SecretInfo>> {
+    store.unlock(&secret_store::store_path(&app)?, &passphrase)
+}
+
+#[tauri::command]
+pub fn lock_secret_store(store: State<'_, SecretStore>) {
+    store.lock()
+}
+
+/// Secret names and timestamps; values are never returned.
+#[tauri::command]
+pub fn list_secrets(app: AppHandle, store: State<'_, SecretStore>) -> AppResult<SecretList> {
+    store.list(&secret_store::store_path(&app)?)
+}
+
+#[tauri::command]
+pub fn add_secret(app: AppHandle, store: State<'_, SecretStore>, name: String, value: String) -> AppResult<SecretInfo> {
+    store.add(&secret_store::store_path(&app)?, name.trim(), &value)
+}
+
+#[tauri::command]
+pub fn rotate_secret(
+    app: AppHandle,
+    store: State<'_, SecretStore>,
+    name: String,
+    value: String,
+) -> AppResult<SecretInfo> {
+    store.rotate(&secret_store::store_path(&app)?, &name, &value)
+}
+
+#[tauri::command]
+pub fn delete_secret(app: AppHandle, store: State<'_, SecretStore>, name: String) -> AppResult<()> {
+    store.delete(&secret_store::store_path(&app)?, &name)
+}
+
+#[tauri::command]
+pub fn list_provisioning_profiles(app: AppHandle, dir: Option<String>) -> AppResult<Vec<ProvisioningProfile>> {
+    let dirs = match dir.filter(|d| !d.trim().is_empty()) {
+        Some(dir) => vec![PathBuf::from(dir.trim())],
+        None => {
+            let home = app
+                .path()
+                .home_dir()
+                .map_err(|e| AppError::new(ErrorKind::Internal, format!("Resolve home dir failed: {}", e)))?;
+            mobileprovision::default_profile_dirs(&home)
+        }
+    };
+
+    let mut profiles: Vec<ProvisioningProfile> = Vec::new();
+    for dir in dirs {
+        // The same profile can be installed in both of Xcode's folders.
+        let listed =
+            mobileprovision::list_profiles(&dir).map_err(|e| AppError::new(ErrorKind::Io, e).with_path(&dir))?;
+        for profile in listed {
+            if !profiles.iter().any(|existing| existing.uuid == profile.uuid) {
+                profiles.push(profile);
+            }
+        }
+    }
+    Ok(profiles)
+}
+
+#[tauri::command]
+pub fn get_app_settings(app: AppHandle) -> AppResult<AppSettings> {
+    app_settings::load(&app)
+}
+
+/// Saves the settings; a changed login shell takes effect on the next command.
+#[tauri::command]
+pub fn save_app_settings(app: AppHandle, settings: AppSettings) -> AppResult<AppSettings> {
+    app_settings::save(&app, &settings)?;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn refresh_login_env(app: AppHandle) -> AppResult<LoginEnvSummary> {
+    let shell = app_settings::load(&app)?.login_shell();
+    let runner = app.state::<SharedRunner>().inner().clone();
+    let env = app.state::<LoginEnvCache>().refresh(runner.as_ref(), &shell);
+    Ok(env.summary())
+}
+
+fn process_tools(app: &AppHandle) -> (SharedRunner, LoginEnv) {
+    let runner = app.state::<SharedRunner>().inner().clone();
+    let shell = app_settings::load(app)
+        .map(|settings| settings.login_shell())
+        .unwrap_or_else(|_| crate::login_env::default_shell());
+    let env = app.state::<LoginEnvCache>().get(runner.as_ref(), &shell);
+    (runner, env)
+}
+
+#[derive(Clone, Copy)]
+struct LaneInvocation<'a> {
+    platform: Option<&'a str>,
+    lane: &'a str,
+    options: &'a [String],
+    /// The `--env` value, e.g. `fastlane,fastlane.staging`.
+    env_arg: Option<&'a str>,
+    /// Resolved secret references, passed in the process environment.
+    secrets: &'a [(String, String)],
+}
+
+fn lane_command(
+    env: &LoginEnv,
+    ruby: &RubyResolution,
+    project_root: &Path,
+    invocation: LaneInvocation,
+    disable_tests: bool,
+) -> CommandSpec {
+    let mut args = vec!["exec", "fastlane"];
+    args.extend(invocation.platform);
+    args.push(invocation.lane);
+    // Each option is its own argv entry, so values need no shell quoting.
+    args.extend(invocation.options.iter().map(String::as_str));
+    if let Some(env_arg) = invocation.env_arg {
+        args.extend(["--env", env_arg]);
+    }
+    let mut cmd = bundle_command(env, ruby, project_root, &args);
+    for (key, value) in invocation.secrets {
+        cmd.env(key, value);
+    }
+    if disable_tests {
+        // `--env` files override ENABLE_TESTS, so generated Fastfiles also
+        // check a variable no env file sets.
+        cmd.env("ENABLE_TESTS", "false");
+        cmd.env("FASTLANE_DESKTOP_SKIP_TESTS", "1");
+    }
+    cmd
+}
+
+fn bundle_command(env: &LoginEnv, ruby: &RubyResolution, project_root: &Path, args: &[&str]) -> CommandSpec {
+    let mut cmd = ruby.command(env, "bundle");
+    cmd.args(args).current_dir(project_root);
+    for (key, value) in APP_LANE_ENV {
+        cmd.env(key, value);
+    }
+    cmd
+}
+
+/// Only when the lane failed for a missing test action that no scheme file ruled out.
+fn retry_without_tests(exit: &CommandExit, runs_tests: bool, decision: &TestActionDecision) -> bool {
+    !exit.success
+        && runs_tests
+        && !decision.disable_tests
+        && !decision.from_scheme_file
+        && lane_failed_for_missing_test_action(&exit.output)
+}
+
+fn lane_failed_for_missing_test_action(output: &str) -> bool {
+    let merged = output.to_lowercase();
+    merged.contains("not currently configured for the test action")
+        || merged.contains("error building/testing the application")
+}
+
+fn normalize_project_root(path: &Path) -> PathBuf {
+    if let Some(ext) = path.extension().and_then(OsStr::to_str) {
+        if ext == "xcodeproj" || ext == "xcworkspace" {
+            return path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| path.to_path_buf());
+        }
+    }
+    path.to_path_buf()
+}
+
+fn container_hint_from_input_path(path: &Path) -> Option<String> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("xcworkspace") | Some("xcodeproj") => path.file_name().and_then(OsStr::to_str).map(|s| s.to_string()),
+        _ => None,
+    }
+}
+
+/// A container the user picked directly always comes first.
+fn ranked_containers(root: &Path, input_path: &Path) -> Vec<ContainerCandidate> {
+    let mut containers = xcworkspace::find_containers(root);
+    if let Some(hint) = container_hint_from_input_path(input_path) {
+        if let Some(index) = containers.iter().position(|candidate| candidate.path == hint) {
+            let picked = containers.remove(index);
+            containers.insert(0, picked);
+        }
+    }
+    containers
+}
+
+/// For a workspace the xcodeproj is its app project, so pbxproj-based lookups
+/// still have a project.
+fn selected_container(containers: &[ContainerCandidate]) -> (Option<String>, Option<String>) {
+    let selected = containers.first();
+    (
+        selected.and_then(ContainerCandidate::workspace).map(str::to_string),
+        selected
+            .and_then(ContainerCandidate::primary_project)
+            .map(str::to_string),
+    )
+}
+
+fn push_optional_arg(cmd: &mut CommandSpec, name: &str, value: &str) {
+    if !value.trim().is_empty() {
+        cmd.arg(name).arg(value.trim());
+    }
+}
+
+fn bool_to_string(value: bool) -> &'static str {
+    if value {
+        "true"
+    } else {
+        "false"
+    }
+}
+
+fn gemfile_fix(project_root: &Path) -> AppResult<Option<RenderedFile>> {
+    let pluginfile = project_root.join("fastlane").join("Pluginfile");
+    if !pluginfile.exists() {
+        return Ok(None);
+    }
+
+    let gemfile = project_root.join("Gemfile");
+    let existing = if gemfile.exists() {
+        fs::read_to_string(&gemfile).map_err(|e| AppError::io("Read Gemfile", &gemfile, e))?
+    } else {
+        "source \"https://rubygems.org\"\n\ngem \"fastlane\"\n".to_string()
+    };
+
+    let content = templates::gemfile_with_plugins(&existing);
+    if gemfile.exists() && content == existing {
+        return Ok(None);
+    }
+    Ok(Some(RenderedFile {
+        path: PathBuf::from("Gemfile"),
+        content,
+        executable: false,
+    }))
+}
+
+fn report_fix(run: &mut LaneRun, message: &str, written: &generation::Written) {
+    if let Some(backup_id) = &written.backup_id {
+        run.preflight(&format!("[preflight] {} (backup {}).", message, backup_id));
+    }
+    for file in &written.files {
+        run.preflight(&file.diff);
+    }
+}
+
+/// The doctor script from the bootstrap script with the Bash 4 `${IS_CI,,}`
+/// replaced, so it runs on macOS's Bash 3.2.
+fn doctor_script_fix(project_root: &Path) -> AppResult<Option<RenderedFile>> {
+    let doctor_script = project_root.join("scripts").join("doctor_fastlane_env.sh");
+    if !doctor_script.exists() {
+        return Ok(None);
+    }
+
+    let content =
+        fs::read_to_string(&doctor_script).map_err(|e| AppError::io("Read doctor script", &doctor_script, e))?;
+    if !content.contains("${IS_CI,,}") {
+        return Ok(None);
+    }
+
+    Ok(Some(RenderedFile {
+        path: PathBuf::from("scripts").join("doctor_fastlane_env.sh"),
+        content: content.replace("${IS_CI,,}", "$(printf '%s' \"$IS_CI\" | tr '[:upper:]' '[:lower:]')"),
+        executable: true,
+    }))
+}
+
+fn normalize_bootstrap_mode(raw: &str) -> AppResult<&'static str> {
+    match raw.trim() {
+        "" | "standard" => Ok("standard"),
+        "dryRun" => Ok("dryRun"),
+        "configFile" => Ok("configFile"),
+        "interactive" => Ok("interactive"),
+        value => Err(
+            AppError::new(ErrorKind::InvalidInput, format!("Unsupported bootstrapMode: {}", value))
+                .with_hint("Use standard, dryRun, configFile or interactive."),
+        ),
+    }
+}
+
+fn runtime_env_values(config: &ProjectConfig, secrets: &[(String, String)]) -> Vec<(String, String)> {
+    let mut values = [
+        ("PGYER_API_KEY", config.pgyer_api_key.clone()),
+        ("MATCH_GIT_URL", config.match_git_url.clone()),
+        ("MATCH_GIT_BRANCH", config.match_git_branch.clone()),
+        (
+            "APP_STORE_CONNECT_API_KEY_PATH",
+            config.app_store_connect_api_key_path.clone(),
+        ),
+        (
+            "ENABLE_QUALITY_GATE",
+            bool_to_string(config.enable_quality_gate).to_string(),
+        ),
+        ("ENABLE_TESTS", bool_to_string(config.enable_tests).to_string()),
+        ("ENABLE_SWIFTLINT", bool_to_string(config.enable_swiftlint).to_string()),
+        (
+            "ENABLE_SLACK_NOTIFY",
+            bool_to_string(config.enable_slack_notify).to_string(),
+        ),
+        (
+            "ENABLE_WECHAT_NOTIFY",
+            bool_to_string(config.enable_wechat_notify).to_string(),
+        ),
+        ("ENABLE_SNAPSHOT", bool_to_string(config.enable_snapshot).to_string()),
+        ("SNAPSHOT_SCHEME", config.snapshot_scheme.clone()),
+        ("SNAPSHOT_DEVICES", config.snapshot_devices.clone()),
+        ("SNAPSHOT_LANGUAGES", config.snapshot_languages.clone()),
+        ("METADATA_PATH", config.metadata_path.clone()),
+        (
+            "ENABLE_METADATA_UPLOAD",
+            bool_to_string(config.enable_metadata_upload).to_string(),
+        ),
+        (
+            "ENABLE_SCREENSHOT_UPLOAD",
+            bool_to_string(config.enable_screenshot_upload).to_string(),
+        ),
+        ("GYM_SKIP_CLEAN", bool_to_string(config.gym_skip_clean).to_string()),
+        ("DERIVED_DATA_PATH", config.derived_data_path.clone()),
+        (
+            "CI_BUNDLE_INSTALL",
+            bool_to_string(config.ci_bundle_install).to_string(),
+        ),
+        (
+            "CI_COCOAPODS_DEPLOYMENT",
+            bool_to_string(config.ci_cocoapods_deployment).to_string(),
+        ),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value))
+    .collect::<Vec<_>>();
+    let extras = environments::default_values(&config.environments)
+        .map(|extra| (&extra.key, &extra.value))
+        .chain(secrets.iter().map(|(key, value)| (key, value)));
+    for (extra_key, extra_value) in extras {
+        match values.iter_mut().find(|(key, _)| key == extra_key) {
+            Some((_, value)) => value.clone_from(extra_value),
+            None => values.push((extra_key.clone(), extra_value.clone())),
+        }
+    }
+    values
+}
+
+fn parse_generated_paths(project_root: &Path, stdout: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for line in stdout.lines() {
+        let trimmed = line.trim();
+        if let Some(path) = trimmed.strip_prefix("Generated: ") {
+            let path_value = path.trim().to_string();
+            if !path_value.is_empty() {
+                paths.push(path_value);
+            }
+        }
+    }
+
+    // If script output format changes, fall back to expected output paths.
+    if paths.is_empty() {
+        paths.extend(templates::output_paths().map(|path| project_root.join(path).display().to_string()));
     }
 
     paths
 }
 
 fn expected_generated_files(project_root: &Path, generated_paths: &[String]) -> Vec<GeneratedFileStatus> {
-    let fastlane_dir = project_root.join("fastlane");
-    let expected = vec![
-        fastlane_dir.join("Fastfile").display().to_string(),
-        fastlane_dir.join("Appfile").display().to_string(),
-        fastlane_dir.join("Pluginfile").display().to_string(),
-        fastlane_dir.join(".env.fastlane.example").display().to_string(),
-        fastlane_dir.join(".env.fastlane.staging.example").display().to_string(),
-        fastlane_dir.join(".env.fastlane.prod.example").display().to_string(),
-        project_root.join("Gemfile").display().to_string(),
-        project_root
-            .join("scripts")
-            .join("doctor_fastlane_env.sh")
-            .display()
-            .to_string(),
-        project_root
-            .join("scripts")
-            .join("fastlane_run.sh")
-            .display()
-            .to_string(),
-    ];
-
-    expected
-        .into_iter()
+    templates::output_paths()
+        .map(|path| project_root.join(path).display().to_string())
         .map(|path| GeneratedFileStatus {
             exists: PathBuf::from(&path).exists(),
             generated: generated_paths.iter().any(|p| p == &path),
@@ -824,36 +1667,137 @@ fn expected_generated_files(project_root: &Path, generated_paths: &[String]) ->
         .collect()
 }
 
+fn scheme_containers(root: &Path, workspace: Option<&str>, xcodeproj: Option<&str>) -> Vec<PathBuf> {
+    let mut containers = Vec::new();
+    if let Some(ws) = workspace.filter(|value| !value.trim().is_empty()) {
+        let ws_path = PathBuf::from(to_absolute_from_project(root, ws));
+        let members = xcworkspace::workspace_projects(&ws_path).unwrap_or_default();
+        containers.push(ws_path);
+        containers.extend(
+            members
+                .into_iter()
+                .filter(|member| !xcworkspace::is_pods_project(member)),
+        );
+    }
+    if let Some(proj) = xcodeproj.filter(|value| !value.trim().is_empty()) {
+        let proj_path = PathBuf::from(to_absolute_from_project(root, proj));
+        if !containers.contains(&proj_path) {
+            containers.push(proj_path);
+        }
+    }
+    containers
+}
+
+fn container_scheme_names(root: &Path, workspace: Option<&str>, xcodeproj: Option<&str>) -> Vec<String> {
+    let names = discover_container_schemes(&scheme_containers(root, workspace, xcodeproj))
+        .into_iter()
+        .map(|scheme| scheme.name)
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        autocreated_scheme_names(root, xcodeproj)
+    } else {
+        names
+    }
+}
+
+fn discover_container_schemes(containers: &[PathBuf]) -> Vec<Scheme> {
+    let mut schemes: Vec<Scheme> = Vec::new();
+    for scheme in containers
+        .iter()
+        .flat_map(|container| xcscheme::discover_schemes(container))
+    {
+        if !schemes.iter().any(|existing| existing.name == scheme.name) {
+            schemes.push(scheme);
+        }
+    }
+    schemes
+}
+
+/// Scheme names Xcode autocreates when a project has no scheme files: one per
+/// non-test native target.
+fn autocreated_scheme_names(root: &Path, xcodeproj: Option<&str>) -> Vec<String> {
+    let project = match xcodeproj.and_then(|proj| load_project_model(root, proj)) {
+        Some(project) => project,
+        None => return vec![],
+    };
+    project
+        .targets
+        .iter()
+        .filter(|target| {
+            let product_type = target.product_type.as_deref().unwrap_or_default();
+            !product_type.contains("unit-test") && !product_type.contains("ui-testing")
+        })
+        .map(|target| target.name.clone())
+        .collect()
+}
+
+fn load_project_model(root: &Path, xcodeproj: &str) -> Option<PbxProject> {
+    pbxproj::load_project(Path::new(&to_absolute_from_project(root, xcodeproj))).ok()
+}
+
+/// Without a scheme file the target is matched by name, or taken as the only
+/// application target in the project.
+fn resolve_build_setting_from_model(
+    project: &PbxProject,
+    scheme_name: &str,
+    scheme: Option<&Scheme>,
+    key: &str,
+) -> Option<ResolvedSetting> {
+    let target = match scheme.and_then(Scheme::archive_target) {
+        Some(reference) => project
+            .target_by_id(&reference.blueprint_identifier)
+            .or_else(|| project.target(&reference.blueprint_name))?,
+        None => project.target(scheme_name).or_else(|| {
+            let mut apps = project.application_targets();
+            match (apps.next(), apps.next()) {
+                (Some(only), None) => Some(only),
+                _ => None,
+            }
+        })?,
+    };
+    let configuration = scheme
+        .and_then(|s| s.archive_configuration.as_deref().or(s.launch_configuration.as_deref()))
+        .or(target.default_configuration_name.as_deref())
+        .or(project.default_configuration_name.as_deref())
+        .unwrap_or("Release");
+    xcconfig::resolve_target_setting(project, target, configuration, key)
+        .ok()
+        .flatten()
+        .filter(|setting| !setting.value.is_empty())
+}
+
+fn xcodebuild_command(
+    env: &LoginEnv,
+    root: &Path,
+    workspace: Option<&str>,
+    xcodeproj: Option<&str>,
+    action: &str,
+) -> Option<CommandSpec> {
+    let (flag, container) = match (workspace, xcodeproj) {
+        (Some(ws), _) => ("-workspace", ws),
+        (None, Some(proj)) => ("-project", proj),
+        (None, None) => return None,
+    };
+    let mut cmd = env.command("xcodebuild");
+    cmd.arg(action).arg(flag).arg(container).current_dir(root);
+    Some(cmd)
+}
+
 fn parse_schemes_from_xcodebuild(
+    runner: &dyn CommandRunner,
+    env: &LoginEnv,
     root: &Path,
     workspace: Option<&str>,
     xcodeproj: Option<&str>,
-) -> Result<Vec<String>, String> {
-    let target_arg = if let Some(ws) = workspace {
-        format!("-workspace '{}'", escape_single_quote(ws))
-    } else if let Some(proj) = xcodeproj {
-        format!("-project '{}'", escape_single_quote(proj))
-    } else {
+) -> AppResult<Vec<String>> {
+    let Some(cmd) = xcodebuild_command(env, root, workspace, xcodeproj, "-list") else {
         return Ok(vec![]);
     };
+    let output = runner
+        .output(&cmd)
+        .map_err(|e| AppError::spawn("xcodebuild -list", e))?;
 
-    let cmd = format!(
-        "cd '{}' && xcodebuild -list {}",
-        escape_single_quote(&root.to_string_lossy()),
-        target_arg
-    );
-
-    let output = Command::new("/bin/zsh")
-        .arg("-lc")
-        .arg(cmd)
-        .output()
-        .map_err(|e| format!("xcodebuild -list failed: {}", e))?;
-
-    let text = format!(
-        "{}\n{}",
-        String::from_utf8_lossy(&output.stdout),
-        String::from_utf8_lossy(&output.stderr)
-    );
+    let text = format!("{}\n{}", output.stdout, output.stderr);
 
     Ok(extract_schemes(&text))
 }
@@ -917,33 +1861,21 @@ fn pick_dev_dis_schemes(schemes: &[String]) -> (Option<String>, Option<String>)
 }
 
 fn resolve_build_setting(
+    runner: &dyn CommandRunner,
+    env: &LoginEnv,
     root: &Path,
     workspace: Option<&str>,
     xcodeproj: Option<&str>,
     scheme: &str,
     key: &str,
 ) -> Option<String> {
-    let target_arg = if let Some(ws) = workspace {
-        format!("-workspace '{}'", escape_single_quote(ws))
-    } else if let Some(proj) = xcodeproj {
-        format!("-project '{}'", escape_single_quote(proj))
-    } else {
-        return None;
-    };
-
-    let cmd = format!(
-        "cd '{}' && xcodebuild -showBuildSettings {} -scheme '{}'",
-        escape_single_quote(&root.to_string_lossy()),
-        target_arg,
-        escape_single_quote(scheme)
-    );
-
-    let output = Command::new("/bin/zsh").arg("-lc").arg(cmd).output().ok()?;
-    if !output.status.success() {
+    let mut cmd = xcodebuild_command(env, root, workspace, xcodeproj, "-showBuildSettings")?;
+    cmd.arg("-scheme").arg(scheme);
+    let output = runner.output(&cmd).ok()?;
+    if !output.success() {
         return None;
     }
-    let text = String::from_utf8_lossy(&output.stdout);
-    extract_build_setting(&text, key)
+    extract_build_setting(&output.stdout, key)
 }
 
 fn extract_build_setting(output: &str, key: &str) -> Option<String> {
@@ -960,18 +1892,13 @@ fn extract_build_setting(output: &str, key: &str) -> Option<String> {
     None
 }
 
-fn check_cmd(
-    name: &str,
-    program: &str,
-    args: &[&str],
-    suggestion: Option<&str>,
-) -> DoctorCheck {
-    let out = Command::new(program).args(args).output();
+fn check_cmd(runner: &dyn CommandRunner, name: &str, cmd: &CommandSpec, suggestion: Option<&str>) -> DoctorCheck {
+    let out = runner.output(cmd);
     match out {
         Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
-            if output.status.success() {
+            let stdout = output.stdout.trim().to_string();
+            let stderr = output.stderr.trim().to_string();
+            if output.success() {
                 let detail = if stdout.is_empty() { "ok".to_string() } else { stdout };
                 DoctorCheck {
                     name: name.to_string(),
@@ -984,7 +1911,11 @@ fn check_cmd(
                 DoctorCheck {
                     name: name.to_string(),
                     status: "warn".to_string(),
-                    detail: if detail.is_empty() { "command failed".to_string() } else { detail },
+                    detail: if detail.is_empty() {
+                        "command failed".to_string()
+                    } else {
+                        detail
+                    },
                     suggestion: suggestion.map(|s| s.to_string()),
                 }
             }
@@ -998,91 +1929,113 @@ fn check_cmd(
     }
 }
 
+fn check_gemfile(root: &Path) -> DoctorCheck {
+    let gemfile = root.join("Gemfile");
+    let exists = gemfile.is_file();
+    DoctorCheck {
+        name: "Gemfile".to_string(),
+        status: if exists { "pass" } else { "warn" }.to_string(),
+        detail: if exists {
+            "ok".to_string()
+        } else {
+            format!("{} not found", gemfile.display())
+        },
+        suggestion: (!exists).then(|| "Create Gemfile to manage fastlane gems consistently.".to_string()),
+    }
+}
+
+/// The external script is optional: only the configFile and interactive
+/// modes need it.
+fn check_bootstrap_script(app: &AppHandle, runner: &dyn CommandRunner, env: &LoginEnv) -> DoctorCheck {
+    let (status, detail, suggestion) = match bootstrap_script::discover(app, runner, env) {
+        Ok(script) => ("pass", script.describe(), None),
+        Err(err) => ("warn", err.message, err.hint),
+    };
+    DoctorCheck {
+        name: "Bootstrap Script".to_string(),
+        status: status.to_string(),
+        detail,
+        suggestion,
+    }
+}
+
 fn resolve_identity_internal(
+    runner: &dyn CommandRunner,
+    env: &LoginEnv,
     root: &Path,
     workspace: Option<&str>,
     xcodeproj: Option<&str>,
     scheme_dev: Option<String>,
     scheme_dis: Option<String>,
 ) -> IdentityResult {
-    let bundle_id_dev = scheme_dev
-        .as_deref()
-        .and_then(|scheme| resolve_build_setting(root, workspace, xcodeproj, scheme, "PRODUCT_BUNDLE_IDENTIFIER"));
-    let bundle_id_dis = scheme_dis
-        .as_deref()
-        .and_then(|scheme| resolve_build_setting(root, workspace, xcodeproj, scheme, "PRODUCT_BUNDLE_IDENTIFIER"));
-    let team_id_dev = scheme_dev
-        .as_deref()
-        .and_then(|scheme| resolve_build_setting(root, workspace, xcodeproj, scheme, "DEVELOPMENT_TEAM"));
-    let team_id_dis = scheme_dis
-        .as_deref()
-        .and_then(|scheme| resolve_build_setting(root, workspace, xcodeproj, scheme, "DEVELOPMENT_TEAM"));
+    // Read project.pbxproj and its xcconfigs first and only ask xcodebuild for
+    // values the project files cannot resolve on their own (e.g. SDK settings).
+    let project = xcodeproj.and_then(|proj| load_project_model(root, proj));
+    let containers = scheme_containers(root, workspace, xcodeproj);
+    let dev = scheme_dev.map(|name| (xcscheme::find_scheme(&containers, &name), name));
+    let dis = scheme_dis.map(|name| (xcscheme::find_scheme(&containers, &name), name));
+    let mut sources = Vec::new();
+    let mut setting = |scheme: &Option<(Option<Scheme>, String)>, key: &str| {
+        let (scheme_file, name) = scheme.as_ref()?;
+        let from_model = project
+            .as_ref()
+            .and_then(|model| resolve_build_setting_from_model(model, name, scheme_file.as_ref(), key));
+        let (value, setting) = match from_model {
+            Some(resolved) => (resolved.value.clone(), Some(resolved)),
+            None => (
+                resolve_build_setting(runner, env, root, workspace, xcodeproj, name, key)?,
+                None,
+            ),
+        };
+        sources.push(IdentitySource {
+            scheme: name.clone(),
+            key: key.to_string(),
+            value: value.clone(),
+            setting,
+        });
+        Some(value)
+    };
+
+    let bundle_id_dev = setting(&dev, "PRODUCT_BUNDLE_IDENTIFIER");
+    let bundle_id_dis = setting(&dis, "PRODUCT_BUNDLE_IDENTIFIER");
+    let team_id_dev = setting(&dev, "DEVELOPMENT_TEAM");
+    let team_id_dis = setting(&dis, "DEVELOPMENT_TEAM");
 
     IdentityResult {
         bundle_id_dev,
         bundle_id_dis,
         team_id: team_id_dis.or(team_id_dev),
+        sources,
     }
 }
 
-fn validate_fastfile_container_config(project_path: &str, lane: &str) -> Result<(), String> {
-    if !lane_requires_project_container(lane) {
-        return Ok(());
-    }
-
+fn validate_fastfile_container_config(project_path: &str) -> AppResult<()> {
     let fastfile = PathBuf::from(project_path).join("fastlane").join("Fastfile");
     if !fastfile.exists() {
         return Ok(());
     }
 
-    let content = fs::read_to_string(&fastfile)
-        .map_err(|e| format!("Read Fastfile failed: {}", e))?;
+    let content = fs::read_to_string(&fastfile).map_err(|e| AppError::io("Read Fastfile", &fastfile, e))?;
 
-    let workspace = extract_fastfile_value(&content, "WORKSPACE").unwrap_or_default();
-    let xcodeproj = extract_fastfile_value(&content, "XCODEPROJ").unwrap_or_default();
+    let workspace = fastfile_constants::get(&content, "WORKSPACE").unwrap_or_default();
+    let xcodeproj = fastfile_constants::get(&content, "XCODEPROJ").unwrap_or_default();
     let workspace_ok = path_exists_from_project(project_path, &workspace);
     let xcodeproj_ok = path_exists_from_project(project_path, &xcodeproj);
 
     if !workspace_ok && !xcodeproj_ok {
-        return Err(format!(
-            "Fastfile container paths are invalid. WORKSPACE='{}' exists={} | XCODEPROJ='{}' exists={}. Please click Scan, confirm workspace/xcodeproj, then Generate Files again.",
-            workspace,
-            workspace_ok,
-            xcodeproj,
-            xcodeproj_ok
-        ));
+        return Err(AppError::new(
+            ErrorKind::InvalidContainerConfig,
+            format!(
+                "Fastfile container paths are invalid. WORKSPACE='{}' exists={} | XCODEPROJ='{}' exists={}.",
+                workspace, workspace_ok, xcodeproj, xcodeproj_ok
+            ),
+        )
+        .with_hint("Click Scan, confirm workspace/xcodeproj, then Generate Files again.")
+        .with_path(&fastfile));
     }
     Ok(())
 }
 
-fn lane_requires_project_container(lane: &str) -> bool {
-    matches!(
-        lane,
-        "dev"
-            | "dis"
-            | "staging"
-            | "prod"
-            | "release_testflight"
-            | "release_appstore"
-            | "ci_build_dev"
-            | "ci_build_dis"
-            | "snapshot_capture"
-    )
-}
-
-fn extract_fastfile_value(content: &str, key: &str) -> Option<String> {
-    let prefix = format!("{} = \"", key);
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if let Some(rest) = trimmed.strip_prefix(&prefix) {
-            if let Some(end_idx) = rest.find('"') {
-                return Some(rest[..end_idx].to_string());
-            }
-        }
-    }
-    None
-}
-
 fn path_exists_from_project(project_path: &str, path_value: &str) -> bool {
     let trimmed = path_value.trim();
     if trimmed.is_empty() {
@@ -1097,53 +2050,43 @@ fn path_exists_from_project(project_path: &str, path_value: &str) -> bool {
     PathBuf::from(project_path).join(candidate).exists()
 }
 
-fn sync_fastfile_container_config(project_path: &str) -> Result<(), String> {
-    let project_root = PathBuf::from(project_path);
+/// Only when WORKSPACE/XCODEPROJ point at paths that do not exist (e.g. after
+/// the project moved); values that resolve are left to the user.
+fn fastfile_container_fix(project_root: &Path) -> AppResult<Option<RenderedFile>> {
     let fastfile = project_root.join("fastlane").join("Fastfile");
     if !fastfile.exists() {
-        return Ok(());
+        return Ok(None);
     }
 
-    let detected_workspace = to_absolute_from_project(
-        &project_root,
-        &find_first_with_ext(&project_root, "xcworkspace").unwrap_or_default(),
-    );
-    let detected_xcodeproj = to_absolute_from_project(
-        &project_root,
-        &find_first_with_ext(&project_root, "xcodeproj").unwrap_or_default(),
-    );
-
-    let content = fs::read_to_string(&fastfile)
-        .map_err(|e| format!("Read Fastfile failed: {}", e))?;
-
-    let mut changed = false;
-    let mut new_lines = Vec::new();
-    for line in content.lines() {
-        let trimmed = line.trim_start();
-        let indent_len = line.len() - trimmed.len();
-        let indent = &line[..indent_len];
-        if trimmed.starts_with("WORKSPACE = ") {
-            new_lines.push(format!("{indent}WORKSPACE = \"{}\"", detected_workspace));
-            changed = true;
-            continue;
-        }
-        if trimmed.starts_with("XCODEPROJ = ") {
-            new_lines.push(format!("{indent}XCODEPROJ = \"{}\"", detected_xcodeproj));
-            changed = true;
-            continue;
-        }
-        new_lines.push(line.to_string());
+    let content = fs::read_to_string(&fastfile).map_err(|e| AppError::io("Read Fastfile", &fastfile, e))?;
+    let project_path = project_root.to_string_lossy();
+    let current = ["WORKSPACE", "XCODEPROJ"].map(|name| fastfile_constants::get(&content, name).unwrap_or_default());
+    let exists = current
+        .clone()
+        .map(|value| path_exists_from_project(&project_path, &value));
+    let stale = current
+        .iter()
+        .zip(exists)
+        .any(|(value, exists)| !value.trim().is_empty() && !exists);
+    if !stale && exists.contains(&true) {
+        return Ok(None);
     }
 
-    if changed {
-        let mut new_content = new_lines.join("\n");
-        if content.ends_with('\n') {
-            new_content.push('\n');
-        }
-        fs::write(&fastfile, new_content)
-            .map_err(|e| format!("Write Fastfile sync failed: {}", e))?;
+    let (workspace, xcodeproj) = selected_container(&ranked_containers(project_root, project_root));
+    let detected_workspace = to_absolute_from_project(project_root, &workspace.unwrap_or_default());
+    let detected_xcodeproj = to_absolute_from_project(project_root, &xcodeproj.unwrap_or_default());
+    let update = fastfile_constants::update(
+        &content,
+        &[("WORKSPACE", &detected_workspace), ("XCODEPROJ", &detected_xcodeproj)],
+    );
+    if update.updated.is_empty() {
+        return Ok(None);
     }
-    Ok(())
+    Ok(Some(RenderedFile {
+        path: PathBuf::from("fastlane").join("Fastfile"),
+        content: update.content,
+        executable: false,
+    }))
 }
 
 fn lane_preflight_report(project_path: &str, extra_note: Option<&str>) -> String {
@@ -1161,8 +2104,8 @@ fn lane_preflight_report(project_path: &str, extra_note: Option<&str>) -> String
 
     match fs::read_to_string(&fastfile) {
         Ok(content) => {
-            let ws = extract_fastfile_value(&content, "WORKSPACE").unwrap_or_default();
-            let xp = extract_fastfile_value(&content, "XCODEPROJ").unwrap_or_default();
+            let ws = fastfile_constants::get(&content, "WORKSPACE").unwrap_or_default();
+            let xp = fastfile_constants::get(&content, "XCODEPROJ").unwrap_or_default();
             let ws_exists = path_exists_from_project(project_path, &ws);
             let xp_exists = path_exists_from_project(project_path, &xp);
             lines.push(format!("[preflight] WORKSPACE='{}' exists={}", ws, ws_exists));
@@ -1177,78 +2120,166 @@ fn lane_preflight_report(project_path: &str, extra_note: Option<&str>) -> String
     lines.join("\n")
 }
 
-fn detect_test_action_and_maybe_disable_tests(project_path: &str, lane: &str) -> (bool, Option<String>) {
-    if !lane_runs_quality_gate(lane) {
-        return (false, None);
+/// When the lane was found in the Fastfile, each key must be one the lane reads.
+fn lane_option_args(
+    project_root: &Path,
+    lane: &str,
+    info: Option<&Lane>,
+    options: &BTreeMap<String, LaneOptionValue>,
+) -> AppResult<Vec<String>> {
+    let mut args = Vec::new();
+    for (key, value) in options {
+        let valid_key = key.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_key {
+            return Err(
+                AppError::new(ErrorKind::InvalidInput, format!("Invalid lane option name: '{}'", key))
+                    .with_hint("Option names are Ruby symbols such as build_number or skip_upload."),
+            );
+        }
+        if let Some(info) = info {
+            if !info.takes_options {
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!("Lane '{}' does not take options, but '{}' was given.", lane, key),
+                )
+                .with_hint("Declare the lane as `lane :name do |options|` to read options.")
+                .with_path(project_root.join(&info.file)));
+            }
+            if !info.forwards_options && !info.options.contains(key) {
+                let known = if info.options.is_empty() {
+                    "none".to_string()
+                } else {
+                    info.options.join(", ")
+                };
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Lane '{}' does not read option '{}'. Options it reads: {}.",
+                        lane, key, known
+                    ),
+                )
+                .with_path(project_root.join(&info.file)));
+            }
+        }
+        args.push(format!("{}:{}", key, value.to_arg_value()));
     }
+    Ok(args)
+}
+
+fn lane_metadata_report(lanes: &FastfileLanes, lane: &str) -> String {
+    let mut lines = Vec::new();
+    match lanes.find(lane) {
+        Some(info) => lines.push(format!(
+            "[preflight] lane={} platform={} file={}:{} container={} tests={} scheme={}",
+            info.name,
+            info.platform.as_deref().unwrap_or("-"),
+            info.file,
+            info.line,
+            info.uses_container,
+            info.runs_tests,
+            info.scheme_constant.as_deref().unwrap_or("-")
+        )),
+        None => lines.push(format!(
+            "[preflight] lane={} not found in the Fastfile or its local imports",
+            lane
+        )),
+    }
+    if !lanes.unresolved_imports.is_empty() {
+        lines.push(format!(
+            "[preflight] unresolved_imports={}",
+            lanes.unresolved_imports.join(", ")
+        ));
+    }
+    lines.join("\n")
+}
+
+struct TestActionDecision {
+    disable_tests: bool,
+    note: Option<String>,
+    /// Whether the decision came from a parsed `.xcscheme` rather than a guess.
+    from_scheme_file: bool,
+}
+
+fn detect_test_action_and_maybe_disable_tests(
+    runner: &dyn CommandRunner,
+    env: &LoginEnv,
+    project_path: &str,
+    lane: Option<&Lane>,
+) -> TestActionDecision {
+    let undecided = TestActionDecision {
+        disable_tests: false,
+        note: None,
+        from_scheme_file: false,
+    };
+    let Some(lane) = lane.filter(|lane| lane.runs_tests) else {
+        return undecided;
+    };
 
     let fastfile = PathBuf::from(project_path).join("fastlane").join("Fastfile");
     let content = match fs::read_to_string(&fastfile) {
         Ok(v) => v,
-        Err(_) => return (false, None),
-    };
-    let workspace = extract_fastfile_value(&content, "WORKSPACE").unwrap_or_default();
-    let xcodeproj = extract_fastfile_value(&content, "XCODEPROJ").unwrap_or_default();
-    let scheme_dev = extract_fastfile_value(&content, "SCHEME_DEV").unwrap_or_default();
-    let scheme_dis = extract_fastfile_value(&content, "SCHEME_DIS").unwrap_or_default();
-
-    let scheme = match lane {
-        "dev" | "ci_build_dev" => scheme_dev,
-        "dis" | "staging" | "prod" | "release_testflight" | "release_appstore" | "ci_build_dis" => scheme_dis,
-        _ => scheme_dev,
+        Err(_) => return undecided,
     };
+    let workspace = fastfile_constants::get(&content, "WORKSPACE").unwrap_or_default();
+    let xcodeproj = fastfile_constants::get(&content, "XCODEPROJ").unwrap_or_default();
+    let scheme_constant = lane.scheme_constant.as_deref().unwrap_or("SCHEME_DEV");
+    let scheme = fastfile_constants::get(&content, scheme_constant).unwrap_or_default();
     if scheme.trim().is_empty() {
-        return (false, None);
+        return undecided;
     }
 
-    let container_arg = if path_exists_from_project(project_path, &workspace) {
-        format!("-workspace '{}'", escape_single_quote(&workspace))
-    } else if path_exists_from_project(project_path, &xcodeproj) {
-        format!("-project '{}'", escape_single_quote(&xcodeproj))
-    } else {
-        return (false, None);
-    };
-
-    let cmd = format!(
-        "cd '{}' && xcodebuild -showTestPlans {} -scheme '{}' >/dev/null 2>&1",
-        escape_single_quote(project_path),
-        container_arg,
-        escape_single_quote(&scheme)
+    let workspace_exists = path_exists_from_project(project_path, &workspace);
+    let xcodeproj_exists = path_exists_from_project(project_path, &xcodeproj);
+    let containers = scheme_containers(
+        Path::new(project_path),
+        Some(workspace.as_str()).filter(|_| workspace_exists),
+        Some(xcodeproj.as_str()).filter(|_| xcodeproj_exists),
     );
-    let ok = Command::new("/bin/zsh")
-        .arg("-lc")
-        .arg(cmd)
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false);
+    if let Some(scheme_file) = xcscheme::find_scheme(&containers, &scheme) {
+        if scheme_file.has_test_action() {
+            return TestActionDecision {
+                from_scheme_file: true,
+                ..undecided
+            };
+        }
+        return TestActionDecision {
+            disable_tests: true,
+            note: Some(format!(
+                "Scheme '{}' ({}) has no test action. Auto override ENABLE_TESTS=false for this run.",
+                scheme, scheme_file.path
+            )),
+            from_scheme_file: true,
+        };
+    }
+
+    // Autocreated schemes have no file on disk; ask xcodebuild instead.
+    let Some(mut cmd) = xcodebuild_command(
+        env,
+        Path::new(project_path),
+        Some(workspace.as_str()).filter(|_| workspace_exists),
+        Some(xcodeproj.as_str()).filter(|_| xcodeproj_exists),
+        "-showTestPlans",
+    ) else {
+        return undecided;
+    };
+    cmd.arg("-scheme").arg(&scheme);
+    let ok = runner.output(&cmd).map(|output| output.success()).unwrap_or(false);
 
     if ok {
-        (false, None)
+        undecided
     } else {
-        (
-            true,
-            Some(format!(
+        TestActionDecision {
+            disable_tests: true,
+            note: Some(format!(
                 "Detected scheme '{}' without test action. Auto override ENABLE_TESTS=false for this run.",
                 scheme
             )),
-        )
+            from_scheme_file: false,
+        }
     }
 }
 
-fn lane_runs_quality_gate(lane: &str) -> bool {
-    matches!(
-        lane,
-        "dev"
-            | "dis"
-            | "staging"
-            | "prod"
-            | "release_testflight"
-            | "release_appstore"
-            | "ci_build_dev"
-            | "ci_build_dis"
-    )
-}
-
 fn to_absolute_from_project(project_root: &Path, value: &str) -> String {
     let trimmed = value.trim();
     if trimmed.is_empty() {
@@ -1262,3 +2293,323 @@ fn to_absolute_from_project(project_root: &Path, value: &str) -> String {
 
     project_root.join(path).to_string_lossy().to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::ReplayRunner;
+    use crate::environments::EnvValue;
+    use crate::lane_run::LaneStream;
+    use crate::test_support::TestDir;
+
+    const FASTFILE: &str = r#"WORKSPACE = ""
+XCODEPROJ = "MyApp.xcodeproj"
+SCHEME_DEV = "{scheme}"
+
+default_platform(:ios)
+
+platform :ios do
+  lane :test do
+    run_tests(project: XCODEPROJ, scheme: SCHEME_DEV)
+  end
+end
+"#;
+
+    /// Recorded output of the commands below, taken with `HOME=/Users/dev`.
+    fn fixtures() -> ReplayRunner {
+        ReplayRunner::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/commands"))
+    }
+
+    /// An xcodeproj without a readable `project.pbxproj` or scheme files, so
+    /// schemes and settings have to come from xcodebuild.
+    fn xcodebuild_only_project(scheme: &str) -> TestDir {
+        TestDir::with_files(&[
+            ("MyApp.xcodeproj/README", ""),
+            ("fastlane/Fastfile", &FASTFILE.replace("{scheme}", scheme)),
+        ])
+    }
+
+    fn replay_lane(runner: &dyn CommandRunner, spec: &CommandSpec) -> CommandExit {
+        let mut output = String::new();
+        let exit_code = runner
+            .stream(spec, &mut |_| {}, &mut |_: LaneStream, line| {
+                output.push_str(&line);
+                output.push('\n');
+            })
+            .unwrap();
+        CommandExit {
+            success: exit_code == 0,
+            exit_code,
+            output,
+        }
+    }
+
+    fn test_lane_command(env: &LoginEnv, root: &Path, disable_tests: bool) -> CommandSpec {
+        let invocation = LaneInvocation {
+            platform: Some("ios"),
+            lane: "test",
+            options: &[],
+            env_arg: None,
+            secrets: &[],
+        };
+        lane_command(env, &ruby_env::resolve(env, root), root, invocation, disable_tests)
+    }
+
+    #[test]
+    fn scan_falls_back_to_xcodebuild_for_schemes_and_identity() {
+        let runner = fixtures();
+        let env = LoginEnv::capture(&runner, "/bin/zsh");
+        assert!(env.summary().captured);
+        let root = xcodebuild_only_project("MyApp Dev");
+
+        let result = scan(&runner, &env, &root).unwrap();
+
+        assert_eq!(result.containers[0].path, "MyApp.xcodeproj");
+        assert_eq!(result.schemes, ["MyApp Dev", "MyApp Prod"]);
+        assert_eq!(result.bundle_id_dev.as_deref(), Some("com.example.myapp.dev"));
+        assert_eq!(result.bundle_id_dis.as_deref(), Some("com.example.myapp"));
+        assert_eq!(result.team_id.as_deref(), Some("ABCDE12345"));
+    }
+
+    #[test]
+    fn doctor_reports_recorded_tools_and_warns_about_missing_ones() {
+        let runner = fixtures();
+        let env = LoginEnv::capture(&runner, "/bin/zsh");
+        let root = TestDir::with_files(&[("Gemfile", "source \"https://rubygems.org\"\n\ngem \"fastlane\"\n")]);
+
+        let checks = tool_checks(&runner, &env, &root);
+        let check = |name: &str| {
+            let check = checks.iter().find(|check| check.name == name).unwrap();
+            (check.status.as_str(), check.detail.as_str())
+        };
+
+        assert_eq!(
+            check("Xcode CLI"),
+            ("pass", "/Applications/Xcode.app/Contents/Developer")
+        );
+        assert_eq!(check("Xcode Build"), ("pass", "Xcode 16.2\nBuild version 16C5032a"));
+        assert_eq!(check("Ruby Compatibility"), ("pass", "Ruby 3.3.6 compatible"));
+        assert_eq!(check("Bundler"), ("pass", "Bundler version 2.5.22"));
+        assert_eq!(check("Gemfile"), ("pass", "ok"));
+        // Not recorded: replays as a program that cannot be started.
+        assert_eq!(check("Fastlane").0, "warn");
+        assert!(check("Fastlane").1.starts_with("failed to execute"));
+        assert_eq!(
+            check("CocoaPods"),
+            ("warn", "cannot load such file -- cocoapods (LoadError)")
+        );
+    }
+
+    #[test]
+    fn lane_is_retried_without_tests_when_the_scheme_has_no_test_action() {
+        let runner = fixtures();
+        let env = LoginEnv::capture(&runner, "/bin/zsh");
+        let root = xcodebuild_only_project("MyApp Dev");
+        let project_path = root.to_string_lossy().to_string();
+        let lanes = fastfile_lanes::list(&root).unwrap();
+        let lane = lanes.find("test").unwrap();
+        assert!(lane.runs_tests);
+
+        // `xcodebuild -showTestPlans` succeeds, so the first run keeps tests on.
+        let decision = detect_test_action_and_maybe_disable_tests(&runner, &env, &project_path, Some(lane));
+        assert!(!decision.disable_tests);
+        assert!(!decision.from_scheme_file);
+
+        let first = replay_lane(&runner, &test_lane_command(&env, &root, decision.disable_tests));
+        assert!(!first.success);
+        assert!(retry_without_tests(&first, lane.runs_tests, &decision));
+
+        let retry = replay_lane(&runner, &test_lane_command(&env, &root, true));
+        assert!(retry.success);
+        assert!(!retry_without_tests(&retry, lane.runs_tests, &decision));
+    }
+
+    #[test]
+    fn tests_are_disabled_up_front_for_a_scheme_without_test_plans() {
+        let runner = fixtures();
+        let env = LoginEnv::capture(&runner, "/bin/zsh");
+        let root = xcodebuild_only_project("MyApp Widget");
+        let project_path = root.to_string_lossy().to_string();
+        let lanes = fastfile_lanes::list(&root).unwrap();
+        let lane = lanes.find("test");
+
+        let decision = detect_test_action_and_maybe_disable_tests(&runner, &env, &project_path, lane);
+        assert!(decision.disable_tests);
+        assert!(decision.note.unwrap().contains("'MyApp Widget' without test action"));
+
+        let spec = test_lane_command(&env, &root, decision.disable_tests);
+        assert_eq!(spec.env.get("ENABLE_TESTS").map(String::as_str), Some("false"));
+        assert!(replay_lane(&runner, &spec).success);
+    }
+
+    #[test]
+    fn tests_are_not_disabled_for_lanes_that_do_not_run_them() {
+        let runner = fixtures();
+        let env = LoginEnv::capture(&runner, "/bin/zsh");
+        let root = xcodebuild_only_project("MyApp Widget");
+        let project_path = root.to_string_lossy().to_string();
+
+        let decision = detect_test_action_and_maybe_disable_tests(&runner, &env, &project_path, None);
+        assert!(!decision.disable_tests);
+        let failed = CommandExit {
+            success: false,
+            exit_code: 1,
+            output: "Scheme MyApp is not currently configured for the test action.".to_string(),
+        };
+        assert!(!retry_without_tests(&failed, false, &decision));
+    }
+
+    #[test]
+    fn output_is_redacted_with_every_secret_login_variable() {
+        let runner = fixtures();
+        let env = LoginEnv::capture(&runner, "/bin/zsh");
+        assert!(!env.summary().variables.contains_key("SLACK_URL"));
+        let root = TestDir::with_files(&[]);
+
+        let mut redactor = output_redactor(&env, &root, None, &[]);
+
+        assert_eq!(
+            redactor.line("posting to https://hooks.slack.com/services/T000/B000/abcdef"),
+            "posting to [REDACTED]"
+        );
+        assert_eq!(redactor.line("HOME=/Users/dev"), "HOME=/Users/dev");
+    }
+
+    #[test]
+    fn variables_bound_to_secret_refs_are_redacted_whatever_their_name() {
+        let runner = fixtures();
+        let env = LoginEnv::capture(&runner, "/bin/zsh");
+        let root = TestDir::with_files(&[("fastlane/.env.fastlane", "DEPLOY_HOOK=https://hooks.example.com/abc\n")]);
+        let config = ProjectConfig {
+            secret_refs: vec![SecretRef {
+                key: "DEPLOY_HOOK".to_string(),
+                secret: "deploy-hook".to_string(),
+            }],
+            ..ProjectConfig::default()
+        };
+        let line = "POST https://hooks.example.com/abc failed";
+
+        assert_eq!(output_redactor(&env, &root, None, &[]).line(line), line);
+        assert_eq!(
+            output_redactor(&env, &root, Some(&config), &[]).line(line),
+            "POST [REDACTED] failed"
+        );
+    }
+
+    #[test]
+    fn backup_diffs_and_conflicts_mask_secret_values() {
+        let env_file = "SCHEME=MyApp\nPGYER_API_KEY=old-pgyer-key\nDEPLOY_HOOK=https://hooks.example.com/old\n";
+        let root = TestDir::with_files(&[("fastlane/.env.fastlane", env_file)]);
+        let backup = backups::snapshot(&root, "test", &["fastlane/.env.fastlane"]).unwrap();
+        fs::write(root.join("fastlane/.env.fastlane"), "SCHEME=Edited\n").unwrap();
+        let config = ProjectConfig {
+            secret_refs: vec![SecretRef {
+                key: "DEPLOY_HOOK".to_string(),
+                secret: "deploy-hook".to_string(),
+            }],
+            ..ProjectConfig::default()
+        };
+
+        let mut files = backups::diff_backup(&root, &backup.backup_id).unwrap();
+        let mut redactor = Redactor::default();
+        redact_diffs(&mut redactor, Some(&config), &mut files);
+
+        let diff = &files[0].diff;
+        assert!(diff.contains("+SCHEME=MyApp"));
+        // Blanked in the backup itself, see `backups::snapshot`.
+        assert!(diff.contains("+PGYER_API_KEY=\n"));
+        assert!(diff.contains("+DEPLOY_HOOK=[REDACTED]"));
+        assert!(!diff.contains("old"));
+
+        let conflict = |key: &str, value: &str| EnvConflict {
+            path: RUNTIME_ENV_PATH.to_string(),
+            key: key.to_string(),
+            file_value: value.to_string(),
+            app_value: "app".to_string(),
+        };
+        let mut conflicts = vec![
+            conflict("DEPLOY_HOOK", "short"),
+            conflict("SCHEME", "https://hooks.example.com/old"),
+        ];
+        redact_conflicts(&mut redactor, Some(&config), &mut conflicts);
+        assert_eq!(
+            (conflicts[0].file_value.as_str(), conflicts[0].app_value.as_str()),
+            ("[REDACTED]", "[REDACTED]")
+        );
+        assert_eq!(
+            (conflicts[1].file_value.as_str(), conflicts[1].app_value.as_str()),
+            ("[REDACTED]", "app")
+        );
+    }
+
+    #[test]
+    fn saved_profiles_leave_out_secret_values() {
+        let value = |key: &str, value: &str| EnvValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let config = ProjectConfig {
+            pgyer_api_key: "pgyer-key-123".to_string(),
+            match_git_url: "git@example.com:certs.git".to_string(),
+            secret_refs: vec![SecretRef {
+                key: "DEPLOY_HOOK".to_string(),
+                secret: "deploy-hook".to_string(),
+            }],
+            environments: vec![LaneEnvironment {
+                name: "staging".to_string(),
+                values: vec![
+                    value("API_HOST", "staging.example.com"),
+                    value("DEPLOY_HOOK", "https://hooks.example.com/abc"),
+                    value("SENTRY_AUTH_TOKEN", "sntrys_abc"),
+                ],
+            }],
+            ..ProjectConfig::default()
+        };
+
+        let saved = config.without_secret_values();
+
+        assert_eq!(saved.pgyer_api_key, "");
+        assert_eq!(saved.match_git_url, "");
+        let values = saved.environments[0]
+            .values
+            .iter()
+            .map(|value| value.value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["staging.example.com", "", ""]);
+    }
+
+    #[test]
+    fn gemfile_fix_is_proposed_only_while_the_pluginfile_is_not_loaded() {
+        let gemfile = "source \"https://rubygems.org\"\n\ngem \"fastlane\"\n";
+        let root = TestDir::with_files(&[("Gemfile", gemfile)]);
+        assert!(gemfile_fix(&root).unwrap().is_none());
+
+        fs::create_dir_all(root.join("fastlane")).unwrap();
+        fs::write(root.join("fastlane/Pluginfile"), "gem \"fastlane-plugin-pgyer\"\n").unwrap();
+        let fix = gemfile_fix(&root).unwrap().unwrap();
+        assert_eq!(fix.path, Path::new("Gemfile"));
+        assert!(fix.content.starts_with(gemfile));
+        assert!(fix.content.contains("Pluginfile"));
+        // Nothing is written until the preview is applied.
+        assert_eq!(fs::read_to_string(root.join("Gemfile")).unwrap(), gemfile);
+
+        fs::write(root.join("Gemfile"), &fix.content).unwrap();
+        assert!(gemfile_fix(&root).unwrap().is_none());
+    }
+
+    #[test]
+    fn fastfile_container_fix_replaces_only_paths_that_no_longer_exist() {
+        let root = xcodebuild_only_project("MyApp Dev");
+        assert!(fastfile_container_fix(&root).unwrap().is_none());
+
+        let moved = FASTFILE
+            .replace("{scheme}", "MyApp Dev")
+            .replace("\"MyApp.xcodeproj\"", "\"Old/MyApp.xcodeproj\"");
+        fs::write(root.join("fastlane/Fastfile"), &moved).unwrap();
+        let fix = fastfile_container_fix(&root).unwrap().unwrap();
+        assert_eq!(fix.path, Path::new("fastlane/Fastfile"));
+        let xcodeproj = root.join("MyApp.xcodeproj").to_string_lossy().to_string();
+        assert_eq!(fastfile_constants::get(&fix.content, "XCODEPROJ"), Some(xcodeproj));
+        assert_eq!(fs::read_to_string(root.join("fastlane/Fastfile")).unwrap(), moved);
+    }
+}
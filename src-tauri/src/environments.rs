@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dotenv;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::runtime_env::ManagedFile;
+
+/// The environment whose values go into `fastlane/.env.fastlane` itself.
+pub const DEFAULT_ENVIRONMENT: &str = "default";
+
+const BASE_ENV: &str = "fastlane";
+
+/// Files fastlane loads from `fastlane/` on every run, without overriding
+/// variables that are already set.
+const AUTOLOADED_FILES: [&str; 2] = [".env", ".env.default"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneEnvironment {
+    pub name: String,
+    #[serde(default)]
+    pub values: Vec<EnvValue>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvValue {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveEnv {
+    /// What the app passes to `fastlane --env`, if anything.
+    pub env_arg: Option<String>,
+    /// The dotenv files fastlane reads for the run, in load order.
+    pub files: Vec<EnvFileStatus>,
+    pub values: Vec<EffectiveValue>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvFileStatus {
+    /// Relative to the project root.
+    pub path: String,
+    pub exists: bool,
+    /// True for `--env` files, whose values replace ones already set.
+    pub overrides: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveValue {
+    pub key: String,
+    pub value: String,
+    /// `login shell`, `app` or the file the value came from.
+    pub source: String,
+    /// Earlier sources whose value this one replaced.
+    pub overridden: Vec<String>,
+}
+
+pub fn env_file_path(name: &str) -> PathBuf {
+    Path::new("fastlane").join(format!(".env.{}.{}", BASE_ENV, name))
+}
+
+/// Names are used in file names and in `--env`, so they are limited to
+/// letters, digits, `-` and `_`.
+pub fn validate_name(name: &str) -> AppResult<()> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(
+            AppError::new(ErrorKind::InvalidInput, format!("Invalid environment name: '{}'", name))
+                .with_hint("Use letters, digits, '-' and '_', e.g. staging or prod."),
+        )
+    }
+}
+
+pub fn validate(environments: &[LaneEnvironment]) -> AppResult<()> {
+    let mut seen = HashSet::new();
+    for environment in environments {
+        validate_name(&environment.name)?;
+        if !seen.insert(environment.name.as_str()) {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                format!("Environment '{}' is defined twice.", environment.name),
+            ));
+        }
+        for value in environment.values.iter().filter(|value| !value.key.is_empty()) {
+            if !dotenv::is_valid_key(&value.key) {
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid variable name '{}' in environment '{}'.",
+                        value.key, environment.name
+                    ),
+                )
+                .with_hint("Use letters, digits and '_', not starting with a digit."));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn default_values(environments: &[LaneEnvironment]) -> impl Iterator<Item = &EnvValue> {
+    environments
+        .iter()
+        .filter(|environment| environment.name == DEFAULT_ENVIRONMENT)
+        .flat_map(|environment| environment.values.iter())
+        .filter(|value| !value.key.is_empty())
+}
+
+pub fn managed_files(environments: &[LaneEnvironment]) -> Vec<ManagedFile> {
+    environments
+        .iter()
+        .filter(|environment| environment.name != DEFAULT_ENVIRONMENT)
+        .map(|environment| ManagedFile {
+            path: env_file_path(&environment.name),
+            header: format!(
+                "Overrides for `--env {},{}.{}`, written by iOS Fastlane Desktop.",
+                BASE_ENV, BASE_ENV, environment.name
+            ),
+            values: environment
+                .values
+                .iter()
+                .filter(|value| !value.key.is_empty())
+                .map(|value| (value.key.clone(), value.value.clone()))
+                .collect(),
+        })
+        .collect()
+}
+
+pub fn env_arg(project_root: &Path, environment: Option<&str>) -> AppResult<Option<String>> {
+    let mut names = Vec::new();
+    if project_root
+        .join("fastlane")
+        .join(format!(".env.{}", BASE_ENV))
+        .is_file()
+    {
+        names.push(BASE_ENV.to_string());
+    }
+    if let Some(name) = environment.filter(|name| *name != DEFAULT_ENVIRONMENT) {
+        validate_name(name)?;
+        let path = project_root.join(env_file_path(name));
+        if !path.is_file() {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                format!("Environment '{}' has no env file.", name),
+            )
+            .with_hint("Add the environment in the config form and Generate Files.")
+            .with_path(&path));
+        }
+        names.push(format!("{}.{}", BASE_ENV, name));
+    }
+    Ok((!names.is_empty()).then(|| names.join(",")))
+}
+
+/// The variables a lane sees from the dotenv files, merged the way fastlane
+/// does it: the process environment (`login`, then `app`) first, then
+/// `.env` / `.env.default` without overriding, then the `--env` files in
+/// order, each overriding what came before. Login-shell variables are only
+/// listed when a file or the app sets them too.
+pub fn effective(
+    project_root: &Path,
+    environment: Option<&str>,
+    app: &[(&str, &str)],
+    login: impl Fn(&str) -> Option<String>,
+) -> AppResult<EffectiveEnv> {
+    let env_arg = env_arg(project_root, environment)?;
+    let fastlane_dir = project_root.join("fastlane");
+    let mut files = AUTOLOADED_FILES
+        .iter()
+        .map(|name| (name.to_string(), false))
+        .collect::<Vec<_>>();
+    if let Some(arg) = &env_arg {
+        files.extend(arg.split(',').map(|name| (format!(".env.{}", name), true)));
+    }
+
+    let mut values: Vec<EffectiveValue> = Vec::new();
+    let mut statuses = Vec::new();
+    let mut parsed = Vec::new();
+    for (name, overrides) in &files {
+        let path = fastlane_dir.join(name);
+        let relative = Path::new("fastlane").join(name).to_string_lossy().to_string();
+        let exists = path.is_file();
+        if exists {
+            let content = fs::read_to_string(&path).map_err(|e| AppError::io("Read env file", &path, e))?;
+            parsed.push((relative.clone(), *overrides, dotenv::parse(&content)));
+        }
+        statuses.push(EnvFileStatus {
+            path: relative,
+            exists,
+            overrides: *overrides,
+        });
+    }
+
+    let mut keys = app.iter().map(|(key, _)| key.to_string()).collect::<Vec<_>>();
+    for (_, _, pairs) in &parsed {
+        for (key, _) in pairs {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+    }
+    for key in &keys {
+        if let Some(value) = login(key) {
+            set(&mut values, key, value, "login shell", true);
+        }
+    }
+    for (key, value) in app {
+        set(&mut values, key, value.to_string(), "app", true);
+    }
+    for (source, overrides, pairs) in parsed {
+        for (key, value) in pairs {
+            set(&mut values, &key, value, &source, overrides);
+        }
+    }
+
+    Ok(EffectiveEnv {
+        env_arg,
+        files: statuses,
+        values,
+    })
+}
+
+fn set(values: &mut Vec<EffectiveValue>, key: &str, value: String, source: &str, overrides: bool) {
+    match values.iter_mut().find(|existing| existing.key == key) {
+        Some(existing) if overrides => {
+            let replaced = std::mem::replace(&mut existing.source, source.to_string());
+            existing.overridden.push(replaced);
+            existing.value = value;
+        }
+        Some(_) => {}
+        None => values.push(EffectiveValue {
+            key: key.to_string(),
+            value,
+            source: source.to_string(),
+            overridden: vec![],
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    fn value<'a>(env: &'a EffectiveEnv, key: &str) -> &'a EffectiveValue {
+        env.values.iter().find(|value| value.key == key).unwrap()
+    }
+
+    #[test]
+    fn env_arg_lists_the_base_file_and_the_selected_environment() {
+        let root = TestDir::with_files(&[]);
+        assert_eq!(env_arg(&root, None).unwrap(), None);
+        assert_eq!(env_arg(&root, Some(DEFAULT_ENVIRONMENT)).unwrap(), None);
+
+        root.write("fastlane/.env.fastlane", "SCHEME=MyApp\n");
+        root.write(
+            "fastlane/.env.fastlane.staging",
+            "API_URL=https://staging.example.com\n",
+        );
+        assert_eq!(
+            env_arg(&root, Some(DEFAULT_ENVIRONMENT)).unwrap().as_deref(),
+            Some("fastlane")
+        );
+        assert_eq!(
+            env_arg(&root, Some("staging")).unwrap().as_deref(),
+            Some("fastlane,fastlane.staging")
+        );
+
+        let error = env_arg(&root, Some("prod")).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidInput);
+        assert_eq!(error.message, "Environment 'prod' has no env file.");
+        assert_eq!(
+            env_arg(&root, Some("../prod")).unwrap_err().kind,
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn effective_values_follow_fastlane_precedence() {
+        let root = TestDir::with_files(&[
+            (
+                "fastlane/.env",
+                "SHELL_ONLY=from-dotenv\nDOTENV_ONLY=dotenv\nAPI_URL=from-dotenv\n",
+            ),
+            ("fastlane/.env.default", "DOTENV_ONLY=default\nDEFAULT_ONLY=default\n"),
+            ("fastlane/.env.fastlane", "API_URL=https://example.com\nSCHEME=MyApp\n"),
+            (
+                "fastlane/.env.fastlane.staging",
+                "API_URL=https://staging.example.com\n",
+            ),
+        ]);
+        let login = |key: &str| match key {
+            "SHELL_ONLY" | "SCHEME" => Some(format!("login-{}", key)),
+            _ => None,
+        };
+
+        let env = effective(&root, Some("staging"), &[("SCHEME", "app-scheme")], login).unwrap();
+
+        assert_eq!(env.env_arg.as_deref(), Some("fastlane,fastlane.staging"));
+        let files = env
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.overrides))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            [
+                ("fastlane/.env", false),
+                ("fastlane/.env.default", false),
+                ("fastlane/.env.fastlane", true),
+                ("fastlane/.env.fastlane.staging", true),
+            ]
+        );
+
+        // The process environment wins over .env and .env.default.
+        let shell_only = value(&env, "SHELL_ONLY");
+        assert_eq!(
+            (shell_only.value.as_str(), shell_only.source.as_str()),
+            ("login-SHELL_ONLY", "login shell")
+        );
+        assert!(shell_only.overridden.is_empty());
+        // .env and .env.default do not override each other either.
+        assert_eq!(value(&env, "DOTENV_ONLY").value, "dotenv");
+        assert_eq!(value(&env, "DEFAULT_ONLY").source, "fastlane/.env.default");
+        // The app overrides the login shell, and --env files override both.
+        let scheme = value(&env, "SCHEME");
+        assert_eq!(scheme.value, "MyApp");
+        assert_eq!(scheme.source, "fastlane/.env.fastlane");
+        assert_eq!(scheme.overridden, ["login shell", "app"]);
+        let api_url = value(&env, "API_URL");
+        assert_eq!(api_url.value, "https://staging.example.com");
+        assert_eq!(api_url.overridden, ["fastlane/.env", "fastlane/.env.fastlane"]);
+        // Login-shell variables no file or app sets are not listed.
+        assert_eq!(env.values.len(), 5);
+    }
+
+    #[test]
+    fn missing_autoloaded_files_are_listed_as_absent() {
+        let root = TestDir::with_files(&[("fastlane/.env.fastlane", "SCHEME=MyApp\n")]);
+        let env = effective(&root, None, &[], |_| None).unwrap();
+        let files = env
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.exists))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            [
+                ("fastlane/.env", false),
+                ("fastlane/.env.default", false),
+                ("fastlane/.env.fastlane", true)
+            ]
+        );
+        assert!(effective(&root, Some("prod"), &[], |_| None).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Stable error codes the frontend branches on. Variants are serialized in
+/// camelCase and must not be renamed once shipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    ProjectNotFound,
+    ContainerNotFound,
+    InvalidContainerConfig,
+    BootstrapScriptNotFound,
+    BootstrapScriptIncompatible,
+    ProfileNotFound,
+    RunNotFound,
+    LaneNotRunning,
+    InvalidApiKey,
+    PreviewNotFound,
+    PreviewStale,
+    BackupNotFound,
+    SecretStoreLocked,
+    SecretNotFound,
+    InvalidInput,
+    Io,
+    Parse,
+    CommandFailed,
+    Internal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub hint: Option<String>,
+    pub path: Option<String>,
+    pub command: Option<String>,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            hint: None,
+            path: None,
+            command: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().display().to_string());
+        self
+    }
+
+    pub fn with_command(mut self, command: impl Into<String>) -> Self {
+        self.command = Some(command.into());
+        self
+    }
+
+    /// A filesystem operation (`action`, e.g. "Read Fastfile") failed on `path`.
+    pub fn io(action: &str, path: impl AsRef<Path>, err: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Io, format!("{} failed: {}", action, err)).with_path(path)
+    }
+
+    pub fn parse(what: &str, path: impl AsRef<Path>, err: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Parse, format!("Parse {} failed: {}", what, err)).with_path(path)
+    }
+
+    pub fn spawn(command: impl Into<String>, err: impl fmt::Display) -> Self {
+        let command = command.into();
+        Self::new(ErrorKind::CommandFailed, format!("Run {} failed: {}", command, err)).with_command(command)
+    }
+
+    pub fn project_not_found(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self::new(
+            ErrorKind::ProjectNotFound,
+            format!("Project path not found: {}", path.display()),
+        )
+        .with_hint("Choose the folder that contains your .xcworkspace or .xcodeproj.")
+        .with_path(path)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
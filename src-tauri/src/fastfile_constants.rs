@@ -0,0 +1,320 @@
+use serde::Serialize;
+use std::ops::Range;
+
+use crate::templates;
+
+/// A `NAME = ...` assignment whose value is a plain string literal, directly
+/// or as the fallback of `ENV.fetch("X", "...")` / `ENV["X"] || "..."`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FastfileConstant {
+    pub name: String,
+    /// The literal; for the `ENV` forms, the value used when the variable is unset.
+    pub value: String,
+    /// The environment variable that overrides the value, if any.
+    pub env_var: Option<String>,
+    pub line: usize,
+}
+
+pub struct ConstantsUpdate {
+    pub content: String,
+    pub updated: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+struct Literal {
+    /// Byte range in the file, quotes included.
+    range: Range<usize>,
+    quote: char,
+    value: String,
+}
+
+struct Assignment {
+    constant: FastfileConstant,
+    literal: Literal,
+}
+
+pub fn read(content: &str) -> Vec<FastfileConstant> {
+    assignments(content)
+        .into_iter()
+        .map(|assignment| assignment.constant)
+        .collect()
+}
+
+pub fn get(content: &str, name: &str) -> Option<String> {
+    assignments(content)
+        .into_iter()
+        .find(|assignment| assignment.constant.name == name)
+        .map(|assignment| assignment.literal.value)
+}
+
+/// Keeps the quote style, any `ENV` wrapper, `.freeze` and trailing comment;
+/// every other byte of `content` is left as it was.
+pub fn update(content: &str, values: &[(&str, &str)]) -> ConstantsUpdate {
+    let assignments = assignments(content);
+    let mut edits = Vec::new();
+    let mut updated = Vec::new();
+    let mut missing = Vec::new();
+    for (name, value) in values {
+        match assignments.iter().find(|assignment| assignment.constant.name == *name) {
+            Some(assignment) if assignment.literal.value == *value => {}
+            Some(assignment) => {
+                edits.push((assignment.literal.range.clone(), quote(value, assignment.literal.quote)));
+                updated.push(name.to_string());
+            }
+            None => missing.push(name.to_string()),
+        }
+    }
+
+    let mut content = content.to_string();
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    for (range, literal) in edits {
+        content.replace_range(range, &literal);
+    }
+    ConstantsUpdate {
+        content,
+        updated,
+        missing,
+    }
+}
+
+fn assignments(content: &str) -> Vec<Assignment> {
+    let mut offset = 0;
+    let mut found = Vec::new();
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let text = line.trim_end_matches(['\n', '\r']);
+        if let Some(assignment) = parse_assignment(text, offset, index + 1) {
+            found.push(assignment);
+        }
+        offset += line.len();
+    }
+    found
+}
+
+/// `NAME = <value> [.freeze] [# comment]`, where `<value>` is a literal,
+/// `ENV.fetch(<literal>, <literal>)` or `ENV[<literal>] || <literal>`.
+fn parse_assignment(line: &str, offset: usize, number: usize) -> Option<Assignment> {
+    let mut scanner = Scanner { line, pos: 0 };
+    scanner.skip_spaces();
+    let name = scanner.constant_name()?;
+    scanner.skip_spaces();
+    if !scanner.eat("=") || scanner.peek().is_some_and(|c| c == '=' || c == '~') {
+        return None;
+    }
+    scanner.skip_spaces();
+
+    let (env_var, literal) = if scanner.eat("ENV.fetch(") {
+        scanner.skip_spaces();
+        let env_var = scanner.literal()?;
+        scanner.skip_spaces();
+        scanner.expect(",")?;
+        scanner.skip_spaces();
+        let fallback = scanner.literal()?;
+        scanner.skip_spaces();
+        scanner.expect(")")?;
+        (Some(env_var.value), fallback)
+    } else if scanner.eat("ENV[") {
+        scanner.skip_spaces();
+        let env_var = scanner.literal()?;
+        scanner.skip_spaces();
+        scanner.expect("]")?;
+        scanner.skip_spaces();
+        scanner.expect("||")?;
+        scanner.skip_spaces();
+        (Some(env_var.value), scanner.literal()?)
+    } else {
+        (None, scanner.literal()?)
+    };
+
+    scanner.skip_spaces();
+    scanner.eat(".freeze");
+    scanner.skip_spaces();
+    if !(scanner.rest().is_empty() || scanner.rest().starts_with('#')) {
+        return None;
+    }
+
+    Some(Assignment {
+        constant: FastfileConstant {
+            name,
+            value: literal.value.clone(),
+            env_var,
+            line: number,
+        },
+        literal: Literal {
+            range: offset + literal.range.start..offset + literal.range.end,
+            ..literal
+        },
+    })
+}
+
+struct Scanner<'a> {
+    line: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn rest(&self) -> &'a str {
+        &self.line[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_spaces(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Option<()> {
+        self.eat(token).then_some(())
+    }
+
+    /// A Ruby constant name such as `SCHEME_DEV`.
+    fn constant_name(&mut self) -> Option<String> {
+        let rest = self.rest();
+        if !rest.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return None;
+        }
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        Some(rest[..len].to_string())
+    }
+
+    /// A single- or double-quoted string without interpolation. The range is
+    /// relative to the line.
+    fn literal(&mut self) -> Option<Literal> {
+        let start = self.pos;
+        let quote = self.peek().filter(|c| *c == '"' || *c == '\'')?;
+        let body = &self.line[start + 1..];
+        let mut value = String::new();
+        let mut chars = body.char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.pos = start + 1 + index + c.len_utf8();
+                    return Some(Literal {
+                        range: start..self.pos,
+                        quote,
+                        value,
+                    });
+                }
+                '\\' => {
+                    let (_, escaped) = chars.next()?;
+                    match (quote, escaped) {
+                        ('\'', '\\' | '\'') => value.push(escaped),
+                        ('\'', _) => {
+                            value.push('\\');
+                            value.push(escaped);
+                        }
+                        (_, 'n') => value.push('\n'),
+                        (_, 't') => value.push('\t'),
+                        (_, _) => value.push(escaped),
+                    }
+                }
+                // Interpolated strings have no static value.
+                '#' if quote == '"' && body[index + 1..].starts_with('{') => return None,
+                c => value.push(c),
+            }
+        }
+        None
+    }
+}
+
+fn quote(value: &str, quote: char) -> String {
+    if quote == '\'' {
+        format!(
+            "'{}'",
+            value.replace('\\', "\\\\").replace('\'', "\\'").replace('\n', " ")
+        )
+    } else {
+        format!("\"{}\"", templates::escape_ruby_string(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FASTFILE: &str = "# Generated\r\n\
+WORKSPACE = \"MyApp.xcworkspace\"\r\n\
+XCODEPROJ = 'My \\'App\\'.xcodeproj'.freeze # keep\r\n\
+SCHEME_DEV = ENV.fetch(\"SCHEME_DEV\", \"MyApp Dev\")\r\n\
+SCHEME_DIS = ENV[\"SCHEME_DIS\"] || 'MyApp'\r\n\
+TEAM_ID = \"#{ENV['TEAM']}\"\r\n\
+SLACK = ENV[\"SLACK\"]\r\n\
+  BUNDLE_ID_DEV   =   \"com.example.\\\"dev\\\"\"   # indented\r\n\
+SIGNING_STYLE == \"manual\"\r\n\
+lane_name = \"beta\"\r\n";
+
+    #[test]
+    fn reads_literals_and_env_fallbacks() {
+        let constants = read(FASTFILE);
+        let summary = constants
+            .iter()
+            .map(|c| (c.name.as_str(), c.value.as_str(), c.env_var.as_deref(), c.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("WORKSPACE", "MyApp.xcworkspace", None, 2),
+                ("XCODEPROJ", "My 'App'.xcodeproj", None, 3),
+                ("SCHEME_DEV", "MyApp Dev", Some("SCHEME_DEV"), 4),
+                ("SCHEME_DIS", "MyApp", Some("SCHEME_DIS"), 5),
+                ("BUNDLE_ID_DEV", "com.example.\"dev\"", None, 8),
+            ]
+        );
+        assert_eq!(get(FASTFILE, "TEAM_ID"), None);
+    }
+
+    #[test]
+    fn updates_only_the_literals_and_keeps_every_other_byte() {
+        let update = update(
+            FASTFILE,
+            &[
+                ("WORKSPACE", "MyApp.xcworkspace"),
+                ("XCODEPROJ", "Other's.xcodeproj"),
+                ("SCHEME_DEV", "Staging \"QA\""),
+                ("SCHEME_DIS", "MyApp Prod"),
+                ("TEAM_ID", "ABCDE12345"),
+            ],
+        );
+        assert_eq!(update.updated, ["XCODEPROJ", "SCHEME_DEV", "SCHEME_DIS"]);
+        assert_eq!(update.missing, ["TEAM_ID"]);
+        assert_eq!(
+            update.content,
+            FASTFILE
+                .replace("'My \\'App\\'.xcodeproj'", "'Other\\'s.xcodeproj'")
+                .replace("\"MyApp Dev\"", "\"Staging \\\"QA\\\"\"")
+                .replace("|| 'MyApp'", "|| 'MyApp Prod'")
+        );
+        assert_eq!(get(&update.content, "XCODEPROJ").as_deref(), Some("Other's.xcodeproj"));
+        assert_eq!(get(&update.content, "SCHEME_DEV").as_deref(), Some("Staging \"QA\""));
+    }
+
+    #[test]
+    fn an_update_without_changes_is_byte_for_byte_identical() {
+        let values = read(FASTFILE)
+            .into_iter()
+            .map(|constant| (constant.name, constant.value))
+            .collect::<Vec<_>>();
+        let values = values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        let update = update(FASTFILE, &values);
+        assert!(update.updated.is_empty());
+        assert_eq!(update.content, FASTFILE);
+    }
+}
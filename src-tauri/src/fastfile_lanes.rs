@@ -0,0 +1,634 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, AppResult};
+
+/// Actions that build or test, and so need WORKSPACE/XCODEPROJ to exist.
+const CONTAINER_ACTIONS: &[&str] = &[
+    "gym",
+    "build_app",
+    "build_ios_app",
+    "run_tests",
+    "scan",
+    "capture_screenshots",
+    "capture_ios_screenshots",
+    "snapshot",
+    "xcodebuild",
+];
+const TEST_ACTIONS: &[&str] = &["run_tests", "scan"];
+/// Keywords that open a block closed by `end` when they start a statement.
+const BLOCK_KEYWORDS: &[&str] = &[
+    "def", "if", "unless", "case", "while", "until", "for", "begin", "class", "module",
+];
+/// Keywords that also open a block as a value, e.g. `version = if ... end`.
+const EXPRESSION_KEYWORDS: &[&str] = &["if", "unless", "case", "while", "until", "begin"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lane {
+    pub platform: Option<String>,
+    pub name: String,
+    pub private: bool,
+    pub description: Option<String>,
+    /// Keys the lane reads from its options, e.g. `build_number` for `options[:build_number]`.
+    pub options: Vec<String>,
+    /// Declares a block parameter (`do |options|`); lanes without one ignore options.
+    pub takes_options: bool,
+    /// Uses its options other than by key, e.g. `beta(options)`, so any key may be used.
+    pub forwards_options: bool,
+    pub file: String,
+    pub line: usize,
+    /// Builds or tests, directly or through the lanes and methods it calls.
+    pub uses_container: bool,
+    pub runs_tests: bool,
+    /// The first `SCHEME_*` constant the lane refers to, its own body first.
+    pub scheme_constant: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FastfileLanes {
+    pub lanes: Vec<Lane>,
+    /// Imports that were not read: remote `import_from_git` sources and missing files.
+    pub unresolved_imports: Vec<String>,
+}
+
+impl FastfileLanes {
+    /// The lane `fastlane <name>` would run, public lanes first.
+    pub fn find(&self, name: &str) -> Option<&Lane> {
+        let mut matches = self.lanes.iter().filter(|lane| lane.name == name);
+        let first = matches.next()?;
+        if first.private {
+            return matches.find(|lane| !lane.private).or(Some(first));
+        }
+        Some(first)
+    }
+}
+
+/// A project without a Fastfile has no lanes.
+pub fn list(project_root: &Path) -> AppResult<FastfileLanes> {
+    let fastfile = project_root.join("fastlane").join("Fastfile");
+    if !fastfile.is_file() {
+        return Ok(FastfileLanes::default());
+    }
+
+    let mut parser = Parser {
+        project_root,
+        visited: HashSet::new(),
+        lanes: Vec::new(),
+        bodies: HashMap::new(),
+        unresolved_imports: Vec::new(),
+    };
+    parser.parse_file(&fastfile)?;
+
+    let Parser {
+        mut lanes,
+        bodies,
+        unresolved_imports,
+        ..
+    } = parser;
+    for (lane, _) in &mut lanes {
+        lane.uses_container = reaches(&lane.name, &bodies, CONTAINER_ACTIONS, &mut HashSet::new());
+        lane.runs_tests = reaches(&lane.name, &bodies, TEST_ACTIONS, &mut HashSet::new());
+        lane.scheme_constant = scheme_constant(&lane.name, &bodies, &mut HashSet::new());
+    }
+    Ok(FastfileLanes {
+        lanes: lanes.into_iter().map(|(lane, _)| lane).collect(),
+        unresolved_imports,
+    })
+}
+
+fn reaches(name: &str, bodies: &HashMap<String, Vec<String>>, actions: &[&str], visited: &mut HashSet<String>) -> bool {
+    if !visited.insert(name.to_string()) {
+        return false;
+    }
+    let Some(calls) = bodies.get(name) else {
+        return false;
+    };
+    calls
+        .iter()
+        .any(|call| actions.contains(&call.as_str()) || reaches(call, bodies, actions, visited))
+}
+
+fn scheme_constant(name: &str, bodies: &HashMap<String, Vec<String>>, visited: &mut HashSet<String>) -> Option<String> {
+    if !visited.insert(name.to_string()) {
+        return None;
+    }
+    let calls = bodies.get(name)?;
+    if let Some(constant) = calls.iter().find(|call| call.starts_with("SCHEME_")) {
+        return Some(constant.clone());
+    }
+    calls.iter().find_map(|call| scheme_constant(call, bodies, visited))
+}
+
+#[derive(Debug)]
+enum Block {
+    Platform(String),
+    Lane(usize),
+    Def(String),
+    Other,
+}
+
+struct Parser<'a> {
+    project_root: &'a Path,
+    visited: HashSet<PathBuf>,
+    lanes: Vec<(Lane, Option<String>)>,
+    bodies: HashMap<String, Vec<String>>,
+    unresolved_imports: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn parse_file(&mut self, path: &Path) -> AppResult<()> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if !self.visited.insert(canonical) {
+            return Ok(());
+        }
+        let content = fs::read_to_string(path).map_err(|e| AppError::io("Read Fastfile", path, e))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let label = path
+            .strip_prefix(self.project_root)
+            .unwrap_or(path)
+            .display()
+            .to_string();
+
+        let lines = content.lines().collect::<Vec<_>>();
+        let mut stack: Vec<Block> = Vec::new();
+        let mut pending_desc: Vec<String> = Vec::new();
+        let mut imports = Vec::new();
+        let mut index = 0;
+        while index < lines.len() {
+            let raw = lines[index];
+            let number = index + 1;
+            index += 1;
+            let code = strip_strings_and_comment(raw);
+            let words = words(&code);
+            let first = words.first().map(|word| word.text.as_str()).unwrap_or_default();
+
+            match first {
+                "desc" => pending_desc.push(string_literals(raw).into_iter().next().unwrap_or_default()),
+                "import" => {
+                    if let Some(relative) = string_literals(raw).into_iter().next() {
+                        imports.push(dir.join(relative));
+                    }
+                }
+                "import_from_git" => {
+                    // The arguments often span several lines.
+                    let mut statement = raw.to_string();
+                    let mut depth = paren_depth(&code);
+                    while depth > 0 && index < lines.len() {
+                        statement.push('\n');
+                        statement.push_str(lines[index]);
+                        depth += paren_depth(&strip_strings_and_comment(lines[index]));
+                        index += 1;
+                    }
+                    match self.local_git_import(&statement) {
+                        Some(path) => imports.push(path),
+                        None => self
+                            .unresolved_imports
+                            .push(keyword_string(&statement, "url").unwrap_or(statement.trim().to_string())),
+                    }
+                }
+                _ => {}
+            }
+
+            // Record identifiers for the innermost lane or method before this
+            // line opens or closes anything.
+            let owner = stack.iter().rev().find_map(|block| match block {
+                Block::Lane(lane) => Some(self.lanes[*lane].0.name.clone()),
+                Block::Def(name) => Some(name.clone()),
+                _ => None,
+            });
+            if let Some(owner) = &owner {
+                let calls = self.bodies.entry(owner.clone()).or_default();
+                calls.extend(
+                    words
+                        .iter()
+                        .filter(|word| word.is_reference())
+                        .map(|word| word.text.clone()),
+                );
+                if let Some(Block::Lane(lane)) = stack.iter().rev().find(|block| matches!(block, Block::Lane(_))) {
+                    let (lane, param) = &mut self.lanes[*lane];
+                    if let Some(param) = param {
+                        for key in option_keys(&code, param) {
+                            if !lane.options.contains(&key) {
+                                lane.options.push(key);
+                            }
+                        }
+                        lane.forwards_options |= words.iter().any(|word| {
+                            let after = &code[word.start + word.text.len()..];
+                            word.text == *param
+                                && word.is_reference()
+                                && !["[:", ".fetch(:", ".key?(:"]
+                                    .iter()
+                                    .any(|accessor| after.starts_with(accessor))
+                        });
+                    }
+                }
+            }
+
+            let mut opener = match first {
+                "platform" => symbol_after(&code, "platform").map(Block::Platform),
+                "lane" | "private_lane" => symbol_after(&code, first).map(|name| {
+                    let description = pending_desc
+                        .drain(..)
+                        .filter(|desc| !desc.is_empty())
+                        .collect::<Vec<_>>();
+                    let platform = stack.iter().rev().find_map(|block| match block {
+                        Block::Platform(platform) => Some(platform.clone()),
+                        _ => None,
+                    });
+                    self.bodies.entry(name.clone()).or_default();
+                    let param = block_param(&code);
+                    self.lanes.push((
+                        Lane {
+                            platform,
+                            name,
+                            private: first == "private_lane",
+                            description: (!description.is_empty()).then(|| description.join("\n")),
+                            options: Vec::new(),
+                            takes_options: param.is_some(),
+                            forwards_options: false,
+                            file: label.clone(),
+                            line: number,
+                            uses_container: false,
+                            runs_tests: false,
+                            scheme_constant: None,
+                        },
+                        param,
+                    ));
+                    Block::Lane(self.lanes.len() - 1)
+                }),
+                "def" => words.get(1).map(|word| {
+                    self.bodies.entry(word.text.clone()).or_default();
+                    Block::Def(word.text.clone())
+                }),
+                _ => None,
+            };
+            let loop_keyword = matches!(first, "while" | "until" | "for");
+            for (position, word) in words.iter().enumerate() {
+                if word.after_dot || word.after_colon || word.before_colon {
+                    continue;
+                }
+                match word.text.as_str() {
+                    "do" if !loop_keyword => stack.push(opener.take().unwrap_or(Block::Other)),
+                    "end" => {
+                        stack.pop();
+                    }
+                    keyword if position == 0 && BLOCK_KEYWORDS.contains(&keyword) => {
+                        stack.push(opener.take().unwrap_or(Block::Other))
+                    }
+                    keyword if EXPRESSION_KEYWORDS.contains(&keyword) && starts_value(&code[..word.start]) => {
+                        stack.push(Block::Other)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for import in imports {
+            if import.is_file() {
+                self.parse_file(&import)?;
+            } else {
+                self.unresolved_imports.push(import.display().to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The Fastfile an `import_from_git` points at when its url is a local
+    /// directory (absolute, `file://`, or relative to the project root).
+    fn local_git_import(&self, statement: &str) -> Option<PathBuf> {
+        let url = keyword_string(statement, "url")?;
+        let dir = PathBuf::from(url.strip_prefix("file://").unwrap_or(&url));
+        let dir = if dir.is_absolute() {
+            dir
+        } else {
+            self.project_root.join(dir)
+        };
+        if !dir.is_dir() {
+            return None;
+        }
+        let path = keyword_string(statement, "path").unwrap_or_else(|| "fastlane/Fastfile".to_string());
+        Some(dir.join(path))
+    }
+}
+
+/// Whether a keyword after `before` starts a value (an assignment's right
+/// side or an argument) rather than being a modifier like `x if y`.
+fn starts_value(before: &str) -> bool {
+    let before = before.trim_end();
+    before.ends_with('(') || (before.ends_with('=') && !before.ends_with("=="))
+}
+
+#[derive(Debug)]
+struct Word {
+    text: String,
+    start: usize,
+    /// `foo.bar`: a method on another receiver.
+    after_dot: bool,
+    /// `:bar`: a symbol.
+    after_colon: bool,
+    /// `bar:`: a keyword argument or hash key.
+    before_colon: bool,
+}
+
+impl Word {
+    fn is_reference(&self) -> bool {
+        let keyword = matches!(
+            self.text.as_str(),
+            "do" | "end" | "if" | "unless" | "else" | "elsif" | "then" | "return"
+        );
+        !(keyword || self.after_dot || self.after_colon || self.before_colon)
+    }
+}
+
+fn words(code: &str) -> Vec<Word> {
+    let bytes = code.as_bytes();
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in code.char_indices().chain([(code.len(), ' ')]) {
+        let is_word = c.is_ascii_alphanumeric() || c == '_' || (start.is_some() && (c == '?' || c == '!'));
+        match (start, is_word) {
+            (None, true) if !c.is_ascii_digit() => start = Some(index),
+            (Some(begin), false) => {
+                let before = begin.checked_sub(1).map(|i| bytes[i]);
+                let after = bytes.get(index).copied();
+                words.push(Word {
+                    text: code[begin..index].to_string(),
+                    start: begin,
+                    after_dot: before == Some(b'.') && bytes.get(begin.wrapping_sub(2)) != Some(&b'.'),
+                    after_colon: before == Some(b':') && bytes.get(begin.wrapping_sub(2)) != Some(&b':'),
+                    before_colon: after == Some(b':') && bytes.get(index + 1) != Some(&b':'),
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// The line with its comment removed and string contents blanked, so words
+/// inside strings are not taken for code.
+fn strip_strings_and_comment(line: &str) -> String {
+    let mut code = String::with_capacity(line.len());
+    let mut quote = None;
+    let mut escaped = false;
+    for c in line.chars() {
+        match quote {
+            Some(q) if !escaped && c == q => {
+                quote = None;
+                code.push(c);
+            }
+            // Keep byte offsets aligned with the original line.
+            Some(_) => {
+                escaped = !escaped && c == '\\';
+                code.push_str(&" ".repeat(c.len_utf8()));
+            }
+            None if c == '#' => break,
+            None => {
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                }
+                code.push(c);
+            }
+        }
+    }
+    code
+}
+
+fn string_literals(text: &str) -> Vec<String> {
+    let mut literals = Vec::new();
+    let mut current: Option<(char, String)> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match &mut current {
+            Some((_, value)) if c == '\\' => {
+                if let Some(escaped) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            Some((quote, value)) if c == *quote => {
+                literals.push(std::mem::take(value));
+                current = None;
+            }
+            Some((_, value)) => value.push(c),
+            None if c == '#' => break,
+            None if c == '"' || c == '\'' => current = Some((c, String::new())),
+            None => {}
+        }
+    }
+    literals
+}
+
+fn keyword_string(statement: &str, key: &str) -> Option<String> {
+    statement.lines().find_map(|line| {
+        let label = words(&strip_strings_and_comment(line))
+            .into_iter()
+            .find(|word| word.text == key && word.before_colon)?;
+        string_literals(&line[label.start..]).into_iter().next()
+    })
+}
+
+fn paren_depth(code: &str) -> i32 {
+    code.chars().fold(0, |depth, c| match c {
+        '(' => depth + 1,
+        ')' => depth - 1,
+        _ => depth,
+    })
+}
+
+fn symbol_after(code: &str, keyword: &str) -> Option<String> {
+    let rest = code.trim_start().strip_prefix(keyword)?.trim_start();
+    let rest = rest.strip_prefix('(').unwrap_or(rest).trim_start().strip_prefix(':')?;
+    let len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    (len > 0).then(|| rest[..len].to_string())
+}
+
+fn block_param(code: &str) -> Option<String> {
+    let start = code.find('|')? + 1;
+    let end = start + code[start..].find('|')?;
+    let param = code[start..end].split(',').next()?.trim();
+    (!param.is_empty()).then(|| param.to_string())
+}
+
+/// Keys read as `param[:key]`, `param.fetch(:key` or `param.key?(:key)`.
+fn option_keys(code: &str, param: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    for accessor in ["[:", ".fetch(:", ".key?(:"] {
+        let pattern = format!("{}{}", param, accessor);
+        let mut search = 0;
+        while let Some(found) = code[search..].find(&pattern) {
+            let begin = search + found;
+            let key_start = begin + pattern.len();
+            search = key_start;
+            let standalone = code[..begin]
+                .chars()
+                .next_back()
+                .map_or(true, |c| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'));
+            if !standalone {
+                continue;
+            }
+            let rest = &code[key_start..];
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if len > 0 {
+                keys.push(rest[..len].to_string());
+            }
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    fn project(files: &[(&str, &str)]) -> TestDir {
+        let root = TestDir::with_files(&[]);
+        for (file, content) in files {
+            root.write(&format!("fastlane/{}", file), content);
+        }
+        root
+    }
+
+    fn names(lanes: &FastfileLanes) -> Vec<(Option<&str>, &str)> {
+        lanes
+            .lanes
+            .iter()
+            .map(|lane| (lane.platform.as_deref(), lane.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn conditional_values_do_not_close_the_enclosing_block() {
+        let root = project(&[(
+            "Fastfile",
+            r#"platform :ios do
+  lane :beta do
+    version = if ENV["VERSION"]
+      ENV["VERSION"]
+    else
+      get_version_number
+    end
+    result = case version
+             when "1.0" then "first"
+             else "next"
+             end
+    notes = (begin
+      File.read("notes.txt")
+    rescue
+      ""
+    end)
+    build_app(scheme: SCHEME_DIS) if version
+  end
+
+  lane :release do
+    upload_to_app_store
+  end
+end
+"#,
+        )]);
+
+        let lanes = list(&root).unwrap();
+        assert_eq!(names(&lanes), [(Some("ios"), "beta"), (Some("ios"), "release")]);
+        let beta = lanes.find("beta").unwrap();
+        assert!(beta.uses_container);
+        assert_eq!(beta.scheme_constant.as_deref(), Some("SCHEME_DIS"));
+        assert!(!lanes.find("release").unwrap().uses_container);
+    }
+
+    #[test]
+    fn reads_lanes_from_imported_files() {
+        let root = project(&[
+            (
+                "Fastfile",
+                r#"import "lanes/Build.rb" # shared build lanes
+import "Missing.rb"
+import_from_git(
+  url: "https://github.com/example/fastlane-shared",
+  path: "fastlane/Fastfile"
+)
+import_from_git(url: "vendor/shared", branch: "main")
+
+default_platform(:ios)
+
+platform :ios do
+  desc "Runs the tests"
+  desc "then uploads to TestFlight"
+  lane :beta do |options|
+    test_suite
+    upload_to_testflight(changelog: options[:changelog], skip_waiting: options.fetch(:skip_waiting, true))
+  end
+end
+"#,
+            ),
+            (
+                "lanes/Build.rb",
+                r#"platform :ios do
+  private_lane :build do
+    build_app(scheme: SCHEME_DIS)
+  end
+end
+
+def test_suite
+  run_tests(scheme: SCHEME_DEV)
+end
+"#,
+            ),
+        ]);
+        let shared = root.join("vendor/shared/fastlane");
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(shared.join("Fastfile"), "lane :release do\n  build\nend\n").unwrap();
+
+        let lanes = list(&root).unwrap();
+        assert_eq!(
+            names(&lanes),
+            [(Some("ios"), "beta"), (Some("ios"), "build"), (None, "release")]
+        );
+
+        let beta = lanes.find("beta").unwrap();
+        assert_eq!(
+            beta.description.as_deref(),
+            Some("Runs the tests\nthen uploads to TestFlight")
+        );
+        assert_eq!(beta.options, ["changelog", "skip_waiting"]);
+        assert!(beta.takes_options && !beta.forwards_options);
+        assert!(beta.runs_tests && beta.uses_container);
+        assert_eq!(beta.scheme_constant.as_deref(), Some("SCHEME_DEV"));
+
+        let build = lanes.find("build").unwrap();
+        assert!(build.private);
+        assert_eq!(build.file, Path::new("fastlane/lanes/Build.rb").display().to_string());
+        assert_eq!(build.line, 2);
+
+        let release = lanes.find("release").unwrap();
+        assert!(release.uses_container && !release.runs_tests);
+
+        assert_eq!(lanes.unresolved_imports.len(), 2);
+        assert_eq!(
+            lanes.unresolved_imports[0],
+            "https://github.com/example/fastlane-shared"
+        );
+        assert!(lanes.unresolved_imports[1].ends_with("Missing.rb"));
+    }
+
+    #[test]
+    fn prefers_a_public_lane_over_a_private_one_with_the_same_name() {
+        let root = project(&[(
+            "Fastfile",
+            "private_lane :deploy do\nend\n\nplatform :ios do\n  lane :deploy do |opts|\n    beta(opts)\n  end\nend\n",
+        )]);
+        let lanes = list(&root).unwrap();
+        let deploy = lanes.find("deploy").unwrap();
+        assert!(!deploy.private);
+        assert!(deploy.forwards_options);
+        assert!(list(&root.join("missing")).unwrap().lanes.is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::backups;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::runtime_env::EnvConflict;
+use crate::templates::{self, RenderedFile};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileChange {
+    New,
+    Changed,
+    Unchanged,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePreview {
+    pub path: String,
+    pub change: FileChange,
+    /// Unified diff against the file on disk; empty when unchanged.
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationPreview {
+    pub preview_id: String,
+    pub project_path: String,
+    pub files: Vec<FilePreview>,
+    /// Managed env keys edited by hand; applying keeps their current values.
+    pub env_conflicts: Vec<EnvConflict>,
+}
+
+pub struct Written {
+    /// The backup taken first; `None` when nothing changed.
+    pub backup_id: Option<String>,
+    pub paths: Vec<PathBuf>,
+    pub files: Vec<FilePreview>,
+}
+
+struct PendingFile {
+    rendered: RenderedFile,
+    /// The content the diff was computed against; `None` for a new file.
+    before: Option<String>,
+}
+
+impl PendingFile {
+    fn preview(&self) -> FilePreview {
+        let path = self.rendered.path.to_string_lossy().to_string();
+        let change = match &self.before {
+            None => FileChange::New,
+            Some(content) if *content == self.rendered.content => FileChange::Unchanged,
+            Some(_) => FileChange::Changed,
+        };
+        let diff = match change {
+            FileChange::Unchanged => String::new(),
+            _ => unified_diff(&path, self.before.as_deref(), Some(&self.rendered.content)),
+        };
+        FilePreview { path, change, diff }
+    }
+}
+
+struct PendingGeneration {
+    project_root: PathBuf,
+    files: Vec<PendingFile>,
+}
+
+#[derive(Default)]
+pub struct GenerationPreviews {
+    pending: Mutex<HashMap<String, PendingGeneration>>,
+}
+
+impl GenerationPreviews {
+    /// Diffs `rendered` against the project and keeps it for `apply`. A new
+    /// preview replaces any earlier one for the same project.
+    pub fn prepare(&self, project_root: &Path, rendered: Vec<RenderedFile>) -> AppResult<GenerationPreview> {
+        let mut hasher = Sha256::new();
+        hasher.update(project_root.to_string_lossy().as_bytes());
+        let files = pending_files(project_root, rendered)?;
+        let previews = files.iter().map(PendingFile::preview).collect::<Vec<_>>();
+        for file in &files {
+            hasher.update(file.rendered.path.to_string_lossy().as_bytes());
+            hasher.update(file.rendered.content.as_bytes());
+            hasher.update(file.before.as_deref().unwrap_or_default().as_bytes());
+        }
+
+        let preview_id = format!("{:x}", hasher.finalize())[..16].to_string();
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, generation| generation.project_root != project_root);
+        pending.insert(
+            preview_id.clone(),
+            PendingGeneration {
+                project_root: project_root.to_path_buf(),
+                files,
+            },
+        );
+        Ok(GenerationPreview {
+            preview_id,
+            project_path: project_root.display().to_string(),
+            files: previews,
+            env_conflicts: vec![],
+        })
+    }
+
+    /// Writes the new and changed files of a preview and returns the project
+    /// root with what was written. Fails without writing anything when a
+    /// file was edited on disk after the preview was made.
+    pub fn apply(&self, preview_id: &str) -> AppResult<(PathBuf, Written)> {
+        let generation = self.pending.lock().unwrap().remove(preview_id).ok_or_else(|| {
+            AppError::new(
+                ErrorKind::PreviewNotFound,
+                format!("Generation preview not found: {}", preview_id),
+            )
+            .with_hint("Preview the generation again; previews are kept until applied or replaced.")
+        })?;
+
+        for file in &generation.files {
+            let path = generation.project_root.join(&file.rendered.path);
+            let current = fs::read_to_string(&path).ok();
+            if current != file.before {
+                return Err(AppError::new(
+                    ErrorKind::PreviewStale,
+                    format!("{} changed after the preview was made", file.rendered.path.display()),
+                )
+                .with_hint("Preview the generation again to see the current differences.")
+                .with_path(&path));
+            }
+        }
+
+        let written = write_changed(&generation.project_root, "apply-generation", generation.files)?;
+        Ok((generation.project_root, written))
+    }
+}
+
+pub fn write(project_root: &Path, reason: &str, rendered: Vec<RenderedFile>) -> AppResult<Written> {
+    write_changed(project_root, reason, pending_files(project_root, rendered)?)
+}
+
+fn pending_files(project_root: &Path, rendered: Vec<RenderedFile>) -> AppResult<Vec<PendingFile>> {
+    rendered
+        .into_iter()
+        .map(|file| {
+            let path = project_root.join(&file.path);
+            let before = if path.exists() {
+                Some(fs::read_to_string(&path).map_err(|e| AppError::io("Read file for preview", &path, e))?)
+            } else {
+                None
+            };
+            Ok(PendingFile { rendered: file, before })
+        })
+        .collect()
+}
+
+fn write_changed(project_root: &Path, reason: &str, files: Vec<PendingFile>) -> AppResult<Written> {
+    let (changed, previews): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .filter(|file| file.before.as_deref() != Some(file.rendered.content.as_str()))
+        .map(|file| {
+            let preview = file.preview();
+            (file.rendered, preview)
+        })
+        .unzip();
+    let mut backup_id = None;
+    if !changed.is_empty() {
+        let paths = changed.iter().map(|file| file.path.as_path()).collect::<Vec<_>>();
+        backup_id = Some(backups::snapshot(project_root, reason, &paths)?.backup_id);
+    }
+    let paths = templates::write(project_root, &changed)?;
+    Ok(Written {
+        backup_id,
+        paths,
+        files: previews,
+    })
+}
+
+/// `None` on either side is a missing file, shown as `/dev/null`.
+pub fn unified_diff(path: &str, before: Option<&str>, after: Option<&str>) -> String {
+    let header = |content: Option<&str>, prefix: &str| match content {
+        Some(_) => format!("{}/{}", prefix, path),
+        None => "/dev/null".to_string(),
+    };
+    TextDiff::from_lines(before.unwrap_or_default(), after.unwrap_or_default())
+        .unified_diff()
+        .context_radius(3)
+        .header(&header(before, "a"), &header(after, "b"))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    fn file(path: &str, content: &str) -> RenderedFile {
+        RenderedFile {
+            path: PathBuf::from(path),
+            content: content.to_string(),
+            executable: false,
+        }
+    }
+
+    #[test]
+    fn write_backs_up_and_writes_only_changed_files() {
+        let root = TestDir::with_files(&[
+            ("Gemfile", "gem \"fastlane\"\n"),
+            ("fastlane/Appfile", "team_id(\"A\")\n"),
+        ]);
+
+        let written = write(
+            &root,
+            "test",
+            vec![
+                file("Gemfile", "gem \"fastlane\"\n"),
+                file("fastlane/Appfile", "team_id(\"B\")\n"),
+                file("fastlane/Pluginfile", ""),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            written.paths,
+            [root.join("fastlane/Appfile"), root.join("fastlane/Pluginfile")]
+        );
+        let changes = written
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                ("fastlane/Appfile", FileChange::Changed),
+                ("fastlane/Pluginfile", FileChange::New)
+            ]
+        );
+        assert!(written.files[0].diff.contains("-team_id(\"A\")\n+team_id(\"B\")\n"));
+        let backup = backups::list_backups(&root).unwrap().remove(0);
+        assert_eq!(Some(backup.backup_id), written.backup_id);
+        assert_eq!(backup.files.len(), 2);
+
+        let unchanged = write(&root, "test", vec![file("Gemfile", "gem \"fastlane\"\n")]).unwrap();
+        assert!(unchanged.backup_id.is_none() && unchanged.paths.is_empty());
+    }
+
+    #[test]
+    fn applying_a_stale_preview_writes_nothing() {
+        let root = TestDir::with_files(&[("Gemfile", "gem \"fastlane\"\n")]);
+        let previews = GenerationPreviews::default();
+        let preview = previews
+            .prepare(&root, vec![file("Gemfile", "gem \"fastlane\"\ngem \"cocoapods\"\n")])
+            .unwrap();
+        assert_eq!(preview.files[0].change, FileChange::Changed);
+
+        root.write("Gemfile", "gem \"fastlane\", \"2.220.0\"\n");
+        let error = previews.apply(&preview.preview_id).err().unwrap();
+
+        assert_eq!(error.kind, ErrorKind::PreviewStale);
+        assert_eq!(
+            fs::read_to_string(root.join("Gemfile")).unwrap(),
+            "gem \"fastlane\", \"2.220.0\"\n"
+        );
+        assert!(backups::list_backups(&root).unwrap().is_empty());
+    }
+}
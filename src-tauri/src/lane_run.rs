@@ -0,0 +1,291 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::command_runner::{CommandSpec, SharedRunner};
+use crate::commands::LaneRunResult;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::redact::Redactor;
+
+pub const LANE_OUTPUT_EVENT: &str = "lane-output";
+pub const LANE_FINISHED_EVENT: &str = "lane-finished";
+
+/// How long a cancelled process group gets to exit after SIGTERM before it is killed.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LaneStream {
+    Stdout,
+    Stderr,
+    Preflight,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneOutputEvent {
+    pub run_id: String,
+    pub stream: LaneStream,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneFinishedEvent {
+    pub run_id: String,
+    pub lane: String,
+    pub status: String,
+    pub exit_code: i32,
+}
+
+pub struct CommandExit {
+    pub success: bool,
+    pub exit_code: i32,
+    pub output: String,
+}
+
+/// Registry of in-flight runs keyed by run id, managed as Tauri state so
+/// `cancel_lane` can reach processes spawned by another command invocation.
+#[derive(Default)]
+pub struct RunningLanes {
+    runs: Mutex<HashMap<String, Arc<RunControl>>>,
+}
+
+#[derive(Default)]
+struct RunControl {
+    cancelled: AtomicBool,
+    process_group: Mutex<Option<u32>>,
+}
+
+impl RunningLanes {
+    /// Returns false when no run with this id is in flight.
+    pub fn cancel(&self, run_id: &str) -> bool {
+        let control = match self.runs.lock().unwrap().get(run_id) {
+            Some(control) => control.clone(),
+            None => return false,
+        };
+        control.cancelled.store(true, Ordering::SeqCst);
+        let process_group = *control.process_group.lock().unwrap();
+        if let Some(pgid) = process_group {
+            terminate_process_group(control, pgid);
+        }
+        true
+    }
+
+    /// Run ids come from the frontend; a second run with a live id would make
+    /// the first one impossible to cancel, so it is rejected.
+    fn register(&self, run_id: &str) -> AppResult<Arc<RunControl>> {
+        let mut runs = self.runs.lock().unwrap();
+        if runs.contains_key(run_id) {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                format!("A lane with runId {} is already running.", run_id),
+            )
+            .with_hint("Start every run with a new run id."));
+        }
+        let control = Arc::new(RunControl::default());
+        runs.insert(run_id.to_string(), control.clone());
+        Ok(control)
+    }
+
+    /// Removes the entry only if it still belongs to `control`.
+    fn unregister(&self, run_id: &str, control: &Arc<RunControl>) {
+        let mut runs = self.runs.lock().unwrap();
+        if runs.get(run_id).is_some_and(|current| Arc::ptr_eq(current, control)) {
+            runs.remove(run_id);
+        }
+    }
+}
+
+/// Every line is redacted before it leaves the run.
+pub struct LaneRun {
+    app: AppHandle,
+    run_id: String,
+    output: String,
+    redactor: Redactor,
+    control: Arc<RunControl>,
+}
+
+impl LaneRun {
+    pub fn new(app: AppHandle, run_id: String, redactor: Redactor) -> AppResult<Self> {
+        let control = app.state::<RunningLanes>().register(&run_id)?;
+        Ok(Self {
+            app,
+            run_id,
+            output: String::new(),
+            redactor,
+            control,
+        })
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.control.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn preflight(&mut self, text: &str) {
+        for line in text.lines() {
+            self.push_line(LaneStream::Preflight, line);
+        }
+    }
+
+    /// The child leads its own process group so cancellation also reaches the
+    /// nested shells, bundler and xcodebuild processes it starts.
+    pub fn run_command(&mut self, spec: &CommandSpec) -> std::io::Result<CommandExit> {
+        if self.is_cancelled() {
+            return Ok(CommandExit {
+                success: false,
+                exit_code: -1,
+                output: String::new(),
+            });
+        }
+
+        let runner = self.app.state::<SharedRunner>().inner().clone();
+        let control = self.control.clone();
+        let mut attempt_output = String::new();
+        let exit_code = runner.stream(
+            spec,
+            &mut |pid| {
+                *control.process_group.lock().unwrap() = Some(pid);
+                // Cover a cancel that arrived between the check above and the spawn.
+                if control.cancelled.load(Ordering::SeqCst) {
+                    terminate_process_group(control.clone(), pid);
+                }
+            },
+            &mut |stream, line| {
+                attempt_output.push_str(&self.push_line(stream, &line));
+                attempt_output.push('\n');
+            },
+        );
+        *self.control.process_group.lock().unwrap() = None;
+        let exit_code = exit_code?;
+        Ok(CommandExit {
+            success: exit_code == 0,
+            exit_code,
+            output: attempt_output,
+        })
+    }
+
+    pub fn redact_values(&mut self, values: impl IntoIterator<Item = String>) {
+        self.redactor.add(values);
+    }
+
+    pub fn fail(&mut self, error: AppError) -> AppError {
+        let error = self.redactor.error(error);
+        self.push_line(LaneStream::Preflight, &format!("[error] {}", error.message));
+        if let Some(hint) = &error.hint {
+            self.push_line(LaneStream::Preflight, &format!("[error] {}", hint));
+        }
+        error
+    }
+
+    pub fn finish(mut self, lane: String, success: bool, exit_code: i32) -> LaneRunResult {
+        let status = if self.is_cancelled() {
+            "cancelled"
+        } else if success {
+            "success"
+        } else {
+            "failed"
+        };
+        let _ = self.app.emit(
+            LANE_FINISHED_EVENT,
+            LaneFinishedEvent {
+                run_id: self.run_id.clone(),
+                lane: lane.clone(),
+                status: status.to_string(),
+                exit_code,
+            },
+        );
+
+        LaneRunResult {
+            run_id: self.run_id.clone(),
+            status: status.to_string(),
+            exit_code,
+            output: std::mem::take(&mut self.output),
+            redactions: self.redactor.count(),
+            lane,
+        }
+    }
+
+    fn push_line(&mut self, stream: LaneStream, line: &str) -> String {
+        let line = self.redactor.line(line);
+        self.output.push_str(&line);
+        self.output.push('\n');
+        let _ = self.app.emit(
+            LANE_OUTPUT_EVENT,
+            LaneOutputEvent {
+                run_id: self.run_id.clone(),
+                stream,
+                line: line.clone(),
+            },
+        );
+        line
+    }
+}
+
+impl Drop for LaneRun {
+    fn drop(&mut self) {
+        self.app.state::<RunningLanes>().unregister(&self.run_id, &self.control);
+    }
+}
+
+/// Sends SIGTERM to the group, then SIGKILL after the grace period if the
+/// run still owns it. `run_command` clears `process_group` once the lane's
+/// process has been waited for, after which the pgid may be reused by an
+/// unrelated group.
+#[cfg(unix)]
+fn terminate_process_group(control: Arc<RunControl>, pgid: u32) {
+    let group = pgid as libc::pid_t;
+    // SAFETY: kill(2) has no memory-safety requirements; a negative pid
+    // addresses the whole process group.
+    unsafe {
+        libc::kill(-group, libc::SIGTERM);
+    }
+    thread::spawn(move || {
+        thread::sleep(CANCEL_GRACE_PERIOD);
+        let owned = *control.process_group.lock().unwrap() == Some(pgid);
+        unsafe {
+            if owned && libc::kill(-group, 0) == 0 {
+                libc::kill(-group, libc::SIGKILL);
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn terminate_process_group(_control: Arc<RunControl>, _pgid: u32) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_a_run_id_that_is_already_running() {
+        let lanes = RunningLanes::default();
+        let first = lanes.register("run-1").unwrap();
+        let error = lanes.register("run-1").err().unwrap();
+        assert_eq!(error.kind, ErrorKind::InvalidInput);
+
+        assert!(lanes.cancel("run-1"));
+        assert!(first.cancelled.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn unregister_keeps_an_entry_owned_by_another_run() {
+        let lanes = RunningLanes::default();
+        let first = lanes.register("run-1").unwrap();
+        lanes.unregister("run-1", &first);
+        let second = lanes.register("run-1").unwrap();
+
+        // The first run's late cleanup must not remove the second run.
+        lanes.unregister("run-1", &first);
+        assert!(lanes.cancel("run-1"));
+        assert!(second.cancelled.load(Ordering::SeqCst));
+
+        lanes.unregister("run-1", &second);
+        assert!(!lanes.cancel("run-1"));
+    }
+}
@@ -0,0 +1,176 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::command_runner::{CommandRunner, CommandSpec};
+
+const FALLBACK_SHELL: &str = "/bin/zsh";
+
+/// Variables shown when the environment is refreshed; the rest may hold secrets.
+const SUMMARY_KEYS: &[&str] = &[
+    "PATH",
+    "GEM_HOME",
+    "GEM_PATH",
+    "DEVELOPER_DIR",
+    "LANG",
+    "LC_ALL",
+    "RBENV_ROOT",
+    "ASDF_DATA_DIR",
+];
+
+pub fn default_shell() -> String {
+    std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.trim().is_empty())
+        .unwrap_or_else(|| FALLBACK_SHELL.to_string())
+}
+
+/// The environment a login shell sets up (PATH, GEM_HOME, DEVELOPER_DIR, ...).
+/// Apps launched from Finder inherit almost none of it, so tools are spawned
+/// with this map instead of the app's own environment.
+#[derive(Debug, Clone)]
+pub struct LoginEnv {
+    shell: String,
+    /// False when the shell could not be run and the app's own environment is used.
+    captured: bool,
+    vars: Arc<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginEnvSummary {
+    pub shell: String,
+    pub captured: bool,
+    pub variable_count: usize,
+    pub variables: BTreeMap<String, String>,
+}
+
+impl LoginEnv {
+    /// Runs `shell` as a login shell and reads its environment. Works for
+    /// zsh, bash and fish, which all accept `-l -c`. Falls back to the app's
+    /// environment when the shell cannot be run.
+    pub fn capture(runner: &dyn CommandRunner, shell: &str) -> Self {
+        let mut cmd = CommandSpec::new(shell);
+        cmd.args(["-l", "-c", "env -0"]);
+        let captured = runner
+            .output(&cmd)
+            .ok()
+            .filter(|output| output.success())
+            .map(|output| parse_env_output(&output.stdout))
+            .filter(|vars| vars.contains_key("PATH"));
+        Self {
+            shell: shell.to_string(),
+            captured: captured.is_some(),
+            vars: Arc::new(captured.unwrap_or_else(|| std::env::vars().collect())),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn from_vars(vars: &[(&str, &str)]) -> Self {
+        Self {
+            shell: "/bin/zsh".to_string(),
+            captured: true,
+            vars: Arc::new(
+                vars.iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn summary(&self) -> LoginEnvSummary {
+        LoginEnvSummary {
+            shell: self.shell.clone(),
+            captured: self.captured,
+            variable_count: self.vars.len(),
+            variables: SUMMARY_KEYS
+                .iter()
+                .filter_map(|key| Some((key.to_string(), self.get(key)?.to_string())))
+                .collect(),
+        }
+    }
+
+    /// Every captured variable, not only the ones in the summary.
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.vars.get(key).map(String::as_str)
+    }
+
+    pub fn home(&self) -> PathBuf {
+        self.get("HOME").map(PathBuf::from).unwrap_or_default()
+    }
+
+    pub fn command(&self, program: impl Into<String>) -> CommandSpec {
+        let mut cmd = CommandSpec::new(program);
+        cmd.base_env = Some(self.vars.clone());
+        cmd
+    }
+
+    pub fn find_program(&self, program: &str) -> Option<PathBuf> {
+        std::env::split_paths(self.get("PATH")?)
+            .map(|dir| dir.join(program))
+            .find(|candidate| is_executable(candidate))
+    }
+
+    pub fn path_with(&self, dir: &Path) -> String {
+        match self.get("PATH") {
+            Some(path) if !path.is_empty() => format!("{}:{}", dir.display(), path),
+            _ => dir.display().to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LoginEnvCache {
+    env: Mutex<Option<LoginEnv>>,
+}
+
+impl LoginEnvCache {
+    pub fn get(&self, runner: &dyn CommandRunner, shell: &str) -> LoginEnv {
+        // Held during the capture so concurrent commands wait for one shell run.
+        let mut cached = self.env.lock().unwrap();
+        match cached.as_ref() {
+            Some(env) if env.shell == shell => env.clone(),
+            _ => {
+                let env = LoginEnv::capture(runner, shell);
+                *cached = Some(env.clone());
+                env
+            }
+        }
+    }
+
+    pub fn refresh(&self, runner: &dyn CommandRunner, shell: &str) -> LoginEnv {
+        let mut cached = self.env.lock().unwrap();
+        let env = LoginEnv::capture(runner, shell);
+        *cached = Some(env.clone());
+        env
+    }
+}
+
+/// Parses `env -0` output: NUL-separated `KEY=value` entries, where values
+/// may contain newlines.
+fn parse_env_output(output: &str) -> BTreeMap<String, String> {
+    output
+        .split('\0')
+        .filter_map(|entry| entry.split_once('='))
+        .filter(|(key, _)| !key.is_empty() && !key.contains('\n'))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
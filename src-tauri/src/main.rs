@@ -1,15 +1,70 @@
+mod app_settings;
+mod asc_api_key;
+mod backups;
+mod bootstrap_script;
+mod command_runner;
 mod commands;
+mod dotenv;
+mod environments;
+mod error;
+mod fastfile_constants;
+mod fastfile_lanes;
+mod generation;
+mod lane_run;
+mod login_env;
+mod mobileprovision;
+mod pbxproj;
+mod redact;
+mod ruby_env;
+mod run_history;
+mod runtime_env;
+mod secret_store;
+mod templates;
+#[cfg(test)]
+mod test_support;
+mod xcconfig;
+mod xcscheme;
+mod xcworkspace;
 
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
+        .manage(command_runner::runner_from_env())
+        .manage(login_env::LoginEnvCache::default())
+        .manage(lane_run::RunningLanes::default())
+        .manage(generation::GenerationPreviews::default())
+        .manage(secret_store::SecretStore::default())
         .invoke_handler(tauri::generate_handler![
             commands::scan_project,
             commands::doctor_check,
+            commands::app_store_connect_token,
             commands::resolve_identity,
             commands::generate_fastlane_files,
+            commands::preview_generation,
+            commands::apply_generation,
+            commands::read_fastfile_constants,
+            commands::update_fastfile_constants,
+            commands::list_lanes,
             commands::run_lane,
+            commands::effective_lane_env,
+            commands::cancel_lane,
             commands::bundle_install_and_validate,
+            commands::list_runs,
+            commands::get_run,
+            commands::delete_runs,
+            commands::list_backups,
+            commands::diff_backup,
+            commands::restore_backup,
+            commands::unlock_secret_store,
+            commands::lock_secret_store,
+            commands::list_secrets,
+            commands::add_secret,
+            commands::rotate_secret,
+            commands::delete_secret,
+            commands::list_provisioning_profiles,
+            commands::get_app_settings,
+            commands::save_app_settings,
+            commands::refresh_login_env,
             commands::save_profile,
             commands::load_profile,
         ])
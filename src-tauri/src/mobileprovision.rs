@@ -0,0 +1,559 @@
+use roxmltree::{Document, Node, ParsingOptions};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `1.2.840.113549.1.7.2`, the CMS SignedData content type.
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProfileType {
+    Development,
+    AdHoc,
+    AppStore,
+    Enterprise,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvisioningProfile {
+    pub path: String,
+    pub uuid: String,
+    pub name: String,
+    pub app_id_name: Option<String>,
+    pub team_identifier: Option<String>,
+    pub team_name: Option<String>,
+    pub application_identifier: Option<String>,
+    /// `application-identifier` without the team prefix, e.g. `com.example.app` or `*`.
+    pub bundle_id: Option<String>,
+    pub creation_date: Option<String>,
+    pub expiration_date: Option<String>,
+    pub expired: bool,
+    pub profile_type: ProfileType,
+    pub entitlements: Value,
+    pub provisioned_devices: Vec<String>,
+}
+
+/// Files that cannot be decoded are skipped.
+pub fn list_profiles(dir: &Path) -> Result<Vec<ProvisioningProfile>, String> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let entries = fs::read_dir(dir).map_err(|e| format!("Read {} failed: {}", dir.display(), e))?;
+    let mut profiles = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some("mobileprovision") | Some("provisionprofile")
+            )
+        })
+        .filter_map(|path| load_profile(&path).ok())
+        .collect::<Vec<_>>();
+    profiles.sort_by(|a, b| {
+        a.name
+            .cmp(&b.name)
+            .then_with(|| b.expiration_date.cmp(&a.expiration_date))
+    });
+    Ok(profiles)
+}
+
+pub fn load_profile(path: &Path) -> Result<ProvisioningProfile, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Read {} failed: {}", path.display(), e))?;
+    let plist = signed_data_content(&bytes).map_err(|e| format!("Decode {} failed: {}", path.display(), e))?;
+    let text = String::from_utf8_lossy(&plist);
+    let mut profile = parse_profile_plist(&text).map_err(|e| format!("Parse {} failed: {}", path.display(), e))?;
+    profile.path = path.display().to_string();
+    Ok(profile)
+}
+
+pub fn parse_profile_plist(text: &str) -> Result<ProvisioningProfile, String> {
+    // Profiles carry the Apple plist DOCTYPE, which roxmltree rejects by default.
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(text, options).map_err(|e| e.to_string())?;
+    let root = doc
+        .root_element()
+        .children()
+        .find(Node::is_element)
+        .ok_or_else(|| "plist is empty".to_string())?;
+    let plist = plist_value(root)?;
+    let dict = plist
+        .as_object()
+        .ok_or_else(|| "plist root is not a dictionary".to_string())?;
+
+    let string = |key: &str| dict.get(key).and_then(Value::as_str).map(str::to_string);
+    let entitlements = dict.get("Entitlements").cloned().unwrap_or(Value::Object(Map::new()));
+    let application_identifier = entitlements
+        .get("application-identifier")
+        .or_else(|| entitlements.get("com.apple.application-identifier"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let team_identifier = dict
+        .get("TeamIdentifier")
+        .and_then(Value::as_array)
+        .and_then(|teams| teams.first())
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let bundle_id = application_identifier.as_deref().map(|app_id| {
+        match team_identifier
+            .as_deref()
+            .and_then(|team| app_id.strip_prefix(&format!("{}.", team)))
+        {
+            Some(bundle_id) => bundle_id.to_string(),
+            None => app_id
+                .split_once('.')
+                .map(|(_, rest)| rest)
+                .unwrap_or(app_id)
+                .to_string(),
+        }
+    });
+    let provisioned_devices = dict
+        .get("ProvisionedDevices")
+        .and_then(Value::as_array)
+        .map(|devices| devices.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default();
+    let expiration_date = string("ExpirationDate");
+    let expired = expiration_date
+        .as_deref()
+        .and_then(parse_plist_date)
+        .map(|expires_at| expires_at <= unix_now())
+        .unwrap_or(false);
+
+    let profile_type = profile_type(dict, &entitlements);
+    Ok(ProvisioningProfile {
+        path: String::new(),
+        uuid: string("UUID").ok_or_else(|| "profile has no UUID".to_string())?,
+        name: string("Name").unwrap_or_default(),
+        app_id_name: string("AppIDName"),
+        team_identifier,
+        team_name: string("TeamName"),
+        application_identifier,
+        bundle_id,
+        creation_date: string("CreationDate"),
+        expiration_date,
+        expired,
+        profile_type,
+        entitlements,
+        provisioned_devices,
+    })
+}
+
+/// Enterprise profiles provision all devices; development and ad-hoc ones list
+/// devices and differ by `get-task-allow`; App Store profiles list none.
+fn profile_type(dict: &Map<String, Value>, entitlements: &Value) -> ProfileType {
+    if dict.get("ProvisionsAllDevices").and_then(Value::as_bool) == Some(true) {
+        return ProfileType::Enterprise;
+    }
+    if dict.contains_key("ProvisionedDevices") {
+        if entitlements.get("get-task-allow").and_then(Value::as_bool) == Some(true) {
+            return ProfileType::Development;
+        }
+        return ProfileType::AdHoc;
+    }
+    ProfileType::AppStore
+}
+
+fn plist_value(node: Node) -> Result<Value, String> {
+    let text = || node.text().unwrap_or_default().trim().to_string();
+    let value = match node.tag_name().name() {
+        "dict" => {
+            let mut map = Map::new();
+            let mut children = node.children().filter(Node::is_element);
+            while let Some(key) = children.next() {
+                if !key.has_tag_name("key") {
+                    return Err(format!("expected <key> in dict, found <{}>", key.tag_name().name()));
+                }
+                let value = children
+                    .next()
+                    .ok_or_else(|| format!("missing value for key {}", key.text().unwrap_or_default()))?;
+                map.insert(key.text().unwrap_or_default().to_string(), plist_value(value)?);
+            }
+            Value::Object(map)
+        }
+        "array" => Value::Array(
+            node.children()
+                .filter(Node::is_element)
+                .map(plist_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        "string" | "date" => Value::String(node.text().unwrap_or_default().to_string()),
+        // Base64 payloads (e.g. DeveloperCertificates) are kept as text without line breaks.
+        "data" => Value::String(text().split_whitespace().collect()),
+        "integer" => Value::from(text().parse::<i64>().map_err(|e| format!("invalid integer: {}", e))?),
+        "real" => Value::from(text().parse::<f64>().map_err(|e| format!("invalid real: {}", e))?),
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        other => return Err(format!("unsupported plist element <{}>", other)),
+    };
+    Ok(value)
+}
+
+/// Apple signs profiles with BER, so indefinite lengths and constructed
+/// OCTET STRINGs are accepted.
+fn signed_data_content(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = BerReader::new(bytes);
+    let content_info = reader.read(0x30)?;
+    let mut content_info = BerReader::new(content_info.content);
+    let content_type = content_info.read(0x06)?;
+    if content_type.content != OID_SIGNED_DATA {
+        return Err("not a CMS SignedData envelope".to_string());
+    }
+    let explicit = content_info.read(0xa0)?;
+    let signed_data = BerReader::new(explicit.content).read(0x30)?;
+
+    let mut signed_data = BerReader::new(signed_data.content);
+    signed_data.read(0x02)?; // version
+    signed_data.read(0x31)?; // digestAlgorithms
+    let encap = signed_data.read(0x30)?;
+    let mut encap = BerReader::new(encap.content);
+    encap.read(0x06)?; // eContentType (id-data)
+    let explicit = encap.read(0xa0)?;
+    let content = BerReader::new(explicit.content).read_any()?;
+    octet_string_bytes(&content)
+}
+
+fn octet_string_bytes(element: &BerElement) -> Result<Vec<u8>, String> {
+    match element.tag {
+        0x04 => Ok(element.content.to_vec()),
+        0x24 => {
+            let mut out = Vec::new();
+            let mut reader = BerReader::new(element.content);
+            while !reader.at_end() {
+                out.extend(octet_string_bytes(&reader.read_any()?)?);
+            }
+            Ok(out)
+        }
+        tag => Err(format!("expected OCTET STRING, found tag 0x{:02x}", tag)),
+    }
+}
+
+struct BerElement<'a> {
+    tag: u8,
+    content: &'a [u8],
+}
+
+struct BerReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BerReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// True at the end of input or at an end-of-contents marker.
+    fn at_end(&self) -> bool {
+        self.pos >= self.bytes.len() || self.bytes[self.pos..].starts_with(&[0, 0])
+    }
+
+    fn read(&mut self, tag: u8) -> Result<BerElement<'a>, String> {
+        let element = self.read_any()?;
+        if element.tag != tag {
+            return Err(format!("expected tag 0x{:02x}, found 0x{:02x}", tag, element.tag));
+        }
+        Ok(element)
+    }
+
+    fn read_any(&mut self) -> Result<BerElement<'a>, String> {
+        let tag = self.byte()?;
+        if tag & 0x1f == 0x1f {
+            return Err("multi-byte tags are not supported".to_string());
+        }
+        let first = self.byte()?;
+        if first == 0x80 {
+            // Indefinite length: the content runs until the matching end-of-contents.
+            let start = self.pos;
+            let mut inner = BerReader::new(&self.bytes[start..]);
+            while !inner.at_end() {
+                inner.read_any()?;
+            }
+            if !inner.bytes[inner.pos..].starts_with(&[0, 0]) {
+                return Err("missing end-of-contents marker".to_string());
+            }
+            self.pos = start + inner.pos + 2;
+            return Ok(BerElement {
+                tag,
+                content: &self.bytes[start..start + inner.pos],
+            });
+        }
+
+        let len = if first & 0x80 == 0 {
+            first as usize
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 {
+                return Err("unsupported length encoding".to_string());
+            }
+            let mut len = 0usize;
+            for _ in 0..count {
+                len = (len << 8) | self.byte()? as usize;
+            }
+            len
+        };
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "element length exceeds input".to_string())?;
+        let content = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(BerElement { tag, content })
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| "unexpected end of input".to_string())?;
+        self.pos += 1;
+        Ok(byte)
+    }
+}
+
+fn parse_plist_date(value: &str) -> Option<i64> {
+    let value = value.strip_suffix('Z')?;
+    let (date, time) = value.split_once('T')?;
+    let mut date_parts = date.split('-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+    let mut time_parts = time.split(':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time_parts.next()??, time_parts.next()??, time_parts.next()??);
+
+    // Days since 1970-01-01 for a proleptic Gregorian date.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Where Xcode keeps installed profiles: the classic MobileDevice folder and
+/// the Xcode 16+ UserData folder.
+pub fn default_profile_dirs(home: &Path) -> Vec<PathBuf> {
+    vec![
+        home.join("Library/MobileDevice/Provisioning Profiles"),
+        home.join("Library/Developer/Xcode/UserData/Provisioning Profiles"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    const OID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+
+    fn plist(name: &str, uuid: &str, expiration: &str, extra: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AppIDName</key>
+	<string>My App</string>
+	<key>CreationDate</key>
+	<date>2024-01-31T12:00:00Z</date>
+	<key>DeveloperCertificates</key>
+	<array>
+		<data>
+		MIIFtz
+		CCBJ+g
+		</data>
+	</array>
+	<key>Entitlements</key>
+	<dict>
+		<key>application-identifier</key>
+		<string>ABCDE12345.com.example.app</string>
+		<key>get-task-allow</key>
+		<true/>
+	</dict>
+	<key>ExpirationDate</key>
+	<date>{expiration}</date>
+	<key>Name</key>
+	<string>{name}</string>
+	<key>TeamIdentifier</key>
+	<array>
+		<string>ABCDE12345</string>
+	</array>
+	<key>TeamName</key>
+	<string>Example Inc.</string>
+	<key>TimeToLive</key>
+	<integer>365</integer>
+	<key>UUID</key>
+	<string>{uuid}</string>
+	{extra}
+</dict>
+</plist>
+"#
+        )
+    }
+
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match content.len() {
+            len if len < 0x80 => out.push(len as u8),
+            len if len <= 0xff => out.extend([0x81, len as u8]),
+            len => out.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    /// An indefinite-length element, as Apple's signing tool writes them.
+    fn indefinite(tag: u8, parts: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = vec![tag, 0x80];
+        out.extend(parts.concat());
+        out.extend([0, 0]);
+        out
+    }
+
+    /// A CMS SignedData envelope around `content`, split into a constructed
+    /// OCTET STRING of 100-byte chunks.
+    fn envelope(content: &[u8]) -> Vec<u8> {
+        let chunks = content.chunks(100).map(|chunk| tlv(0x04, chunk)).collect::<Vec<_>>();
+        let encap = indefinite(
+            0x30,
+            &[tlv(0x06, OID_DATA), indefinite(0xa0, &[indefinite(0x24, &chunks)])],
+        );
+        let signed_data = indefinite(
+            0x30,
+            &[
+                tlv(0x02, &[1]),
+                tlv(
+                    0x31,
+                    &tlv(
+                        0x30,
+                        &tlv(0x06, &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01]),
+                    ),
+                ),
+                encap,
+                tlv(0xa0, &[]),
+                tlv(0x31, &[]),
+            ],
+        );
+        indefinite(0x30, &[tlv(0x06, OID_SIGNED_DATA), indefinite(0xa0, &[signed_data])])
+    }
+
+    #[test]
+    fn reads_the_plist_from_an_indefinite_length_envelope() {
+        let text = plist(
+            "Dev",
+            "UUID-1",
+            "2999-01-31T12:00:00Z",
+            "<key>ProvisionedDevices</key><array><string>00008101-0001</string></array>",
+        );
+        assert_eq!(
+            signed_data_content(&envelope(text.as_bytes())).unwrap(),
+            text.as_bytes()
+        );
+
+        let dir = TestDir::with_files(&[]);
+        let path = dir.join("dev.mobileprovision");
+        fs::write(&path, envelope(text.as_bytes())).unwrap();
+        let profile = load_profile(&path).unwrap();
+
+        assert_eq!(profile.path, path.display().to_string());
+        assert_eq!(profile.uuid, "UUID-1");
+        assert_eq!(profile.team_identifier.as_deref(), Some("ABCDE12345"));
+        assert_eq!(profile.bundle_id.as_deref(), Some("com.example.app"));
+        assert_eq!(profile.profile_type, ProfileType::Development);
+        assert_eq!(profile.provisioned_devices, ["00008101-0001"]);
+        assert!(!profile.expired);
+    }
+
+    #[test]
+    fn rejects_envelopes_that_are_not_signed_data() {
+        let not_signed = indefinite(0x30, &[tlv(0x06, OID_DATA), indefinite(0xa0, &[])]);
+        assert_eq!(
+            signed_data_content(&not_signed).unwrap_err(),
+            "not a CMS SignedData envelope"
+        );
+
+        let mut truncated = envelope(b"<plist/>");
+        truncated.truncate(truncated.len() - 2);
+        assert!(signed_data_content(&truncated).is_err());
+        assert!(signed_data_content(&[0x30, 0x05, 0x06]).is_err());
+    }
+
+    #[test]
+    fn tells_profile_types_apart_and_marks_expired_ones() {
+        let app_store = parse_profile_plist(&plist("Store", "UUID-2", "2000-01-01T00:00:00Z", "")).unwrap();
+        assert_eq!(app_store.profile_type, ProfileType::AppStore);
+        assert!(app_store.expired);
+
+        let enterprise = parse_profile_plist(&plist(
+            "Ent",
+            "UUID-3",
+            "2999-01-01T00:00:00Z",
+            "<key>ProvisionsAllDevices</key><true/>",
+        ))
+        .unwrap();
+        assert_eq!(enterprise.profile_type, ProfileType::Enterprise);
+
+        let ad_hoc = plist(
+            "AdHoc",
+            "UUID-4",
+            "2999-01-01T00:00:00Z",
+            "<key>ProvisionedDevices</key><array/>",
+        )
+        .replace("<true/>\n\t</dict>", "<false/>\n\t</dict>");
+        assert_eq!(parse_profile_plist(&ad_hoc).unwrap().profile_type, ProfileType::AdHoc);
+
+        let without_uuid = plist("NoId", "x", "", "").replace("<key>UUID</key>\n\t<string>x</string>", "");
+        assert_eq!(parse_profile_plist(&without_uuid).unwrap_err(), "profile has no UUID");
+    }
+
+    #[test]
+    fn lists_decodable_profiles_sorted_by_name() {
+        let dir = TestDir::with_files(&[]);
+        fs::write(
+            dir.join("b.mobileprovision"),
+            envelope(plist("Beta", "B", "2999-01-01T00:00:00Z", "").as_bytes()),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("a.provisionprofile"),
+            envelope(plist("Alpha", "A", "2999-01-01T00:00:00Z", "").as_bytes()),
+        )
+        .unwrap();
+        fs::write(dir.join("broken.mobileprovision"), b"not a profile").unwrap();
+        fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+
+        let names = list_profiles(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|profile| profile.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Alpha", "Beta"]);
+        assert!(list_profiles(&dir.join("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_plist_dates_as_unix_seconds() {
+        assert_eq!(parse_plist_date("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_plist_date("2025-01-31T12:00:00Z"), Some(1_738_324_800));
+        assert_eq!(parse_plist_date("2024-02-29T23:59:59Z"), Some(1_709_251_199));
+        assert_eq!(parse_plist_date("2025-01-31 12:00:00"), None);
+    }
+}
@@ -0,0 +1,730 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const APPLICATION_PRODUCT_TYPE: &str = "com.apple.product-type.application";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlistValue {
+    String(String),
+    Array(Vec<PlistValue>),
+    Dict(BTreeMap<String, PlistValue>),
+}
+
+impl PlistValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PlistValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[PlistValue]> {
+        match self {
+            PlistValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&BTreeMap<String, PlistValue>> {
+        match self {
+            PlistValue::Dict(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&PlistValue> {
+        self.as_dict().and_then(|map| map.get(key))
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(PlistValue::as_str)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BuildConfiguration {
+    pub name: String,
+    pub build_settings: BTreeMap<String, String>,
+    /// The `.xcconfig` file this configuration is based on, resolved to a path.
+    pub base_configuration: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NativeTarget {
+    pub id: String,
+    pub name: String,
+    pub product_name: Option<String>,
+    pub product_type: Option<String>,
+    pub build_configurations: Vec<BuildConfiguration>,
+    pub default_configuration_name: Option<String>,
+}
+
+impl NativeTarget {
+    pub fn is_application(&self) -> bool {
+        self.product_type.as_deref() == Some(APPLICATION_PRODUCT_TYPE)
+    }
+
+    pub fn configuration(&self, name: &str) -> Option<&BuildConfiguration> {
+        self.build_configurations.iter().find(|c| c.name == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PbxProject {
+    pub targets: Vec<NativeTarget>,
+    pub build_configurations: Vec<BuildConfiguration>,
+    pub default_configuration_name: Option<String>,
+}
+
+impl PbxProject {
+    pub fn target(&self, name: &str) -> Option<&NativeTarget> {
+        self.targets.iter().find(|t| t.name == name)
+    }
+
+    pub fn target_by_id(&self, id: &str) -> Option<&NativeTarget> {
+        self.targets.iter().find(|t| t.id == id)
+    }
+
+    pub fn application_targets(&self) -> impl Iterator<Item = &NativeTarget> {
+        self.targets.iter().filter(|t| t.is_application())
+    }
+
+    pub fn configuration(&self, name: &str) -> Option<&BuildConfiguration> {
+        self.build_configurations.iter().find(|c| c.name == name)
+    }
+}
+
+pub fn load_project(xcodeproj: &Path) -> Result<PbxProject, String> {
+    let pbxproj = xcodeproj.join("project.pbxproj");
+    let content = fs::read_to_string(&pbxproj).map_err(|e| format!("Read {} failed: {}", pbxproj.display(), e))?;
+    let source_root = xcodeproj.parent().unwrap_or_else(|| Path::new("."));
+    parse_project(&content, source_root)
+}
+
+/// `source_root` is the directory holding the `.xcodeproj` bundle.
+pub fn parse_project(content: &str, source_root: &Path) -> Result<PbxProject, String> {
+    let root = parse_plist(content)?;
+    let objects = root
+        .get("objects")
+        .and_then(PlistValue::as_dict)
+        .ok_or_else(|| "project.pbxproj has no objects dictionary".to_string())?;
+    let root_id = root
+        .get_str("rootObject")
+        .ok_or_else(|| "project.pbxproj has no rootObject".to_string())?;
+    let project = objects
+        .get(root_id)
+        .ok_or_else(|| format!("project.pbxproj rootObject {} not found", root_id))?;
+
+    let files = FileResolver::new(objects, project, source_root);
+    let (build_configurations, default_configuration_name) =
+        read_configuration_list(objects, &files, project.get_str("buildConfigurationList"));
+
+    let targets = project
+        .get("targets")
+        .and_then(PlistValue::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(PlistValue::as_str)
+        .filter_map(|id| {
+            let object = objects.get(id)?;
+            if object.get_str("isa") != Some("PBXNativeTarget") {
+                return None;
+            }
+            let (build_configurations, default_configuration_name) =
+                read_configuration_list(objects, &files, object.get_str("buildConfigurationList"));
+            Some(NativeTarget {
+                id: id.to_string(),
+                name: object.get_str("name").unwrap_or(id).to_string(),
+                product_name: object.get_str("productName").map(str::to_string),
+                product_type: object.get_str("productType").map(str::to_string),
+                build_configurations,
+                default_configuration_name,
+            })
+        })
+        .collect();
+
+    Ok(PbxProject {
+        targets,
+        build_configurations,
+        default_configuration_name,
+    })
+}
+
+fn read_configuration_list(
+    objects: &BTreeMap<String, PlistValue>,
+    files: &FileResolver,
+    list_id: Option<&str>,
+) -> (Vec<BuildConfiguration>, Option<String>) {
+    let list = match list_id.and_then(|id| objects.get(id)) {
+        Some(list) => list,
+        None => return (vec![], None),
+    };
+
+    let configurations = list
+        .get("buildConfigurations")
+        .and_then(PlistValue::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(PlistValue::as_str)
+        .filter_map(|id| {
+            let object = objects.get(id)?;
+            let build_settings = object
+                .get("buildSettings")
+                .and_then(PlistValue::as_dict)
+                .map(|settings| {
+                    settings
+                        .iter()
+                        .map(|(key, value)| (key.clone(), flatten_setting(value)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(BuildConfiguration {
+                name: object.get_str("name").unwrap_or(id).to_string(),
+                build_settings,
+                base_configuration: object
+                    .get_str("baseConfigurationReference")
+                    .and_then(|file_id| files.path_of(file_id)),
+            })
+        })
+        .collect();
+
+    (
+        configurations,
+        list.get_str("defaultConfigurationName").map(str::to_string),
+    )
+}
+
+/// Array-valued settings (e.g. `OTHER_LDFLAGS`) are joined the way Xcode shows them.
+fn flatten_setting(value: &PlistValue) -> String {
+    match value {
+        PlistValue::String(s) => s.clone(),
+        PlistValue::Array(values) => values.iter().map(flatten_setting).collect::<Vec<_>>().join(" "),
+        PlistValue::Dict(_) => String::new(),
+    }
+}
+
+/// Replaces each `$(VAR)`, `${VAR}` or `$(VAR:modifier,...)` reference with
+/// `resolve(VAR)`, which returns the final value. References nested in a
+/// variable name are expanded first; `$(inherited)` is passed on as
+/// `inherited`. Returns `None` when any reference cannot be resolved.
+pub fn expand_references(value: &str, resolve: &mut dyn FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let close = match after.chars().next() {
+            Some('(') => ')',
+            Some('{') => '}',
+            _ => {
+                out.push('$');
+                rest = after;
+                continue;
+            }
+        };
+        let end = find_matching(after, close)?;
+        let reference = &after[1..end];
+        rest = &after[end + 1..];
+
+        let reference = expand_references(reference, resolve)?;
+        let (name, modifiers) = match reference.split_once(':') {
+            Some((name, modifiers)) => (name, Some(modifiers)),
+            None => (reference.as_str(), None),
+        };
+        let mut resolved = resolve(name)?;
+        for modifier in modifiers.unwrap_or_default().split(',').filter(|m| !m.is_empty()) {
+            resolved = apply_modifier(&resolved, modifier)?;
+        }
+        out.push_str(&resolved);
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+fn find_matching(text: &str, close: char) -> Option<usize> {
+    let open = if close == ')' { '(' } else { '{' };
+    let mut level = 0;
+    for (idx, c) in text.char_indices() {
+        if c == open {
+            level += 1;
+        } else if c == close {
+            level -= 1;
+            if level == 0 {
+                return Some(idx);
+            }
+        }
+    }
+    None
+}
+
+fn apply_modifier(value: &str, modifier: &str) -> Option<String> {
+    match modifier {
+        "rfc1034identifier" => Some(
+            value
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                        c
+                    } else {
+                        '-'
+                    }
+                })
+                .collect(),
+        ),
+        "c99extidentifier" | "identifier" => Some(
+            value
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+                .collect(),
+        ),
+        "lower" => Some(value.to_lowercase()),
+        "upper" => Some(value.to_uppercase()),
+        "quote" => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+struct FileResolver<'a> {
+    objects: &'a BTreeMap<String, PlistValue>,
+    parents: HashMap<&'a str, &'a str>,
+    project_dir: PathBuf,
+}
+
+impl<'a> FileResolver<'a> {
+    fn new(objects: &'a BTreeMap<String, PlistValue>, project: &'a PlistValue, source_root: &Path) -> Self {
+        let mut parents = HashMap::new();
+        for (id, object) in objects {
+            let is_group = matches!(
+                object.get_str("isa"),
+                Some("PBXGroup") | Some("PBXVariantGroup") | Some("XCVersionGroup")
+            );
+            if !is_group {
+                continue;
+            }
+            for child in object
+                .get("children")
+                .and_then(PlistValue::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(PlistValue::as_str)
+            {
+                parents.insert(child, id.as_str());
+            }
+        }
+
+        Self {
+            objects,
+            parents,
+            project_dir: source_root.join(project.get_str("projectDirPath").unwrap_or_default()),
+        }
+    }
+
+    fn path_of(&self, id: &str) -> Option<PathBuf> {
+        self.path_of_depth(id, 0)
+    }
+
+    fn path_of_depth(&self, id: &str, depth: usize) -> Option<PathBuf> {
+        if depth > 64 {
+            return None;
+        }
+        let object = self.objects.get(id)?;
+        let own = PathBuf::from(object.get_str("path").unwrap_or_default());
+        match object.get_str("sourceTree").unwrap_or("<group>") {
+            "<absolute>" => Some(own),
+            "SOURCE_ROOT" => Some(self.project_dir.join(own)),
+            "<group>" => {
+                let base = match self.parents.get(id) {
+                    Some(parent) => self.path_of_depth(parent, depth + 1)?,
+                    None => self.project_dir.clone(),
+                };
+                Some(base.join(own))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_plist(content: &str) -> Result<PlistValue, String> {
+    let mut parser = Parser {
+        bytes: content.as_bytes(),
+        pos: 0,
+    };
+    let value = parser.parse_value()?;
+    parser.skip_trivia();
+    if parser.pos < parser.bytes.len() {
+        return Err(parser.error("unexpected trailing content"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse_value(&mut self) -> Result<PlistValue, String> {
+        self.skip_trivia();
+        match self.peek() {
+            Some(b'{') => self.parse_dict(),
+            Some(b'(') => self.parse_array(),
+            Some(_) => self.parse_string().map(PlistValue::String),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_dict(&mut self) -> Result<PlistValue, String> {
+        self.pos += 1;
+        let mut map = BTreeMap::new();
+        loop {
+            self.skip_trivia();
+            if self.peek() == Some(b'}') {
+                self.pos += 1;
+                return Ok(PlistValue::Dict(map));
+            }
+            let key = self.parse_string()?;
+            self.expect(b'=')?;
+            let value = self.parse_value()?;
+            self.expect(b';')?;
+            map.insert(key, value);
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<PlistValue, String> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_trivia();
+            if self.peek() == Some(b')') {
+                self.pos += 1;
+                return Ok(PlistValue::Array(values));
+            }
+            values.push(self.parse_value()?);
+            self.skip_trivia();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b')') => {}
+                _ => return Err(self.error("expected ',' or ')' in array")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.skip_trivia();
+        match self.peek() {
+            Some(quote @ (b'"' | b'\'')) => self.parse_quoted(quote),
+            Some(b'<') => {
+                let start = self.pos;
+                while let Some(byte) = self.peek() {
+                    self.pos += 1;
+                    if byte == b'>' {
+                        return Ok(String::from_utf8_lossy(&self.bytes[start..self.pos]).to_string());
+                    }
+                }
+                Err(self.error("unterminated data literal"))
+            }
+            Some(_) => {
+                let start = self.pos;
+                while let Some(byte) = self.peek() {
+                    if byte.is_ascii_alphanumeric() || b"_$/:.-+".contains(&byte) {
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+                if start == self.pos {
+                    return Err(self.error("expected a string"));
+                }
+                Ok(String::from_utf8_lossy(&self.bytes[start..self.pos]).to_string())
+            }
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_quoted(&mut self, quote: u8) -> Result<String, String> {
+        self.pos += 1;
+        let mut buf = Vec::new();
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            if byte == quote {
+                return Ok(String::from_utf8_lossy(&buf).to_string());
+            }
+            if byte != b'\\' {
+                buf.push(byte);
+                continue;
+            }
+            let escaped = self.peek().ok_or_else(|| self.error("unterminated escape"))?;
+            self.pos += 1;
+            match escaped {
+                b'n' => buf.push(b'\n'),
+                b't' => buf.push(b'\t'),
+                b'r' => buf.push(b'\r'),
+                b'U' => {
+                    let unit = self
+                        .utf16_unit(self.pos)
+                        .ok_or_else(|| self.error("invalid \\U escape"))?;
+                    self.pos += 4;
+                    // Characters outside the BMP are written as two escaped
+                    // UTF-16 surrogates; a lone one becomes U+FFFD.
+                    let mut units = vec![unit];
+                    if (0xD800..0xDC00).contains(&unit) && self.bytes[self.pos..].starts_with(b"\\U") {
+                        if let Some(low) = self
+                            .utf16_unit(self.pos + 2)
+                            .filter(|low| (0xDC00..0xE000).contains(low))
+                        {
+                            units.push(low);
+                            self.pos += 6;
+                        }
+                    }
+                    for c in char::decode_utf16(units) {
+                        let mut tmp = [0u8; 4];
+                        buf.extend_from_slice(
+                            c.unwrap_or(char::REPLACEMENT_CHARACTER)
+                                .encode_utf8(&mut tmp)
+                                .as_bytes(),
+                        );
+                    }
+                }
+                other => buf.push(other),
+            }
+        }
+        Err(self.error("unterminated quoted string"))
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            while self.peek().map(|b| b.is_ascii_whitespace()).unwrap_or(false) {
+                self.pos += 1;
+            }
+            let rest = &self.bytes[self.pos..];
+            if rest.starts_with(b"//") {
+                while let Some(byte) = self.peek() {
+                    self.pos += 1;
+                    if byte == b'\n' {
+                        break;
+                    }
+                }
+            } else if rest.starts_with(b"/*") {
+                match rest[2..].windows(2).position(|w| w == b"*/") {
+                    Some(end) => self.pos += end + 4,
+                    None => self.pos = self.bytes.len(),
+                }
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_trivia();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    /// The four hex digits of a `\U` escape starting at `pos`.
+    fn utf16_unit(&self, pos: usize) -> Option<u16> {
+        self.bytes
+            .get(pos..pos + 4)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u16::from_str_radix(h, 16).ok())
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("Parse project.pbxproj failed at byte {}: {}", self.pos, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECT: &str = r#"// !$*UTF8*$!
+{
+	archiveVersion = 1;
+	classes = {
+	};
+	objectVersion = 56;
+	objects = {
+
+/* Begin PBXFileReference section */
+		F1 /* Release.xcconfig */ = {isa = PBXFileReference; lastKnownFileType = text.xcconfig; path = Release.xcconfig; sourceTree = "<group>"; };
+/* End PBXFileReference section */
+
+/* Begin PBXGroup section */
+		G0 = {
+			isa = PBXGroup;
+			children = (
+				G1 /* Configs */,
+			);
+			sourceTree = "<group>";
+		};
+		G1 /* Configs */ = {
+			isa = PBXGroup;
+			children = (
+				F1 /* Release.xcconfig */,
+			);
+			path = "Supporting Files/Configs";
+			sourceTree = "<group>";
+		};
+/* End PBXGroup section */
+
+/* Begin PBXNativeTarget section */
+		T1 /* My "App" */ = {
+			isa = PBXNativeTarget;
+			buildConfigurationList = L2 /* Build configuration list for PBXNativeTarget "My \"App\"" */;
+			name = "My \"App\"";
+			productName = 'Caf\U00e9';
+			productType = "com.apple.product-type.application";
+		};
+		T2 /* MyAppTests */ = {
+			isa = PBXNativeTarget;
+			name = MyAppTests;
+			productType = "com.apple.product-type.bundle.unit-test";
+		};
+/* End PBXNativeTarget section */
+
+/* Begin PBXProject section */
+		P0 /* Project object */ = {
+			isa = PBXProject;
+			buildConfigurationList = L1;
+			mainGroup = G0;
+			projectDirPath = "";
+			targets = (
+				T1 /* My "App" */,
+				T2 /* MyAppTests */,
+			);
+		};
+/* End PBXProject section */
+
+/* Begin XCBuildConfiguration section */
+		C1 /* Release */ = {
+			isa = XCBuildConfiguration;
+			buildSettings = {
+				SWIFT_VERSION = 5.0;
+			};
+			name = Release;
+		};
+		C2 /* Release */ = {
+			isa = XCBuildConfiguration;
+			baseConfigurationReference = F1 /* Release.xcconfig */;
+			buildSettings = {
+				"INFOPLIST_KEY_CFBundleDisplayName" = "Say \"hi\"\n";
+				OTHER_LDFLAGS = (
+					"$(inherited)",
+					"-ObjC",
+				);
+				PRODUCT_BUNDLE_IDENTIFIER = "com.example.$(PRODUCT_NAME:rfc1034identifier)";
+			};
+			name = Release;
+		};
+/* End XCBuildConfiguration section */
+
+/* Begin XCConfigurationList section */
+		L1 = {
+			isa = XCConfigurationList;
+			buildConfigurations = (C1);
+			defaultConfigurationName = Release;
+		};
+		L2 = {
+			isa = XCConfigurationList;
+			buildConfigurations = (
+				C2 /* Release */,
+			);
+			defaultConfigurationIsVisible = 0;
+			defaultConfigurationName = Release;
+		};
+/* End XCConfigurationList section */
+	};
+	rootObject = P0 /* Project object */;
+}
+"#;
+
+    #[test]
+    fn reads_targets_and_settings_from_quoted_and_escaped_values() {
+        let project = parse_project(PROJECT, Path::new("/work")).unwrap();
+
+        assert_eq!(project.default_configuration_name.as_deref(), Some("Release"));
+        assert_eq!(
+            project.configuration("Release").unwrap().build_settings["SWIFT_VERSION"],
+            "5.0"
+        );
+
+        let app = project.target("My \"App\"").unwrap();
+        assert!(app.is_application());
+        assert_eq!(app.product_name.as_deref(), Some("Café"));
+        assert_eq!(project.target_by_id("T1").unwrap().name, app.name);
+        assert_eq!(project.application_targets().count(), 1);
+
+        let release = app.configuration("Release").unwrap();
+        assert_eq!(
+            release.build_settings["INFOPLIST_KEY_CFBundleDisplayName"],
+            "Say \"hi\"\n"
+        );
+        assert_eq!(release.build_settings["OTHER_LDFLAGS"], "$(inherited) -ObjC");
+        assert_eq!(
+            release.base_configuration.as_deref(),
+            Some(Path::new("/work/Supporting Files/Configs/Release.xcconfig"))
+        );
+
+        let tests = project.target("MyAppTests").unwrap();
+        assert!(tests.build_configurations.is_empty());
+    }
+
+    #[test]
+    fn reports_where_parsing_stopped() {
+        let error = parse_plist("{ key = \"unterminated; }").unwrap_err();
+        assert!(error.contains("unterminated quoted string"), "{}", error);
+        assert!(parse_plist("{ a = b; } trailing")
+            .unwrap_err()
+            .contains("trailing content"));
+        assert_eq!(
+            parse_plist("( a, 'b c', ) /* done */").unwrap(),
+            PlistValue::Array(vec![PlistValue::String("a".into()), PlistValue::String("b c".into())])
+        );
+    }
+
+    #[test]
+    fn combines_escaped_surrogate_pairs() {
+        let parsed =
+            parse_plist(r#"( "\UD83D\UDE80 Rocket", "lone \UD83D!", "\UDE80\UD83D", "\U00e9\U0041" )"#).unwrap();
+        assert_eq!(
+            parsed,
+            PlistValue::Array(
+                ["🚀 Rocket", "lone \u{FFFD}!", "\u{FFFD}\u{FFFD}", "éA"]
+                    .into_iter()
+                    .map(|value| PlistValue::String(value.into()))
+                    .collect()
+            )
+        );
+        assert!(parse_plist(r#""\UZZZZ""#).unwrap_err().contains("invalid \\U escape"));
+    }
+
+    #[test]
+    fn expands_nested_references_and_modifiers() {
+        let mut resolve = |name: &str| match name {
+            "PRODUCT_NAME" => Some("My App".to_string()),
+            "VARIANT" => Some("NAME".to_string()),
+            "inherited" => Some(String::new()),
+            _ => None,
+        };
+        assert_eq!(
+            expand_references("com.example.$(PRODUCT_NAME:rfc1034identifier,lower)", &mut resolve).as_deref(),
+            Some("com.example.my-app")
+        );
+        assert_eq!(
+            expand_references("${PRODUCT_$(VARIANT)}$(inherited) $5", &mut resolve).as_deref(),
+            Some("My App $5")
+        );
+        assert_eq!(expand_references("$(UNKNOWN)", &mut resolve), None);
+        assert_eq!(expand_references("$(PRODUCT_NAME:bogus)", &mut resolve), None);
+    }
+}
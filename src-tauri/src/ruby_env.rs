@@ -0,0 +1,536 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::command_runner::CommandSpec;
+use crate::commands::DoctorCheck;
+use crate::login_env::LoginEnv;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RubyManager {
+    Rbenv,
+    Rvm,
+    Asdf,
+    Mise,
+    Frum,
+    Chruby,
+    System,
+}
+
+/// Managers in the order they are preferred when the login PATH does not
+/// already point at one of them.
+const MANAGERS: [RubyManager; 6] = [
+    RubyManager::Rbenv,
+    RubyManager::Rvm,
+    RubyManager::Asdf,
+    RubyManager::Mise,
+    RubyManager::Frum,
+    RubyManager::Chruby,
+];
+
+impl RubyManager {
+    fn name(self) -> &'static str {
+        match self {
+            RubyManager::Rbenv => "rbenv",
+            RubyManager::Rvm => "rvm",
+            RubyManager::Asdf => "asdf",
+            RubyManager::Mise => "mise",
+            RubyManager::Frum => "frum",
+            RubyManager::Chruby => "chruby",
+            RubyManager::System => "system Ruby",
+        }
+    }
+
+    /// The version files this manager reads, in its own precedence order.
+    fn version_files(self) -> &'static [&'static str] {
+        match self {
+            RubyManager::Mise => &["mise.toml", ".mise.toml", ".tool-versions", ".ruby-version"],
+            RubyManager::Asdf => &[".tool-versions", ".ruby-version"],
+            RubyManager::Rbenv | RubyManager::Rvm | RubyManager::Frum | RubyManager::Chruby => &[".ruby-version"],
+            RubyManager::System => &[".ruby-version", ".tool-versions", "mise.toml", ".mise.toml"],
+        }
+    }
+
+    fn version_dirs(self, env: &LoginEnv) -> Vec<PathBuf> {
+        let home = env.home();
+        match self {
+            RubyManager::Rbenv => vec![rbenv_root(env).join("versions")],
+            RubyManager::Rvm => vec![home.join(".rvm").join("rubies")],
+            RubyManager::Asdf => vec![asdf_dir(env).join("installs").join("ruby")],
+            RubyManager::Mise => mise_dirs(env)
+                .into_iter()
+                .map(|dir| dir.join("installs").join("ruby"))
+                .collect(),
+            RubyManager::Frum => vec![env_dir(env, "FRUM_DIR", home.join(".frum")).join("versions")],
+            RubyManager::Chruby => vec![home.join(".rubies"), PathBuf::from("/opt/rubies")],
+            RubyManager::System => vec![],
+        }
+    }
+
+    /// Where the manager lives; a `ruby` on PATH below one of these belongs to it.
+    fn roots(self, env: &LoginEnv) -> Vec<PathBuf> {
+        match self {
+            RubyManager::Rbenv => vec![rbenv_root(env)],
+            RubyManager::Rvm => vec![env.home().join(".rvm")],
+            RubyManager::Asdf => vec![asdf_dir(env)],
+            RubyManager::Mise => mise_dirs(env),
+            RubyManager::Frum => vec![env_dir(env, "FRUM_DIR", env.home().join(".frum"))],
+            RubyManager::Chruby | RubyManager::System => self.version_dirs(env),
+        }
+    }
+
+    fn is_installed(self, env: &LoginEnv) -> bool {
+        let has_versions = || self.version_dirs(env).iter().any(|dir| dir.is_dir());
+        match self {
+            RubyManager::Rbenv => env.find_program("rbenv").is_some() || has_versions(),
+            RubyManager::Rvm => env.home().join(".rvm").join("bin").join("rvm").is_file(),
+            RubyManager::Asdf => env.find_program("asdf").is_some() || has_versions(),
+            RubyManager::Mise => {
+                env.find_program("mise").is_some() || env.find_program("rtx").is_some() || has_versions()
+            }
+            RubyManager::Frum => env.find_program("frum").is_some(),
+            RubyManager::Chruby | RubyManager::System => has_versions(),
+        }
+    }
+
+    /// Whether `3.2` may select the newest installed `3.2.x`, as it does for
+    /// managers that match versions by prefix.
+    fn matches_prefix(self) -> bool {
+        matches!(self, RubyManager::Mise | RubyManager::Frum | RubyManager::Chruby)
+    }
+
+    fn install_hint(self, version: &str) -> String {
+        match self {
+            RubyManager::Rbenv => format!("Run `rbenv install {}`.", version),
+            RubyManager::Rvm => format!("Run `rvm install {}`.", version),
+            RubyManager::Asdf => format!("Run `asdf install ruby {}`.", version),
+            RubyManager::Mise => format!("Run `mise install ruby@{}`.", version),
+            RubyManager::Frum => format!("Run `frum install {}`.", version),
+            RubyManager::Chruby => format!("Run `ruby-install ruby {}`.", version),
+            RubyManager::System => format!(
+                "Install rbenv, asdf or mise and Ruby {}, or remove the version file to use the system Ruby.",
+                version
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VersionRequest {
+    pub version: String,
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct RubyResolution {
+    manager: RubyManager,
+    request: Option<VersionRequest>,
+    installed: Option<PathBuf>,
+    ruby_path: Option<PathBuf>,
+    reason: String,
+}
+
+/// Only the filesystem is inspected.
+pub fn resolve(env: &LoginEnv, project_root: &Path) -> RubyResolution {
+    let path_ruby = env.find_program("ruby");
+    let owner = path_ruby.as_deref().and_then(|ruby| {
+        MANAGERS
+            .into_iter()
+            .find(|manager| manager.roots(env).iter().any(|root| ruby.starts_with(root)))
+    });
+    let (manager, mut reason) = match (owner, MANAGERS.into_iter().find(|m| m.is_installed(env))) {
+        (Some(manager), _) => (manager, format!("ruby on the login PATH belongs to {}", manager.name())),
+        (None, Some(manager)) => (
+            manager,
+            format!(
+                "{} is installed but not active in the login shell; running through it",
+                manager.name()
+            ),
+        ),
+        (None, None) => (RubyManager::System, "no Ruby version manager found".to_string()),
+    };
+
+    let request = find_version_request(env, manager, project_root);
+    let installed = request
+        .as_ref()
+        .and_then(|request| find_installed_version(env, manager, &request.version));
+    match (&request, &installed) {
+        (Some(request), Some(_)) => reason.push_str(&format!(
+            "; {} requested by {}",
+            request.version,
+            request.file.display()
+        )),
+        (Some(request), None) if manager == RubyManager::System => reason.push_str(&format!(
+            "; {} requested by {} cannot be selected",
+            request.version,
+            request.file.display()
+        )),
+        (Some(request), None) => reason.push_str(&format!(
+            "; {} requested by {} is not installed in {}",
+            request.version,
+            request.file.display(),
+            manager.name()
+        )),
+        (None, _) if manager == RubyManager::System => reason.push_str("; no version file"),
+        (None, _) => reason.push_str("; no version file, using the manager's default"),
+    }
+
+    let shim = match manager {
+        RubyManager::Rbenv => Some(rbenv_root(env).join("shims").join("ruby")),
+        RubyManager::Asdf => Some(asdf_dir(env).join("shims").join("ruby")),
+        _ => None,
+    };
+    let ruby_path = installed
+        .as_ref()
+        .map(|dir| dir.join("bin").join("ruby"))
+        .or_else(|| shim.filter(|shim| shim.is_file()))
+        .or(path_ruby);
+
+    RubyResolution {
+        manager,
+        request,
+        installed,
+        ruby_path,
+        reason,
+    }
+}
+
+impl RubyResolution {
+    /// A command for `program` that runs under the resolved Ruby, set up the
+    /// way the manager's shell integration would: rbenv and asdf get their
+    /// shims on PATH and the version in their variable, rvm wraps the command
+    /// in `rvm <version> do`, and mise, frum and chruby get the version's
+    /// `bin` directory on PATH.
+    pub fn command(&self, env: &LoginEnv, program: &str) -> CommandSpec {
+        let version_name = self
+            .installed
+            .as_ref()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string());
+        let mut cmd = env.command(program);
+        match self.manager {
+            RubyManager::Rbenv => {
+                cmd.env("PATH", env.path_with(&rbenv_root(env).join("shims")));
+                if let Some(version) = version_name {
+                    cmd.env("RBENV_VERSION", version);
+                }
+            }
+            RubyManager::Asdf => {
+                cmd.env("PATH", env.path_with(&asdf_dir(env).join("shims")));
+                if let Some(version) = version_name {
+                    cmd.env("ASDF_RUBY_VERSION", version);
+                }
+            }
+            RubyManager::Rvm => {
+                if let Some(version) = version_name {
+                    let rvm = env.home().join(".rvm").join("bin").join("rvm");
+                    cmd = env.command(rvm.to_string_lossy());
+                    cmd.arg(version).arg("do").arg(program);
+                }
+            }
+            RubyManager::Mise | RubyManager::Frum | RubyManager::Chruby => {
+                if let Some(dir) = &self.installed {
+                    let mut path = env.path_with(&dir.join("bin"));
+                    // chruby installs gems per Ruby under ~/.gem/ruby/<version>.
+                    let gem_version = version_name.as_deref().and_then(|v| v.strip_prefix("ruby-"));
+                    if let Some(version) = gem_version.filter(|_| self.manager == RubyManager::Chruby) {
+                        let gem_home = env.home().join(".gem").join("ruby").join(version);
+                        path = format!("{}:{}", gem_home.join("bin").display(), path);
+                        cmd.env("GEM_HOME", gem_home.to_string_lossy());
+                    }
+                    cmd.env("PATH", path);
+                }
+            }
+            RubyManager::System => {}
+        }
+        cmd
+    }
+
+    pub fn describe(&self) -> String {
+        let ruby = self
+            .ruby_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "no ruby found".to_string());
+        format!("{} -> {} ({})", self.manager.name(), ruby, self.reason)
+    }
+
+    pub fn doctor_check(&self) -> DoctorCheck {
+        let (status, suggestion) = match &self.request {
+            Some(request) if self.installed.is_none() => (
+                if self.manager == RubyManager::System {
+                    "warn"
+                } else {
+                    "fail"
+                },
+                Some(self.manager.install_hint(&request.version)),
+            ),
+            _ => ("pass", None),
+        };
+        DoctorCheck {
+            name: "Ruby Version Manager".to_string(),
+            status: status.to_string(),
+            detail: self.describe(),
+            suggestion,
+        }
+    }
+}
+
+/// Searches the project root, then its parents; rbenv falls back to its
+/// global version.
+fn find_version_request(env: &LoginEnv, manager: RubyManager, project_root: &Path) -> Option<VersionRequest> {
+    let files = manager.version_files();
+    let nearest = project_root.ancestors().find_map(|dir| {
+        files.iter().find_map(|name| {
+            let file = dir.join(name);
+            let version = read_version_file(&file)?;
+            Some(VersionRequest { version, file })
+        })
+    });
+    nearest.or_else(|| {
+        let file = rbenv_root(env).join("version");
+        let version = read_version_file(&file).filter(|_| manager == RubyManager::Rbenv)?;
+        Some(VersionRequest { version, file })
+    })
+}
+
+fn read_version_file(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let file_name = path.file_name()?.to_string_lossy();
+    let version = match file_name.as_ref() {
+        ".tool-versions" => parse_tool_versions(&content),
+        "mise.toml" | ".mise.toml" => parse_mise_toml(&content),
+        _ => content.lines().next().map(|line| line.trim().to_string()),
+    }?;
+    Some(version).filter(|v| !v.is_empty() && v != "system")
+}
+
+/// `ruby 3.2.2 3.1.4` in `.tool-versions`; the first version wins.
+fn parse_tool_versions(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut parts = line.split_whitespace();
+        if parts.next()? != "ruby" {
+            return None;
+        }
+        parts.next().map(str::to_string)
+    })
+}
+
+/// `ruby = "3.2"`, `ruby = ["3.2", "3.1"]` or `ruby = { version = "3.2" }`
+/// under `[tools]` in `mise.toml`.
+fn parse_mise_toml(content: &str) -> Option<String> {
+    let mut in_tools = false;
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.starts_with('[') {
+            in_tools = line == "[tools]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().trim_matches('"');
+        if (in_tools && key == "ruby") || key == "tools.ruby" {
+            return value.split('"').nth(1).map(str::to_string);
+        }
+    }
+    None
+}
+
+/// The install directory for `version`, accepting a `ruby-` prefix on either
+/// side and, for managers that allow it, the newest `version.*` match.
+fn find_installed_version(env: &LoginEnv, manager: RubyManager, version: &str) -> Option<PathBuf> {
+    let version = version.strip_prefix("ruby-").unwrap_or(version);
+    let dirs = manager.version_dirs(env);
+    let exact = dirs.iter().find_map(|dir| {
+        [version.to_string(), format!("ruby-{}", version)]
+            .into_iter()
+            .map(|name| dir.join(name))
+            .find(|candidate| candidate.is_dir())
+    });
+    if exact.is_some() || !manager.matches_prefix() {
+        return exact;
+    }
+
+    let prefix = format!("{}.", version);
+    dirs.iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.is_dir() && name.strip_prefix("ruby-").unwrap_or(&name).starts_with(&prefix)
+        })
+        .max_by_key(|path| version_key(&path.file_name().unwrap_or_default().to_string_lossy()))
+}
+
+fn version_key(name: &str) -> Vec<u64> {
+    name.strip_prefix("ruby-")
+        .unwrap_or(name)
+        .split(['.', '-'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+fn env_dir(env: &LoginEnv, key: &str, fallback: PathBuf) -> PathBuf {
+    env.get(key)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or(fallback)
+}
+
+fn rbenv_root(env: &LoginEnv) -> PathBuf {
+    env_dir(env, "RBENV_ROOT", env.home().join(".rbenv"))
+}
+
+fn asdf_dir(env: &LoginEnv) -> PathBuf {
+    env_dir(env, "ASDF_DATA_DIR", env.home().join(".asdf"))
+}
+
+/// mise's data directory, plus rtx's from before the rename.
+fn mise_dirs(env: &LoginEnv) -> Vec<PathBuf> {
+    let data_home = env_dir(env, "XDG_DATA_HOME", env.home().join(".local").join("share"));
+    vec![
+        env_dir(env, "MISE_DATA_DIR", data_home.join("mise")),
+        env_dir(env, "RTX_DATA_DIR", data_home.join("rtx")),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    fn env_for(home: &TestDir) -> LoginEnv {
+        LoginEnv::from_vars(&[
+            ("HOME", &home.to_string_lossy()),
+            ("PATH", &home.join("bin").to_string_lossy()),
+        ])
+    }
+
+    fn make_executable(home: &TestDir, file: &str) {
+        home.write(file, "#!/bin/sh\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(home.join(file), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    fn installed_name(env: &LoginEnv, manager: RubyManager, version: &str) -> Option<String> {
+        find_installed_version(env, manager, version).map(|dir| dir.file_name().unwrap().to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn tool_versions_take_the_first_ruby_version() {
+        let content = "# pinned\nnodejs 20.1.0\nruby 3.2.2 3.1.4 # fallback\n";
+        assert_eq!(parse_tool_versions(content).as_deref(), Some("3.2.2"));
+        assert_eq!(parse_tool_versions("rubyx 1.0\n# ruby 3.3.0\n"), None);
+    }
+
+    #[test]
+    fn mise_toml_accepts_every_ruby_form() {
+        for content in [
+            "[tools]\nruby = \"3.2\"\n",
+            "[tools]\nnode = \"20\"\nruby = [\"3.2\", \"3.1\"]\n",
+            "[tools]\nruby = { version = \"3.2\" } # pinned\n",
+            "tools.ruby = \"3.2\"\n",
+        ] {
+            assert_eq!(parse_mise_toml(content).as_deref(), Some("3.2"), "{}", content);
+        }
+        assert_eq!(parse_mise_toml("[env]\nruby = \"3.2\"\n[tools]\nnode = \"20\"\n"), None);
+    }
+
+    #[test]
+    fn installed_versions_match_exactly_or_by_newest_prefix() {
+        let home = TestDir::with_files(&[
+            (".rbenv/versions/3.2.2/bin/ruby", ""),
+            (".rubies/ruby-3.3.0/bin/ruby", ""),
+            (".local/share/mise/installs/ruby/3.2.9/bin/ruby", ""),
+            (".local/share/mise/installs/ruby/3.2.10/bin/ruby", ""),
+            (".local/share/mise/installs/ruby/3.3.0/bin/ruby", ""),
+        ]);
+        let env = env_for(&home);
+
+        assert_eq!(
+            installed_name(&env, RubyManager::Rbenv, "3.2.2").as_deref(),
+            Some("3.2.2")
+        );
+        assert_eq!(
+            installed_name(&env, RubyManager::Rbenv, "ruby-3.2.2").as_deref(),
+            Some("3.2.2")
+        );
+        assert_eq!(
+            installed_name(&env, RubyManager::Chruby, "3.3.0").as_deref(),
+            Some("ruby-3.3.0")
+        );
+        // rbenv only selects exact versions; mise picks the newest 3.2.x.
+        assert_eq!(installed_name(&env, RubyManager::Rbenv, "3.2"), None);
+        assert_eq!(
+            installed_name(&env, RubyManager::Mise, "3.2").as_deref(),
+            Some("3.2.10")
+        );
+        assert_eq!(installed_name(&env, RubyManager::Mise, "3.4"), None);
+        assert!(version_key("ruby-3.2.10") > version_key("3.2.9"));
+    }
+
+    #[test]
+    fn the_manager_owning_ruby_on_path_wins_over_installed_ones() {
+        let home = TestDir::with_files(&[
+            (".rbenv/versions/3.2.2/bin/ruby", ""),
+            (".asdf/installs/ruby/3.1.4/bin/ruby", ""),
+            ("project/.ruby-version", "3.2.2\n"),
+            ("project/.tool-versions", "ruby 3.1.4\n"),
+        ]);
+        let project = home.join("project");
+
+        // rbenv is installed but no ruby is on the PATH: it is used, with
+        // the version from .ruby-version.
+        let resolution = resolve(&env_for(&home), &project);
+        assert_eq!(resolution.manager, RubyManager::Rbenv);
+        assert_eq!(resolution.request.as_ref().unwrap().version, "3.2.2");
+        assert_eq!(resolution.installed, Some(home.join(".rbenv/versions/3.2.2")));
+        assert!(
+            resolution.reason.starts_with("rbenv is installed but not active"),
+            "{}",
+            resolution.reason
+        );
+
+        // asdf's shim on the PATH makes asdf the owner, which reads .tool-versions first.
+        make_executable(&home, ".asdf/shims/ruby");
+        let env = LoginEnv::from_vars(&[
+            ("HOME", &home.to_string_lossy()),
+            ("PATH", &home.join(".asdf/shims").to_string_lossy()),
+        ]);
+        let resolution = resolve(&env, &project);
+        assert_eq!(resolution.manager, RubyManager::Asdf);
+        assert_eq!(resolution.request.as_ref().unwrap().version, "3.1.4");
+        assert_eq!(
+            resolution.ruby_path,
+            Some(home.join(".asdf/installs/ruby/3.1.4/bin/ruby"))
+        );
+        assert_eq!(resolution.doctor_check().status, "pass");
+        let command = resolution.command(&env, "bundle");
+        assert_eq!(command.env.get("ASDF_RUBY_VERSION").map(String::as_str), Some("3.1.4"));
+    }
+
+    #[test]
+    fn doctor_check_fails_for_a_missing_version_and_warns_without_a_manager() {
+        let home = TestDir::with_files(&[
+            (".rbenv/versions/3.2.2/bin/ruby", ""),
+            ("project/.ruby-version", "3.4.1\n"),
+        ]);
+        let check = resolve(&env_for(&home), &home.join("project")).doctor_check();
+        assert_eq!(check.status, "fail");
+        assert_eq!(check.suggestion.as_deref(), Some("Run `rbenv install 3.4.1`."));
+        assert!(check.detail.contains("3.4.1 requested by"), "{}", check.detail);
+
+        let home = TestDir::with_files(&[("project/.ruby-version", "3.4.1\n")]);
+        let resolution = resolve(&env_for(&home), &home.join("project"));
+        assert_eq!(resolution.manager, RubyManager::System);
+        let check = resolution.doctor_check();
+        assert_eq!(check.status, "warn");
+        assert!(check.detail.contains("cannot be selected"), "{}", check.detail);
+    }
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::command_runner::CommandRunner;
+use crate::commands::{LaneRunResult, ProjectConfig};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::login_env::LoginEnv;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub run_id: String,
+    pub lane: String,
+    pub status: String,
+    pub exit_code: i32,
+    pub started_at_ms: u64,
+    pub finished_at_ms: u64,
+    pub duration_ms: u64,
+    pub git_head: Option<String>,
+    pub config_hash: Option<String>,
+    /// Secrets masked in the output; absent in records from older versions.
+    #[serde(default)]
+    pub redactions: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    #[serde(flatten)]
+    pub summary: RunSummary,
+    pub output: String,
+}
+
+/// State captured before a run starts, so the record reflects the checkout
+/// that was built even if the lane itself commits (e.g. `versioning`).
+pub struct RunStart {
+    started_at: SystemTime,
+    git_head: Option<String>,
+}
+
+impl RunStart {
+    pub fn now(runner: &dyn CommandRunner, env: &LoginEnv, project_root: &Path) -> Self {
+        Self {
+            started_at: SystemTime::now(),
+            git_head: git_head(runner, env, project_root),
+        }
+    }
+}
+
+fn runs_dir(project_root: &Path) -> PathBuf {
+    project_root.join(".fastlane-desktop").join("runs")
+}
+
+pub fn save_run(
+    project_root: &Path,
+    start: RunStart,
+    result: &LaneRunResult,
+    config: Option<&ProjectConfig>,
+) -> AppResult<PathBuf> {
+    let path = run_file(project_root, &result.run_id)?;
+    let RunStart { started_at, git_head } = start;
+    let finished_at = SystemTime::now();
+    let record = RunRecord {
+        summary: RunSummary {
+            run_id: result.run_id.clone(),
+            lane: result.lane.clone(),
+            status: result.status.clone(),
+            exit_code: result.exit_code,
+            started_at_ms: unix_millis(started_at),
+            finished_at_ms: unix_millis(finished_at),
+            duration_ms: finished_at
+                .duration_since(started_at)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            git_head,
+            config_hash: config.and_then(config_hash),
+            redactions: result.redactions,
+        },
+        output: result.output.clone(),
+    };
+
+    let dir = runs_dir(project_root);
+    fs::create_dir_all(&dir).map_err(|e| AppError::io("Create runs dir", &dir, e))?;
+    let payload = serde_json::to_string_pretty(&record)
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Serialize run record failed: {}", e)))?;
+    fs::write(&path, payload).map_err(|e| AppError::io("Write run record", &path, e))?;
+    Ok(path)
+}
+
+pub fn list_runs(project_root: &Path) -> AppResult<Vec<RunSummary>> {
+    let dir = runs_dir(project_root);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let entries = fs::read_dir(&dir).map_err(|e| AppError::io("Read runs dir", &dir, e))?;
+    let mut runs = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.extension().and_then(OsStr::to_str) != Some("json") {
+            continue;
+        }
+        // Skip files that are not run records instead of failing the whole listing.
+        if let Ok(record) = read_record(&path) {
+            runs.push(record.summary);
+        }
+    }
+    runs.sort_by_key(|run| std::cmp::Reverse(run.started_at_ms));
+    Ok(runs)
+}
+
+pub fn load_run(project_root: &Path, run_id: &str) -> AppResult<RunRecord> {
+    let path = run_file(project_root, run_id)?;
+    if !path.exists() {
+        return Err(AppError::new(ErrorKind::RunNotFound, format!("Run not found: {}", run_id)).with_path(&path));
+    }
+    read_record(&path)
+}
+
+/// Every stored run when `run_ids` is empty.
+pub fn delete_runs(project_root: &Path, run_ids: &[String]) -> AppResult<usize> {
+    let targets = if run_ids.is_empty() {
+        list_runs(project_root)?
+            .into_iter()
+            .map(|run| run.run_id)
+            .collect::<Vec<_>>()
+    } else {
+        run_ids.to_vec()
+    };
+
+    let mut deleted = 0;
+    for run_id in targets {
+        let path = run_file(project_root, &run_id)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| AppError::io(&format!("Delete run {}", run_id), &path, e))?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+fn read_record(path: &Path) -> AppResult<RunRecord> {
+    let content = fs::read_to_string(path).map_err(|e| AppError::io("Read run record", path, e))?;
+    serde_json::from_str::<RunRecord>(&content).map_err(|e| AppError::parse("run record", path, e))
+}
+
+fn run_file(project_root: &Path, run_id: &str) -> AppResult<PathBuf> {
+    let valid = !run_id.is_empty()
+        && run_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid runId: {}", run_id),
+        ));
+    }
+    Ok(runs_dir(project_root).join(format!("{}.json", run_id)))
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn git_head(runner: &dyn CommandRunner, env: &LoginEnv, project_root: &Path) -> Option<String> {
+    let output = runner
+        .output(env.command("git").args(["rev-parse", "HEAD"]).current_dir(project_root))
+        .ok()?;
+    if !output.success() {
+        return None;
+    }
+    let head = output.stdout.trim().to_string();
+    if head.is_empty() {
+        None
+    } else {
+        Some(head)
+    }
+}
+
+/// Without secret values: the record is kept in the project, and an
+/// unsalted hash over a guessable secret could be checked offline.
+fn config_hash(config: &ProjectConfig) -> Option<String> {
+    let payload = serde_json::to_vec(&config.clone().without_secret_values()).ok()?;
+    Some(format!("{:x}", Sha256::digest(&payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environments::{EnvValue, LaneEnvironment};
+
+    #[test]
+    fn config_hash_does_not_depend_on_secret_values() {
+        let config = |secret: &str| ProjectConfig {
+            scheme_dev: "MyApp".to_string(),
+            pgyer_api_key: format!("pgyer-{}", secret),
+            match_git_url: format!("https://{}@github.com/org/certs.git", secret),
+            environments: vec![LaneEnvironment {
+                name: "staging".to_string(),
+                values: vec![EnvValue {
+                    key: "SENTRY_AUTH_TOKEN".to_string(),
+                    value: secret.to_string(),
+                }],
+            }],
+            ..ProjectConfig::default()
+        };
+
+        assert_eq!(config_hash(&config("first")), config_hash(&config("second")));
+        let other_scheme = ProjectConfig {
+            scheme_dev: "Other".to_string(),
+            ..config("first")
+        };
+        assert_ne!(config_hash(&config("first")), config_hash(&other_scheme));
+    }
+}
@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dotenv::Document;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::templates::RenderedFile;
+
+/// What the app last wrote to each runtime env file. Values are stored as
+/// hashes; secret values are not recorded at all, since a short unsalted
+/// hash of a guessable secret can be checked offline.
+const STATE_PATH: &str = ".fastlane-desktop/runtime-env.json";
+
+/// A dotenv file in which the app owns `values`; every other line belongs
+/// to whoever edits the file and is kept as it is.
+pub struct ManagedFile {
+    /// Relative to the project root.
+    pub path: PathBuf,
+    /// Comment written at the top when the file is created.
+    pub header: String,
+    pub values: Vec<(String, String)>,
+}
+
+/// A managed key edited by hand since the app wrote it; the edit is kept.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvConflict {
+    pub path: String,
+    pub key: String,
+    pub file_value: String,
+    /// The value from the config, which was not written.
+    pub app_value: String,
+}
+
+pub struct RuntimeEnv {
+    pub files: Vec<RenderedFile>,
+    pub conflicts: Vec<EnvConflict>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct State {
+    /// File path, then key, then the hash of the value the app wrote (empty
+    /// for secrets).
+    files: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Merges `managed` into the files on disk: managed keys are updated in
+/// place (or appended), keys the app wrote before but no longer manages are
+/// removed, and comments, ordering and unknown keys stay untouched. A managed
+/// key whose value differs from both the last written and the new value was
+/// edited by hand; it is reported and left alone. Keys for which `is_secret`
+/// holds cannot be compared that way and are always written.
+pub fn render(project_root: &Path, managed: &[ManagedFile], is_secret: impl Fn(&str) -> bool) -> AppResult<RuntimeEnv> {
+    let previous = read_state(project_root)?;
+    let mut state = State::default();
+    let mut files = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for file in managed {
+        let relative = file.path.to_string_lossy().to_string();
+        let path = project_root.join(&file.path);
+        let mut document = if path.is_file() {
+            let content = fs::read_to_string(&path).map_err(|e| AppError::io("Read env file", &path, e))?;
+            Document::parse(&content)
+        } else {
+            let mut document = Document::parse("");
+            document.push_comment(&file.header);
+            document
+        };
+        let written = previous.files.get(&relative);
+
+        let mut hashes = BTreeMap::new();
+        for (key, value) in &file.values {
+            if is_secret(key) {
+                document.set(key, value);
+                hashes.insert(key.clone(), String::new());
+                continue;
+            }
+            let current = document.get(key).map(str::to_string);
+            let recorded = written.and_then(|written| written.get(key));
+            match (current, recorded) {
+                (Some(current), Some(recorded)) if current != *value && hash(&current) != *recorded => {
+                    hashes.insert(key.clone(), recorded.clone());
+                    conflicts.push(EnvConflict {
+                        path: relative.clone(),
+                        key: key.clone(),
+                        file_value: current,
+                        app_value: value.clone(),
+                    });
+                }
+                _ => {
+                    document.set(key, value);
+                    hashes.insert(key.clone(), hash(value));
+                }
+            }
+        }
+        for (key, recorded) in written.into_iter().flatten() {
+            let unchanged = recorded.is_empty() || document.get(key).is_some_and(|current| hash(current) == *recorded);
+            if !hashes.contains_key(key) && unchanged {
+                document.remove(key);
+            }
+        }
+
+        state.files.insert(relative, hashes);
+        files.push(RenderedFile {
+            path: file.path.clone(),
+            content: document.render(),
+            executable: false,
+        });
+    }
+
+    let state_json = serde_json::to_string_pretty(&state).map_err(|e| {
+        AppError::new(
+            ErrorKind::Internal,
+            format!("Serialize runtime env state failed: {}", e),
+        )
+    })?;
+    files.push(RenderedFile {
+        path: PathBuf::from(STATE_PATH),
+        content: format!("{}\n", state_json),
+        executable: false,
+    });
+    Ok(RuntimeEnv { files, conflicts })
+}
+
+/// Keys the app last wrote as secrets, in any runtime env file.
+pub fn secret_keys(project_root: &Path) -> BTreeSet<String> {
+    read_state(project_root)
+        .unwrap_or_default()
+        .files
+        .into_values()
+        .flat_map(|keys| keys.into_iter().filter(|(_, hash)| hash.is_empty()).map(|(key, _)| key))
+        .collect()
+}
+
+fn read_state(project_root: &Path) -> AppResult<State> {
+    let path = project_root.join(STATE_PATH);
+    if !path.is_file() {
+        return Ok(State::default());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| AppError::io("Read runtime env state", &path, e))?;
+    serde_json::from_str(&content).map_err(|e| AppError::parse("runtime env state", &path, e))
+}
+
+fn hash(value: &str) -> String {
+    format!("{:x}", Sha256::digest(value.as_bytes()))[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates;
+    use crate::test_support::TestDir;
+
+    fn env_file(values: &[(&str, &str)]) -> Vec<ManagedFile> {
+        vec![ManagedFile {
+            path: PathBuf::from("fastlane/.env.fastlane"),
+            header: "Managed".to_string(),
+            values: values
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }]
+    }
+
+    fn is_secret(key: &str) -> bool {
+        key == "DEPLOY_HOOK" || crate::redact::is_secret_key(key)
+    }
+
+    fn render_and_write(root: &Path, values: &[(&str, &str)]) -> Vec<EnvConflict> {
+        let rendered = render(root, &env_file(values), is_secret).unwrap();
+        templates::write(root, &rendered.files).unwrap();
+        rendered.conflicts
+    }
+
+    fn read(root: &Path, path: &str) -> String {
+        fs::read_to_string(root.join(path)).unwrap()
+    }
+
+    #[test]
+    fn secret_values_are_written_but_not_recorded_in_the_state() {
+        let root = TestDir::with_files(&[]);
+        let values = [
+            ("SCHEME", "MyApp"),
+            ("PGYER_API_KEY", "pgyer-key-123"),
+            ("DEPLOY_HOOK", "https://hooks/abc"),
+        ];
+        render_and_write(&root, &values);
+
+        let env = Document::parse(&read(&root, "fastlane/.env.fastlane"));
+        assert_eq!(env.get("PGYER_API_KEY"), Some("pgyer-key-123"));
+        assert_eq!(env.get("DEPLOY_HOOK"), Some("https://hooks/abc"));
+
+        let state: State = serde_json::from_str(&read(&root, STATE_PATH)).unwrap();
+        let recorded = &state.files["fastlane/.env.fastlane"];
+        assert_eq!(recorded["SCHEME"], hash("MyApp"));
+        assert_eq!(recorded["PGYER_API_KEY"], "");
+        assert_eq!(recorded["DEPLOY_HOOK"], "");
+    }
+
+    #[test]
+    fn hand_edits_are_kept_for_plain_values_and_overwritten_for_secrets() {
+        let root = TestDir::with_files(&[]);
+        render_and_write(&root, &[("SCHEME", "MyApp"), ("PGYER_API_KEY", "old-key-123")]);
+        let path = root.join("fastlane/.env.fastlane");
+        let edited = read(&root, "fastlane/.env.fastlane")
+            .replace("SCHEME=MyApp", "SCHEME=Edited")
+            .replace("old-key-123", "hand-key-456");
+        fs::write(&path, edited).unwrap();
+
+        let conflicts = render_and_write(&root, &[("SCHEME", "MyApp"), ("PGYER_API_KEY", "new-key-789")]);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            (conflicts[0].key.as_str(), conflicts[0].file_value.as_str()),
+            ("SCHEME", "Edited")
+        );
+        let env = Document::parse(&read(&root, "fastlane/.env.fastlane"));
+        assert_eq!(env.get("SCHEME"), Some("Edited"));
+        assert_eq!(env.get("PGYER_API_KEY"), Some("new-key-789"));
+    }
+
+    #[test]
+    fn secrets_no_longer_managed_are_removed() {
+        let root = TestDir::with_files(&[]);
+        render_and_write(&root, &[("SCHEME", "MyApp"), ("DEPLOY_HOOK", "https://hooks/abc")]);
+
+        render_and_write(&root, &[("SCHEME", "MyApp")]);
+
+        let env = Document::parse(&read(&root, "fastlane/.env.fastlane"));
+        assert_eq!(env.get("SCHEME"), Some("MyApp"));
+        assert_eq!(env.get("DEPLOY_HOOK"), None);
+    }
+}
@@ -0,0 +1,489 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{AppError, AppResult, ErrorKind};
+
+/// Encrypted with the passphrase key so a wrong passphrase is detected
+/// before anything is decrypted or written.
+const CHECK_PLAINTEXT: &[u8] = b"ios-fastlane-desktop secret store";
+const CHECK_AAD: &[u8] = b"check";
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretRef {
+    /// e.g. `PGYER_API_KEY`.
+    pub key: String,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretInfo {
+    pub name: String,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretList {
+    pub exists: bool,
+    pub unlocked: bool,
+    pub secrets: Vec<SecretInfo>,
+}
+
+/// `secrets.json` in the app data dir. Names and timestamps are plain; every
+/// value is sealed with ChaCha20-Poly1305 under a key derived from the
+/// passphrase with Argon2id, and bound to its name.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreFile {
+    version: u32,
+    kdf: KdfParams,
+    check: Sealed,
+    secrets: Vec<StoredSecret>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KdfParams {
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredSecret {
+    name: String,
+    created_at_ms: u64,
+    updated_at_ms: u64,
+    value: Sealed,
+}
+
+/// The key of the unlocked store, kept in memory for the session and managed
+/// as Tauri state. Nothing can be read or changed while it is locked.
+#[derive(Default)]
+pub struct SecretStore {
+    key: Mutex<Option<[u8; 32]>>,
+}
+
+pub fn store_path(app: &AppHandle) -> AppResult<PathBuf> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Resolve app data dir failed: {}", e)))?;
+    Ok(dir.join("secrets.json"))
+}
+
+impl SecretStore {
+    /// Derives the key from `passphrase` and keeps it. Creates the store on
+    /// first use.
+    pub fn unlock(&self, path: &Path, passphrase: &str) -> AppResult<Vec<SecretInfo>> {
+        let (store, key) = if path.exists() {
+            let store = read_store(path)?;
+            let key = derive_key(passphrase, &store.kdf)?;
+            if open(&key, &store.check, CHECK_AAD).is_none() {
+                return Err(
+                    AppError::new(ErrorKind::InvalidInput, "Wrong passphrase for the secret store.").with_path(path),
+                );
+            }
+            (store, key)
+        } else {
+            if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+                return Err(AppError::new(
+                    ErrorKind::InvalidInput,
+                    format!("Use a passphrase of at least {} characters.", MIN_PASSPHRASE_LEN),
+                ));
+            }
+            let kdf = KdfParams {
+                salt: STANDARD.encode(random_bytes::<16>()?),
+                // Argon2id with the OWASP-recommended minimum cost.
+                memory_kib: 19 * 1024,
+                iterations: 2,
+                parallelism: 1,
+            };
+            let key = derive_key(passphrase, &kdf)?;
+            let store = StoreFile {
+                version: 1,
+                kdf,
+                check: seal(&key, CHECK_PLAINTEXT, CHECK_AAD)?,
+                secrets: vec![],
+            };
+            write_store(path, &store)?;
+            (store, key)
+        };
+        *self.key.lock().unwrap() = Some(key);
+        Ok(infos(&store))
+    }
+
+    pub fn lock(&self) {
+        *self.key.lock().unwrap() = None;
+    }
+
+    pub fn list(&self, path: &Path) -> AppResult<SecretList> {
+        let exists = path.exists();
+        let secrets = if exists { infos(&read_store(path)?) } else { vec![] };
+        Ok(SecretList {
+            exists,
+            unlocked: self.key.lock().unwrap().is_some(),
+            secrets,
+        })
+    }
+
+    pub fn add(&self, path: &Path, name: &str, value: &str) -> AppResult<SecretInfo> {
+        validate_name(name)?;
+        let key = self.unlocked_key()?;
+        let mut store = read_store(path)?;
+        if store.secrets.iter().any(|secret| secret.name == name) {
+            return Err(
+                AppError::new(ErrorKind::InvalidInput, format!("Secret '{}' already exists.", name))
+                    .with_hint("Rotate it to change its value."),
+            );
+        }
+        let now = now_ms();
+        store.secrets.push(StoredSecret {
+            name: name.to_string(),
+            created_at_ms: now,
+            updated_at_ms: now,
+            value: seal(&key, value.as_bytes(), name.as_bytes())?,
+        });
+        store.secrets.sort_by(|a, b| a.name.cmp(&b.name));
+        write_store(path, &store)?;
+        info(&store, name)
+    }
+
+    pub fn rotate(&self, path: &Path, name: &str, value: &str) -> AppResult<SecretInfo> {
+        let key = self.unlocked_key()?;
+        let mut store = read_store(path)?;
+        let secret = store
+            .secrets
+            .iter_mut()
+            .find(|secret| secret.name == name)
+            .ok_or_else(|| secret_not_found(name))?;
+        secret.value = seal(&key, value.as_bytes(), name.as_bytes())?;
+        secret.updated_at_ms = now_ms();
+        write_store(path, &store)?;
+        info(&store, name)
+    }
+
+    pub fn delete(&self, path: &Path, name: &str) -> AppResult<()> {
+        self.unlocked_key()?;
+        let mut store = read_store(path)?;
+        let before = store.secrets.len();
+        store.secrets.retain(|secret| secret.name != name);
+        if store.secrets.len() == before {
+            return Err(secret_not_found(name));
+        }
+        write_store(path, &store)
+    }
+
+    /// `(key, value)` for every reference, decrypted from the store.
+    pub fn resolve(&self, path: &Path, refs: &[SecretRef]) -> AppResult<Vec<(String, String)>> {
+        let key = self.unlocked_key()?;
+        let store = read_store(path)?;
+        refs.iter()
+            .map(|reference| {
+                let secret = store
+                    .secrets
+                    .iter()
+                    .find(|secret| secret.name == reference.secret)
+                    .ok_or_else(|| secret_not_found(&reference.secret))?;
+                let value = open(&key, &secret.value, secret.name.as_bytes()).ok_or_else(|| {
+                    AppError::new(
+                        ErrorKind::Parse,
+                        format!("Secret '{}' could not be decrypted.", secret.name),
+                    )
+                    .with_path(path)
+                })?;
+                let value = String::from_utf8(value).map_err(|e| AppError::parse("secret value", path, e))?;
+                Ok((reference.key.clone(), value))
+            })
+            .collect()
+    }
+
+    fn unlocked_key(&self) -> AppResult<[u8; 32]> {
+        self.key.lock().unwrap().ok_or_else(|| {
+            AppError::new(ErrorKind::SecretStoreLocked, "The secret store is locked.")
+                .with_hint("Unlock it with its passphrase in the Secrets panel.")
+        })
+    }
+}
+
+pub fn validate_refs(refs: &[SecretRef]) -> AppResult<()> {
+    for reference in refs {
+        if !crate::dotenv::is_valid_key(&reference.key) || reference.secret.is_empty() {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid secret reference: {} -> {}", reference.key, reference.secret),
+            )
+            .with_hint("Pick an environment variable name and a secret from the store."));
+        }
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> AppResult<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(
+            AppError::new(ErrorKind::InvalidInput, format!("Invalid secret name: '{}'", name))
+                .with_hint("Use letters, digits, '.', '-' and '_'."),
+        )
+    }
+}
+
+fn secret_not_found(name: &str) -> AppError {
+    AppError::new(ErrorKind::SecretNotFound, format!("Secret not found: {}", name))
+        .with_hint("Add it in the Secrets panel or remove the reference.")
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> AppResult<[u8; 32]> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Invalid key derivation parameters: {}", e)))?;
+    let salt = STANDARD
+        .decode(&kdf.salt)
+        .map_err(|e| AppError::new(ErrorKind::Parse, format!("Invalid secret store salt: {}", e)))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Derive secret store key failed: {}", e)))?;
+    Ok(key)
+}
+
+fn seal(key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> AppResult<Sealed> {
+    let nonce: [u8; 12] = random_bytes()?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|_| AppError::new(ErrorKind::Internal, "Encrypt secret failed"))?;
+    Ok(Sealed {
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+fn open(key: &[u8; 32], sealed: &Sealed, aad: &[u8]) -> Option<Vec<u8>> {
+    let nonce = STANDARD.decode(&sealed.nonce).ok().filter(|nonce| nonce.len() == 12)?;
+    let ciphertext = STANDARD.decode(&sealed.ciphertext).ok()?;
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad })
+        .ok()
+}
+
+fn random_bytes<const N: usize>() -> AppResult<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Generate random bytes failed: {}", e)))?;
+    Ok(bytes)
+}
+
+fn read_store(path: &Path) -> AppResult<StoreFile> {
+    if !path.exists() {
+        return Err(
+            AppError::new(ErrorKind::SecretStoreLocked, "No secret store has been created yet.")
+                .with_hint("Unlock the Secrets panel with a new passphrase to create it.")
+                .with_path(path),
+        );
+    }
+    let content = fs::read_to_string(path).map_err(|e| AppError::io("Read secret store", path, e))?;
+    serde_json::from_str(&content).map_err(|e| AppError::parse("secret store", path, e))
+}
+
+/// Writes through a temporary file so a failed write never leaves a
+/// truncated store, readable only by the user.
+fn write_store(path: &Path, store: &StoreFile) -> AppResult<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| AppError::io("Create app data dir", dir, e))?;
+    }
+    let payload = serde_json::to_string_pretty(store)
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Serialize secret store failed: {}", e)))?;
+    let temp = path.with_extension("json.tmp");
+    // A leftover temp file would keep its old mode, so start from a new one.
+    if temp.exists() {
+        fs::remove_file(&temp).map_err(|e| AppError::io("Remove stale secret store temp file", &temp, e))?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&temp)
+        .and_then(|mut file| file.write_all(payload.as_bytes()).and_then(|_| file.sync_all()))
+        .map_err(|e| AppError::io("Write secret store", &temp, e))?;
+    fs::rename(&temp, path).map_err(|e| AppError::io("Write secret store", path, e))
+}
+
+fn infos(store: &StoreFile) -> Vec<SecretInfo> {
+    store
+        .secrets
+        .iter()
+        .map(|secret| SecretInfo {
+            name: secret.name.clone(),
+            created_at_ms: secret.created_at_ms,
+            updated_at_ms: secret.updated_at_ms,
+        })
+        .collect()
+}
+
+fn info(store: &StoreFile, name: &str) -> AppResult<SecretInfo> {
+    infos(store)
+        .into_iter()
+        .find(|secret| secret.name == name)
+        .ok_or_else(|| secret_not_found(name))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    fn reference(key: &str, secret: &str) -> SecretRef {
+        SecretRef {
+            key: key.to_string(),
+            secret: secret.to_string(),
+        }
+    }
+
+    fn unlocked(dir: &TestDir) -> (SecretStore, PathBuf) {
+        let path = dir.join("secrets.json");
+        let store = SecretStore::default();
+        store.unlock(&path, PASSPHRASE).unwrap();
+        (store, path)
+    }
+
+    #[test]
+    fn unlock_creates_the_store_and_checks_the_passphrase() {
+        let dir = TestDir::with_files(&[]);
+        let path = dir.join("secrets.json");
+        let store = SecretStore::default();
+        let error = store.unlock(&path, "short").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidInput);
+        assert!(!path.exists());
+
+        assert!(store.unlock(&path, PASSPHRASE).unwrap().is_empty());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        store.lock();
+        let list = store.list(&path).unwrap();
+        assert!(list.exists && !list.unlocked);
+
+        let error = store.unlock(&path, "wrong passphrase").unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidInput);
+        assert!(!store.list(&path).unwrap().unlocked);
+        store.unlock(&path, PASSPHRASE).unwrap();
+        assert!(store.list(&path).unwrap().unlocked);
+    }
+
+    #[test]
+    fn secrets_round_trip_through_add_rotate_and_delete() {
+        let dir = TestDir::with_files(&[]);
+        let (store, path) = unlocked(&dir);
+        store.add(&path, "pgyer", "first").unwrap();
+        assert_eq!(
+            store.add(&path, "pgyer", "again").unwrap_err().kind,
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            store.add(&path, "bad name", "x").unwrap_err().kind,
+            ErrorKind::InvalidInput
+        );
+        assert!(!fs::read_to_string(&path).unwrap().contains("first"));
+
+        let refs = [reference("PGYER_API_KEY", "pgyer")];
+        assert_eq!(
+            store.resolve(&path, &refs).unwrap(),
+            vec![("PGYER_API_KEY".to_string(), "first".to_string())]
+        );
+        store.rotate(&path, "pgyer", "second").unwrap();
+        assert_eq!(store.resolve(&path, &refs).unwrap()[0].1, "second");
+        assert_eq!(
+            store.rotate(&path, "missing", "x").unwrap_err().kind,
+            ErrorKind::SecretNotFound
+        );
+
+        store.lock();
+        assert_eq!(
+            store.resolve(&path, &refs).unwrap_err().kind,
+            ErrorKind::SecretStoreLocked
+        );
+        store.unlock(&path, PASSPHRASE).unwrap();
+
+        store.delete(&path, "pgyer").unwrap();
+        assert_eq!(store.resolve(&path, &refs).unwrap_err().kind, ErrorKind::SecretNotFound);
+        assert_eq!(
+            store.delete(&path, "pgyer").unwrap_err().kind,
+            ErrorKind::SecretNotFound
+        );
+    }
+
+    #[test]
+    fn a_value_moved_to_another_name_does_not_open() {
+        let dir = TestDir::with_files(&[]);
+        let (store, path) = unlocked(&dir);
+        store.add(&path, "a", "value of a").unwrap();
+        store.add(&path, "b", "value of b").unwrap();
+
+        let mut file = read_store(&path).unwrap();
+        let moved = std::mem::replace(
+            &mut file.secrets[1].value,
+            Sealed {
+                nonce: String::new(),
+                ciphertext: String::new(),
+            },
+        );
+        file.secrets[0].value = moved;
+        write_store(&path, &file).unwrap();
+
+        let error = store.resolve(&path, &[reference("A", "a")]).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Parse);
+        assert_eq!(error.message, "Secret 'a' could not be decrypted.");
+    }
+
+    #[test]
+    fn validate_refs_needs_a_variable_name_and_a_secret() {
+        assert!(validate_refs(&[reference("PGYER_API_KEY", "pgyer"), reference("_TOKEN2", "t")]).is_ok());
+        for (key, secret) in [("1KEY", "pgyer"), ("MY-KEY", "pgyer"), ("", "pgyer"), ("KEY", "")] {
+            let error = validate_refs(&[reference(key, secret)]).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::InvalidInput, "{} -> {}", key, secret);
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::ProjectConfig;
+use crate::error::{AppError, AppResult};
+
+#[derive(Clone, Copy)]
+enum Syntax {
+    /// Values land inside double-quoted Ruby strings.
+    Ruby,
+    Plain,
+}
+
+struct Template {
+    /// Output path relative to the project root.
+    path: &'static str,
+    source: &'static str,
+    syntax: Syntax,
+    executable: bool,
+}
+
+/// Every file `generate_fastlane_files` writes, embedded at build time so
+/// generation does not depend on anything installed on the machine.
+const TEMPLATES: &[Template] = &[
+    Template {
+        path: "fastlane/Fastfile",
+        source: include_str!("../templates/Fastfile"),
+        syntax: Syntax::Ruby,
+        executable: false,
+    },
+    Template {
+        path: "fastlane/Appfile",
+        source: include_str!("../templates/Appfile"),
+        syntax: Syntax::Ruby,
+        executable: false,
+    },
+    Template {
+        path: "fastlane/Pluginfile",
+        source: include_str!("../templates/Pluginfile"),
+        syntax: Syntax::Ruby,
+        executable: false,
+    },
+    Template {
+        path: "fastlane/.env.fastlane.example",
+        source: include_str!("../templates/env.fastlane.example"),
+        syntax: Syntax::Plain,
+        executable: false,
+    },
+    Template {
+        path: "fastlane/.env.fastlane.staging.example",
+        source: include_str!("../templates/env.fastlane.staging.example"),
+        syntax: Syntax::Plain,
+        executable: false,
+    },
+    Template {
+        path: "fastlane/.env.fastlane.prod.example",
+        source: include_str!("../templates/env.fastlane.prod.example"),
+        syntax: Syntax::Plain,
+        executable: false,
+    },
+    Template {
+        path: "Gemfile",
+        source: include_str!("../templates/Gemfile"),
+        syntax: Syntax::Ruby,
+        executable: false,
+    },
+    Template {
+        path: "scripts/doctor_fastlane_env.sh",
+        source: include_str!("../templates/doctor_fastlane_env.sh"),
+        syntax: Syntax::Plain,
+        executable: true,
+    },
+    Template {
+        path: "scripts/fastlane_run.sh",
+        source: include_str!("../templates/fastlane_run.sh"),
+        syntax: Syntax::Plain,
+        executable: true,
+    },
+];
+
+/// Placeholders the Fastfile assigns to top-level constants of the same name,
+/// which `update_fastfile_constants` keeps in sync after generation.
+const FASTFILE_CONSTANTS: &[&str] = &[
+    "WORKSPACE",
+    "XCODEPROJ",
+    "SCHEME_DEV",
+    "SCHEME_DIS",
+    "BUNDLE_ID_DEV",
+    "BUNDLE_ID_DIS",
+    "TEAM_ID",
+    "PROFILE_DEV",
+    "PROFILE_DIS",
+    "SIGNING_STYLE",
+];
+
+const PLUGINS_EVAL: &str = "eval_gemfile(plugins_path)";
+
+/// Files holding secrets or local state that must not be committed; the
+/// `.example` env files are meant to be.
+const GITIGNORE_ENTRIES: &[&str] = &[
+    ".fastlane-desktop/",
+    "fastlane/.env.fastlane",
+    "fastlane/.env.fastlane.*",
+    "!fastlane/.env.fastlane*.example",
+];
+
+pub struct TemplateInput<'a> {
+    pub config: &'a ProjectConfig,
+    pub project_name: &'a str,
+    pub workspace: &'a str,
+    pub xcodeproj: &'a str,
+    /// The project's Gemfile, which is extended instead of replaced.
+    pub existing_gemfile: Option<&'a str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedFile {
+    /// Relative to the project root.
+    pub path: PathBuf,
+    pub content: String,
+    pub executable: bool,
+}
+
+pub fn output_paths() -> impl Iterator<Item = &'static str> {
+    TEMPLATES.iter().map(|template| template.path)
+}
+
+/// The output depends only on `input`.
+pub fn render(input: &TemplateInput) -> Vec<RenderedFile> {
+    let values = placeholder_values(input);
+    TEMPLATES
+        .iter()
+        .map(|template| {
+            let content = match (template.path, input.existing_gemfile) {
+                ("Gemfile", Some(existing)) => gemfile_with_plugins(existing),
+                _ => fill(template.source, &values, template.syntax),
+            };
+            RenderedFile {
+                path: PathBuf::from(template.path),
+                content,
+                executable: template.executable,
+            }
+        })
+        .collect()
+}
+
+pub fn write(project_root: &Path, files: &[RenderedFile]) -> AppResult<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for file in files {
+        let path = project_root.join(&file.path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| AppError::io("Create directory", dir, e))?;
+        }
+        fs::write(&path, &file.content).map_err(|e| AppError::io("Write generated file", &path, e))?;
+        if file.executable {
+            make_executable(&path)?;
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+pub fn fastfile_constants(input: &TemplateInput) -> Vec<(&'static str, String)> {
+    placeholder_values(input)
+        .into_iter()
+        .filter(|(key, _)| FASTFILE_CONSTANTS.contains(key))
+        .collect()
+}
+
+pub fn gemfile_with_plugins(existing: &str) -> String {
+    let mut content = existing.to_string();
+    if content.contains(PLUGINS_EVAL) {
+        return content;
+    }
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str("\nplugins_path = File.join(File.dirname(__FILE__), \"fastlane\", \"Pluginfile\")\n");
+    content.push_str(&format!("{} if File.exist?(plugins_path)\n", PLUGINS_EVAL));
+    content
+}
+
+pub fn gitignore_with_entries(existing: Option<&str>) -> String {
+    let mut content = existing.unwrap_or_default().to_string();
+    let listed = content.lines().map(str::trim).collect::<Vec<_>>();
+    let missing = GITIGNORE_ENTRIES
+        .iter()
+        .filter(|entry| !listed.contains(entry))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return content;
+    }
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    content.push_str("# iOS Fastlane Desktop: secrets and local state\n");
+    for entry in missing {
+        content.push_str(entry);
+        content.push('\n');
+    }
+    content
+}
+
+/// Secrets render empty: the generated files are meant to be committed.
+fn placeholder_values(input: &TemplateInput) -> Vec<(&'static str, String)> {
+    let config = input.config;
+    let values = vec![
+        ("PROJECT_NAME", input.project_name.to_string()),
+        ("WORKSPACE", input.workspace.to_string()),
+        ("XCODEPROJ", input.xcodeproj.to_string()),
+        ("SCHEME_DEV", config.scheme_dev.trim().to_string()),
+        ("SCHEME_DIS", config.scheme_dis.trim().to_string()),
+        ("BUNDLE_ID_DEV", config.bundle_id_dev.trim().to_string()),
+        ("BUNDLE_ID_DIS", config.bundle_id_dis.trim().to_string()),
+        ("TEAM_ID", config.team_id.trim().to_string()),
+        ("PROFILE_DEV", config.profile_dev.trim().to_string()),
+        ("PROFILE_DIS", config.profile_dis.trim().to_string()),
+        ("SIGNING_STYLE", config.signing_style.trim().to_string()),
+        ("MATCH_GIT_URL", config.match_git_url.trim().to_string()),
+        ("MATCH_GIT_BRANCH", config.match_git_branch.trim().to_string()),
+        ("ENABLE_QUALITY_GATE", config.enable_quality_gate.to_string()),
+        ("ENABLE_TESTS", config.enable_tests.to_string()),
+        ("ENABLE_SWIFTLINT", config.enable_swiftlint.to_string()),
+        ("ENABLE_SLACK_NOTIFY", config.enable_slack_notify.to_string()),
+        ("ENABLE_WECHAT_NOTIFY", config.enable_wechat_notify.to_string()),
+        ("ENABLE_SNAPSHOT", config.enable_snapshot.to_string()),
+        ("SNAPSHOT_SCHEME", config.snapshot_scheme.trim().to_string()),
+        ("SNAPSHOT_DEVICES", config.snapshot_devices.trim().to_string()),
+        ("SNAPSHOT_LANGUAGES", config.snapshot_languages.trim().to_string()),
+        ("METADATA_PATH", config.metadata_path.trim().to_string()),
+        ("ENABLE_METADATA_UPLOAD", config.enable_metadata_upload.to_string()),
+        ("ENABLE_SCREENSHOT_UPLOAD", config.enable_screenshot_upload.to_string()),
+        ("GYM_SKIP_CLEAN", config.gym_skip_clean.to_string()),
+        ("DERIVED_DATA_PATH", config.derived_data_path.trim().to_string()),
+        ("CI_BUNDLE_INSTALL", config.ci_bundle_install.to_string()),
+        ("CI_COCOAPODS_DEPLOYMENT", config.ci_cocoapods_deployment.to_string()),
+    ];
+    values
+        .into_iter()
+        .map(|(key, value)| {
+            if config.is_secret(key) {
+                (key, String::new())
+            } else {
+                (key, value)
+            }
+        })
+        .collect()
+}
+
+/// Replaces each `{{KEY}}` in `source` with its escaped value, in one pass so
+/// a value that contains a placeholder is not filled again.
+fn fill(source: &str, values: &[(&'static str, String)], syntax: Syntax) -> String {
+    let mut filled = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("{{") {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find("}}").and_then(|end| {
+            values
+                .iter()
+                .find(|(key, _)| *key == &rest[2..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                match syntax {
+                    Syntax::Ruby => filled.push_str(&escape_ruby_string(value)),
+                    Syntax::Plain => filled.push_str(&value.replace(['\n', '\r'], " ")),
+                }
+                rest = &rest[end + 2..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+pub fn escape_ruby_string(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("#{", "\\#{")
+        .replace('\n', "\\n")
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> AppResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| AppError::io("Make script executable", path, e))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> AppResult<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fastfile_constants;
+    use crate::secret_store::SecretRef;
+
+    fn config() -> ProjectConfig {
+        ProjectConfig {
+            scheme_dev: " MyApp Dev ".to_string(),
+            scheme_dis: "MyApp \"Prod\" #{x}".to_string(),
+            bundle_id_dev: "com.example.app.dev".to_string(),
+            match_git_url: "git@example.com:certs.git\nsecond line".to_string(),
+            ..ProjectConfig::default()
+        }
+    }
+
+    fn input<'a>(config: &'a ProjectConfig, existing_gemfile: Option<&'a str>) -> TemplateInput<'a> {
+        TemplateInput {
+            config,
+            project_name: "MyApp",
+            workspace: "MyApp.xcworkspace",
+            xcodeproj: "MyApp.xcodeproj",
+            existing_gemfile,
+        }
+    }
+
+    fn rendered<'a>(files: &'a [RenderedFile], path: &str) -> &'a RenderedFile {
+        files.iter().find(|file| file.path == Path::new(path)).unwrap()
+    }
+
+    #[test]
+    fn fills_every_placeholder_in_every_template() {
+        let config = config();
+        let files = render(&input(&config, None));
+
+        assert_eq!(
+            files.iter().map(|file| file.path.to_str().unwrap()).collect::<Vec<_>>(),
+            output_paths().collect::<Vec<_>>()
+        );
+        for file in &files {
+            assert!(
+                !file.content.contains("{{"),
+                "{} has an unfilled placeholder",
+                file.path.display()
+            );
+        }
+        assert!(rendered(&files, "scripts/fastlane_run.sh").executable);
+        assert!(!rendered(&files, "fastlane/Fastfile").executable);
+    }
+
+    #[test]
+    fn escapes_values_for_ruby_strings_and_plain_files() {
+        let config = config();
+        let files = render(&input(&config, None));
+
+        let fastfile = &rendered(&files, "fastlane/Fastfile").content;
+        assert_eq!(
+            fastfile_constants::get(fastfile, "SCHEME_DEV").as_deref(),
+            Some("MyApp Dev")
+        );
+        assert_eq!(
+            fastfile_constants::get(fastfile, "SCHEME_DIS").as_deref(),
+            Some("MyApp \"Prod\" #{x}")
+        );
+        assert!(fastfile.contains(r#""MyApp \"Prod\" \#{x}""#));
+
+        let env_example = &rendered(&files, "fastlane/.env.fastlane.example").content;
+        assert!(env_example.contains("git@example.com:certs.git second line"));
+        assert_eq!(escape_ruby_string("a\\b\n"), "a\\\\b\\n");
+    }
+
+    #[test]
+    fn placeholders_inside_values_are_not_filled_again() {
+        let values = [
+            ("SCHEME_DEV", "{{TEAM_ID}} {{".to_string()),
+            ("TEAM_ID", "ABCDE12345".to_string()),
+        ];
+        let filled = fill(
+            "{{SCHEME_DEV}}/{{TEAM_ID}}/{{UNKNOWN}}/{{{TEAM_ID}}}",
+            &values,
+            Syntax::Plain,
+        );
+        assert_eq!(filled, "{{TEAM_ID}} {{/ABCDE12345/{{UNKNOWN}}/{ABCDE12345}");
+    }
+
+    #[test]
+    fn values_from_secret_refs_render_empty() {
+        let config = ProjectConfig {
+            secret_refs: vec![SecretRef {
+                key: "MATCH_GIT_URL".to_string(),
+                secret: "match-url".to_string(),
+            }],
+            ..config()
+        };
+        let files = render(&input(&config, None));
+
+        let env_example = &rendered(&files, "fastlane/.env.fastlane.example").content;
+        assert!(env_example.contains("\nMATCH_GIT_URL=\n"));
+        assert!(!env_example.contains("certs.git"));
+    }
+
+    #[test]
+    fn generated_constants_match_the_rendered_fastfile() {
+        let config = config();
+        let input = input(&config, None);
+        let files = render(&input);
+        let fastfile = &rendered(&files, "fastlane/Fastfile").content;
+
+        for (key, value) in fastfile_constants(&input) {
+            assert_eq!(fastfile_constants::get(fastfile, key), Some(value), "{}", key);
+        }
+    }
+
+    #[test]
+    fn gitignore_lists_env_files_and_app_state_once() {
+        let created = gitignore_with_entries(None);
+        assert!(created.contains("\n.fastlane-desktop/\nfastlane/.env.fastlane\n"));
+        assert_eq!(gitignore_with_entries(Some(&created)), created);
+
+        let existing = "Pods/\n.fastlane-desktop/";
+        let extended = gitignore_with_entries(Some(existing));
+        assert!(extended.starts_with("Pods/\n.fastlane-desktop/\n\n"));
+        assert_eq!(extended.matches(".fastlane-desktop/").count(), 1);
+        assert!(extended.ends_with("!fastlane/.env.fastlane*.example\n"));
+    }
+
+    #[test]
+    fn extends_an_existing_gemfile_once() {
+        let existing = "source \"https://rubygems.org\"\n\ngem \"fastlane\"\ngem \"cocoapods\"";
+        let config = config();
+        let files = render(&input(&config, Some(existing)));
+        let gemfile = &rendered(&files, "Gemfile").content;
+
+        assert!(gemfile.starts_with(existing));
+        assert!(gemfile.ends_with("eval_gemfile(plugins_path) if File.exist?(plugins_path)\n"));
+        assert_eq!(gemfile_with_plugins(gemfile), *gemfile);
+    }
+}
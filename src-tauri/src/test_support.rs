@@ -0,0 +1,39 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::Path;
+
+use tempfile::TempDir;
+
+/// A temporary directory that is removed when dropped.
+pub struct TestDir(TempDir);
+
+impl TestDir {
+    /// A new directory holding `files`, given as relative path and content.
+    pub fn with_files(files: &[(&str, &str)]) -> Self {
+        let dir = Self(tempfile::tempdir().unwrap());
+        for (file, content) in files {
+            dir.write(file, content);
+        }
+        dir
+    }
+
+    pub fn write(&self, file: &str, content: &str) {
+        let path = self.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.0.path()
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        self
+    }
+}
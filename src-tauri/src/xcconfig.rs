@@ -0,0 +1,464 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::pbxproj::{self, BuildConfiguration, NativeTarget, PbxProject};
+
+/// Where an assignment sits in Xcode's precedence order, lowest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingLayer {
+    Default,
+    ProjectXcconfig,
+    Project,
+    TargetXcconfig,
+    Target,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingSource {
+    pub layer: SettingLayer,
+    /// The `.xcconfig` file and line for xcconfig layers.
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    /// The condition the assignment was written with, e.g. `[sdk=iphoneos*]`.
+    pub condition: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedSetting {
+    pub key: String,
+    pub value: String,
+    pub source: SettingSource,
+    pub references: Vec<ResolvedSetting>,
+}
+
+#[derive(Debug, Clone)]
+struct Assignment {
+    key: String,
+    value: String,
+    source: SettingSource,
+}
+
+pub struct BuildContext<'a> {
+    pub configuration: &'a str,
+    pub sdk: &'a str,
+    pub arch: &'a str,
+}
+
+impl<'a> BuildContext<'a> {
+    /// A device build, which is what archive and identity resolution describe.
+    pub fn device(configuration: &'a str) -> Self {
+        Self {
+            configuration,
+            sdk: "iphoneos",
+            arch: "arm64",
+        }
+    }
+}
+
+/// Every assignment that applies to one target and configuration, ordered the
+/// way Xcode layers them: defaults, project xcconfig, project settings, target
+/// xcconfig, target settings. Later assignments win; `$(inherited)` refers to
+/// the value below.
+pub struct SettingStack {
+    assignments: Vec<Assignment>,
+}
+
+impl SettingStack {
+    pub fn for_target(project: &PbxProject, target: &NativeTarget, context: &BuildContext) -> Result<Self, String> {
+        let mut stack = Self { assignments: vec![] };
+        stack.push_default("TARGET_NAME", &target.name);
+        stack.push_default(
+            "PRODUCT_NAME",
+            target.product_name.as_deref().unwrap_or("$(TARGET_NAME)"),
+        );
+        stack.push_default("CONFIGURATION", context.configuration);
+        stack.push_default("PLATFORM_NAME", context.sdk);
+        stack.push_default("CURRENT_ARCH", context.arch);
+
+        if let Some(config) = project.configuration(context.configuration) {
+            stack.push_configuration(config, SettingLayer::ProjectXcconfig, SettingLayer::Project, context)?;
+        }
+        if let Some(config) = target.configuration(context.configuration) {
+            stack.push_configuration(config, SettingLayer::TargetXcconfig, SettingLayer::Target, context)?;
+        }
+        Ok(stack)
+    }
+
+    /// Resolves `key`, expanding references against the whole stack. Returns
+    /// `None` when the key is unset or references something the project
+    /// files do not define (e.g. SDK-provided settings).
+    pub fn resolve(&self, key: &str) -> Option<ResolvedSetting> {
+        self.resolve_below(key, self.assignments.len(), 0)
+    }
+
+    fn resolve_below(&self, key: &str, limit: usize, depth: usize) -> Option<ResolvedSetting> {
+        if depth > 32 {
+            return None;
+        }
+        let index = self.assignments[..limit].iter().rposition(|a| a.key == key)?;
+        let assignment = &self.assignments[index];
+
+        let mut references = Vec::new();
+        let value = pbxproj::expand_references(&assignment.value, &mut |name| {
+            let resolved = if name == "inherited" {
+                match self.resolve_below(key, index, depth + 1) {
+                    Some(resolved) => resolved,
+                    None => return Some(String::new()),
+                }
+            } else {
+                self.resolve_below(name, self.assignments.len(), depth + 1)?
+            };
+            let value = resolved.value.clone();
+            references.push(resolved);
+            Some(value)
+        })?;
+
+        Some(ResolvedSetting {
+            key: key.to_string(),
+            value: value.trim().to_string(),
+            source: assignment.source.clone(),
+            references,
+        })
+    }
+
+    fn push_default(&mut self, key: &str, value: &str) {
+        self.assignments.push(Assignment {
+            key: key.to_string(),
+            value: value.to_string(),
+            source: SettingSource {
+                layer: SettingLayer::Default,
+                file: None,
+                line: None,
+                condition: None,
+            },
+        });
+    }
+
+    fn push_configuration(
+        &mut self,
+        config: &BuildConfiguration,
+        xcconfig_layer: SettingLayer,
+        layer: SettingLayer,
+        context: &BuildContext,
+    ) -> Result<(), String> {
+        if let Some(path) = &config.base_configuration {
+            let mut chain = Vec::new();
+            read_xcconfig(path, xcconfig_layer, context, &mut chain, &mut self.assignments)?;
+        }
+
+        // Build settings are an unordered dictionary; a matching conditional
+        // value overrides the plain one at the same level.
+        let mut settings = config
+            .build_settings
+            .iter()
+            .filter_map(|(raw_key, value)| {
+                let (key, condition) = parse_setting_key(raw_key)?;
+                if !condition_matches(&condition, context) {
+                    return None;
+                }
+                Some(Assignment {
+                    key,
+                    value: value.clone(),
+                    source: SettingSource {
+                        layer,
+                        file: None,
+                        line: None,
+                        condition: (!condition.is_empty()).then_some(condition),
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        settings.sort_by_key(|assignment| assignment.source.condition.is_some());
+        self.assignments.extend(settings);
+        Ok(())
+    }
+}
+
+pub fn resolve_target_setting(
+    project: &PbxProject,
+    target: &NativeTarget,
+    configuration: &str,
+    key: &str,
+) -> Result<Option<ResolvedSetting>, String> {
+    let stack = SettingStack::for_target(project, target, &BuildContext::device(configuration))?;
+    Ok(stack.resolve(key))
+}
+
+/// `#include?` tolerates a missing file; `#include` does not.
+fn read_xcconfig(
+    path: &Path,
+    layer: SettingLayer,
+    context: &BuildContext,
+    chain: &mut Vec<PathBuf>,
+    out: &mut Vec<Assignment>,
+) -> Result<(), String> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if chain.contains(&canonical) {
+        return Err(format!("xcconfig include cycle at {}", path.display()));
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Read {} failed: {}", path.display(), e))?;
+    chain.push(canonical);
+
+    for (index, raw_line) in content.lines().enumerate() {
+        let line = strip_comment(raw_line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("#include") {
+            let (optional, rest) = match rest.strip_prefix('?') {
+                Some(rest) => (true, rest),
+                None => (false, rest),
+            };
+            let include = rest.trim().trim_matches('"');
+            // `<DEVELOPER_DIR>/...` includes point into Xcode itself.
+            if include.is_empty() || include.starts_with('<') {
+                continue;
+            }
+            let include_path = path.parent().unwrap_or_else(|| Path::new("")).join(include);
+            if !include_path.exists() {
+                if optional {
+                    continue;
+                }
+                return Err(format!(
+                    "{}:{}: included file not found: {}",
+                    path.display(),
+                    index + 1,
+                    include
+                ));
+            }
+            read_xcconfig(&include_path, layer, context, chain, out)?;
+            continue;
+        }
+
+        let Some((lhs, value)) = split_assignment(line) else {
+            continue;
+        };
+        let Some((key, condition)) = parse_setting_key(lhs.trim()) else {
+            continue;
+        };
+        if !condition_matches(&condition, context) {
+            continue;
+        }
+        out.push(Assignment {
+            key,
+            value: value.trim().trim_end_matches(';').trim_end().to_string(),
+            source: SettingSource {
+                layer,
+                file: Some(path.display().to_string()),
+                line: Some(index + 1),
+                condition: (!condition.is_empty()).then_some(condition),
+            },
+        });
+    }
+
+    chain.pop();
+    Ok(())
+}
+
+fn split_assignment(line: &str) -> Option<(&str, &str)> {
+    let mut depth = 0usize;
+    for (idx, c) in line.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            '=' if depth == 0 => return Some((&line[..idx], &line[idx + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Xcode treats `//` as a comment anywhere on an xcconfig line.
+fn strip_comment(line: &str) -> &str {
+    match line.find("//") {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+}
+
+fn parse_setting_key(raw: &str) -> Option<(String, String)> {
+    let (key, condition) = match raw.find('[') {
+        Some(idx) => (&raw[..idx], &raw[idx..]),
+        None => (raw, ""),
+    };
+    let key = key.trim();
+    let valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid || (!condition.is_empty() && !condition.ends_with(']')) {
+        return None;
+    }
+    Some((key.to_string(), condition.to_string()))
+}
+
+/// Conditions this resolver cannot evaluate (e.g. `variant`, `dialect`) never match.
+fn condition_matches(condition: &str, context: &BuildContext) -> bool {
+    condition
+        .split(['[', ']', ','])
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .all(|part| {
+            let Some((name, pattern)) = part.split_once('=') else {
+                return false;
+            };
+            let actual = match name.trim() {
+                "sdk" => context.sdk,
+                "config" => context.configuration,
+                "arch" => context.arch,
+                _ => return false,
+            };
+            glob_matches(pattern.trim(), actual)
+        })
+}
+
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == value,
+        Some((prefix, rest)) => {
+            let Some(remaining) = value.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=remaining.len())
+                .filter(|&idx| remaining.is_char_boundary(idx))
+                .any(|idx| glob_matches(rest, &remaining[idx..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+    use std::collections::BTreeMap;
+
+    fn configuration(base: Option<PathBuf>, settings: &[(&str, &str)]) -> BuildConfiguration {
+        BuildConfiguration {
+            name: "Release".to_string(),
+            build_settings: settings
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+            base_configuration: base,
+        }
+    }
+
+    fn project(target_config: BuildConfiguration, project_config: BuildConfiguration) -> PbxProject {
+        PbxProject {
+            targets: vec![NativeTarget {
+                id: "T1".to_string(),
+                name: "MyApp".to_string(),
+                product_name: Some("My App".to_string()),
+                product_type: Some(pbxproj::APPLICATION_PRODUCT_TYPE.to_string()),
+                build_configurations: vec![target_config],
+                default_configuration_name: Some("Release".to_string()),
+            }],
+            build_configurations: vec![project_config],
+            default_configuration_name: Some("Release".to_string()),
+        }
+    }
+
+    fn resolve(project: &PbxProject, key: &str) -> Option<ResolvedSetting> {
+        resolve_target_setting(project, &project.targets[0], "Release", key).unwrap()
+    }
+
+    #[test]
+    fn layers_xcconfigs_includes_and_conditions() {
+        let dir = TestDir::with_files(&[
+                (
+                    "Shared.xcconfig",
+                    "// Shared settings\nBUNDLE_PREFIX = com.example\nDEVELOPMENT_TEAM = SHARED1234\n",
+                ),
+                (
+                    "App.xcconfig",
+                    "#include? \"Local.xcconfig\"\n#include \"Shared.xcconfig\"\n#include \"<DEVELOPER_DIR>/Makefiles/CoreOccam.xcconfig\"\n\
+                     PRODUCT_BUNDLE_IDENTIFIER = $(BUNDLE_PREFIX).$(PRODUCT_NAME:rfc1034identifier) // trailing comment\n\
+                     PRODUCT_BUNDLE_IDENTIFIER[sdk=iphonesimulator*] = $(BUNDLE_PREFIX).simulator\n\
+                     DEVELOPMENT_TEAM[config=Release][sdk=iphoneos*] = RELEASE123;\n\
+                     OTHER_SWIFT_FLAGS = $(inherited) -DAPP\n\
+                     OTHER_SWIFT_FLAGS[variant=debug] = -DNEVER\n",
+                ),
+            ],
+        );
+        let project = project(
+            configuration(
+                Some(dir.join("App.xcconfig")),
+                &[("OTHER_SWIFT_FLAGS[arch=arm64]", "$(inherited) -DARM")],
+            ),
+            configuration(None, &[("OTHER_SWIFT_FLAGS", "-DPROJECT"), ("SWIFT_VERSION", "5.0")]),
+        );
+
+        let bundle_id = resolve(&project, "PRODUCT_BUNDLE_IDENTIFIER").unwrap();
+        assert_eq!(bundle_id.value, "com.example.My-App");
+        assert_eq!(bundle_id.source.layer, SettingLayer::TargetXcconfig);
+        assert_eq!(bundle_id.source.line, Some(4));
+        assert_eq!(
+            bundle_id.references[0].source.file,
+            Some(dir.join("Shared.xcconfig").display().to_string())
+        );
+
+        let team = resolve(&project, "DEVELOPMENT_TEAM").unwrap();
+        assert_eq!(team.value, "RELEASE123");
+        assert_eq!(
+            team.source.condition.as_deref(),
+            Some("[config=Release][sdk=iphoneos*]")
+        );
+
+        let flags = resolve(&project, "OTHER_SWIFT_FLAGS").unwrap();
+        assert_eq!(flags.value, "-DPROJECT -DAPP -DARM");
+        assert_eq!(flags.source.layer, SettingLayer::Target);
+
+        assert_eq!(
+            resolve(&project, "SWIFT_VERSION").unwrap().source.layer,
+            SettingLayer::Project
+        );
+        assert!(resolve(&project, "SDKROOT").is_none());
+    }
+
+    #[test]
+    fn a_missing_required_include_is_an_error() {
+        let dir = TestDir::with_files(&[("App.xcconfig", "A = 1\n#include \"Missing.xcconfig\"\n")]);
+        let project = project(
+            configuration(Some(dir.join("App.xcconfig")), &[]),
+            configuration(None, &[]),
+        );
+        let error = resolve_target_setting(&project, &project.targets[0], "Release", "A").unwrap_err();
+        assert!(
+            error.ends_with("App.xcconfig:2: included file not found: Missing.xcconfig"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn include_cycles_are_reported() {
+        let dir = TestDir::with_files(&[
+            ("A.xcconfig", "#include \"B.xcconfig\"\n"),
+            ("B.xcconfig", "#include \"A.xcconfig\"\n"),
+        ]);
+        let project = project(
+            configuration(Some(dir.join("A.xcconfig")), &[]),
+            configuration(None, &[]),
+        );
+        let error = resolve_target_setting(&project, &project.targets[0], "Release", "A").unwrap_err();
+        assert!(error.starts_with("xcconfig include cycle"), "{}", error);
+    }
+
+    #[test]
+    fn conditions_match_globs_and_reject_unknown_names() {
+        let context = BuildContext::device("Release");
+        assert!(condition_matches("", &context));
+        assert!(condition_matches("[sdk=iphone*][arch=*]", &context));
+        assert!(condition_matches("[config=Release,sdk=iphoneos*]", &context));
+        assert!(!condition_matches("[sdk=iphonesimulator*]", &context));
+        assert!(!condition_matches("[variant=normal]", &context));
+        assert_eq!(
+            parse_setting_key("KEY[sdk=*]"),
+            Some(("KEY".to_string(), "[sdk=*]".to_string()))
+        );
+        assert_eq!(parse_setting_key("NOT A KEY"), None);
+        assert_eq!(split_assignment("KEY[sdk=*] = a=b"), Some(("KEY[sdk=*] ", " a=b")));
+    }
+}
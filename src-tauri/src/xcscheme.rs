@@ -0,0 +1,334 @@
+use roxmltree::{Document, Node};
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildableReference {
+    pub blueprint_identifier: String,
+    pub blueprint_name: String,
+    pub buildable_name: String,
+    pub referenced_container: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildActionEntry {
+    pub target: BuildableReference,
+    pub build_for_running: bool,
+    pub build_for_testing: bool,
+    pub build_for_archiving: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Testable {
+    pub target: BuildableReference,
+    pub skipped: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestPlanReference {
+    pub reference: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scheme {
+    pub name: String,
+    pub path: String,
+    pub shared: bool,
+    pub build_entries: Vec<BuildActionEntry>,
+    pub testables: Vec<Testable>,
+    pub test_plans: Vec<TestPlanReference>,
+    pub launch_configuration: Option<String>,
+    pub test_configuration: Option<String>,
+    pub archive_configuration: Option<String>,
+}
+
+impl Scheme {
+    /// True when `xcodebuild test` has something to run: at least one enabled
+    /// testable or a test plan attached to the TestAction.
+    pub fn has_test_action(&self) -> bool {
+        self.testables.iter().any(|t| !t.skipped) || !self.test_plans.is_empty()
+    }
+
+    /// The target the Archive action produces, falling back to the Run target.
+    pub fn archive_target(&self) -> Option<&BuildableReference> {
+        self.build_entries
+            .iter()
+            .find(|entry| entry.build_for_archiving)
+            .or_else(|| self.build_entries.iter().find(|entry| entry.build_for_running))
+            .map(|entry| &entry.target)
+    }
+}
+
+/// Finds every `.xcscheme` of a `.xcodeproj` or `.xcworkspace` container:
+/// shared schemes first, then per-user schemes not already shared.
+pub fn discover_schemes(container: &Path) -> Vec<Scheme> {
+    let mut scheme_dirs = vec![(container.join("xcshareddata").join("xcschemes"), true)];
+    if let Ok(entries) = fs::read_dir(container.join("xcuserdata")) {
+        let mut user_dirs = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path().join("xcschemes"))
+            .collect::<Vec<_>>();
+        user_dirs.sort();
+        scheme_dirs.extend(user_dirs.into_iter().map(|dir| (dir, false)));
+    }
+
+    let mut schemes: Vec<Scheme> = Vec::new();
+    for (dir, shared) in scheme_dirs {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(OsStr::to_str) == Some("xcscheme"))
+            .collect::<Vec<PathBuf>>();
+        paths.sort();
+        for path in paths {
+            let Ok(scheme) = load_scheme(&path, shared) else {
+                continue;
+            };
+            if !schemes.iter().any(|existing| existing.name == scheme.name) {
+                schemes.push(scheme);
+            }
+        }
+    }
+    schemes
+}
+
+pub fn find_scheme(containers: &[PathBuf], name: &str) -> Option<Scheme> {
+    containers
+        .iter()
+        .flat_map(|container| discover_schemes(container))
+        .find(|scheme| scheme.name == name)
+}
+
+pub fn load_scheme(path: &Path, shared: bool) -> Result<Scheme, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Read {} failed: {}", path.display(), e))?;
+    let name = path.file_stem().and_then(OsStr::to_str).unwrap_or_default().to_string();
+    parse_scheme(&name, &content).map(|mut scheme| {
+        scheme.path = path.display().to_string();
+        scheme.shared = shared;
+        scheme
+    })
+}
+
+pub fn parse_scheme(name: &str, content: &str) -> Result<Scheme, String> {
+    let doc = Document::parse(content).map_err(|e| format!("Parse scheme {} failed: {}", name, e))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("Scheme") {
+        return Err(format!("Parse scheme {} failed: root element is not <Scheme>", name));
+    }
+
+    let build_entries = child(root, "BuildAction")
+        .and_then(|action| child(action, "BuildActionEntries"))
+        .map(|entries| {
+            children(entries, "BuildActionEntry")
+                .filter_map(|entry| {
+                    Some(BuildActionEntry {
+                        target: buildable_reference(child(entry, "BuildableReference")?),
+                        build_for_running: yes(entry, "buildForRunning"),
+                        build_for_testing: yes(entry, "buildForTesting"),
+                        build_for_archiving: yes(entry, "buildForArchiving"),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let test_action = child(root, "TestAction");
+    let testables = test_action
+        .and_then(|action| child(action, "Testables"))
+        .map(|list| {
+            children(list, "TestableReference")
+                .filter_map(|testable| {
+                    Some(Testable {
+                        target: buildable_reference(child(testable, "BuildableReference")?),
+                        skipped: yes(testable, "skipped"),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let test_plans = test_action
+        .and_then(|action| child(action, "TestPlans"))
+        .map(|list| {
+            children(list, "TestPlanReference")
+                .map(|plan| TestPlanReference {
+                    reference: plan.attribute("reference").unwrap_or_default().to_string(),
+                    is_default: yes(plan, "default"),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(Scheme {
+        name: name.to_string(),
+        path: String::new(),
+        shared: false,
+        build_entries,
+        testables,
+        test_plans,
+        launch_configuration: action_configuration(root, "LaunchAction"),
+        test_configuration: action_configuration(root, "TestAction"),
+        archive_configuration: action_configuration(root, "ArchiveAction"),
+    })
+}
+
+fn action_configuration(root: Node, action: &str) -> Option<String> {
+    child(root, action)
+        .and_then(|node| node.attribute("buildConfiguration"))
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn buildable_reference(node: Node) -> BuildableReference {
+    let attr = |name: &str| node.attribute(name).unwrap_or_default().to_string();
+    BuildableReference {
+        blueprint_identifier: attr("BlueprintIdentifier"),
+        blueprint_name: attr("BlueprintName"),
+        buildable_name: attr("BuildableName"),
+        referenced_container: attr("ReferencedContainer"),
+    }
+}
+
+fn yes(node: Node, attribute: &str) -> bool {
+    node.attribute(attribute) == Some("YES")
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+fn children<'a, 'input: 'a>(node: Node<'a, 'input>, tag: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    const FIXTURE: &str = include_str!("../tests/fixtures/xcscheme/MyApp Dev.xcscheme");
+
+    const NO_TEST_ACTION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Scheme LastUpgradeVersion = "1540" version = "1.7">
+   <BuildAction parallelizeBuildables = "YES">
+      <BuildActionEntries>
+         <BuildActionEntry buildForRunning = "YES" buildForArchiving = "NO">
+            <BuildableReference
+               BlueprintIdentifier = "8D1A2B3C4D5E6F7081920004"
+               BuildableName = "Widget.appex"
+               BlueprintName = "Widget"
+               ReferencedContainer = "container:MyApp.xcodeproj">
+            </BuildableReference>
+         </BuildActionEntry>
+      </BuildActionEntries>
+   </BuildAction>
+   <LaunchAction buildConfiguration = "">
+   </LaunchAction>
+</Scheme>
+"#;
+
+    #[test]
+    fn build_entries_and_configurations_are_read() {
+        let scheme = parse_scheme("MyApp Dev", FIXTURE).unwrap();
+
+        let targets = scheme
+            .build_entries
+            .iter()
+            .map(|entry| {
+                let flags = (
+                    entry.build_for_running,
+                    entry.build_for_testing,
+                    entry.build_for_archiving,
+                );
+                (entry.target.blueprint_name.as_str(), flags)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            [("MyApp", (true, true, true)), ("MyAppTests", (false, true, false))]
+        );
+        let app = scheme.archive_target().unwrap();
+        assert_eq!(app.buildable_name, "MyApp.app");
+        assert_eq!(app.blueprint_identifier, "8D1A2B3C4D5E6F7081920001");
+        assert_eq!(app.referenced_container, "container:MyApp.xcodeproj");
+
+        assert_eq!(scheme.launch_configuration.as_deref(), Some("Debug-Dev"));
+        assert_eq!(scheme.test_configuration.as_deref(), Some("Debug"));
+        assert_eq!(scheme.archive_configuration.as_deref(), Some("Release-Dev"));
+    }
+
+    #[test]
+    fn testables_and_test_plans_are_read() {
+        let scheme = parse_scheme("MyApp Dev", FIXTURE).unwrap();
+
+        let testables = scheme
+            .testables
+            .iter()
+            .map(|testable| (testable.target.blueprint_name.as_str(), testable.skipped))
+            .collect::<Vec<_>>();
+        assert_eq!(testables, [("MyAppTests", false), ("MyAppUITests", true)]);
+        let plans = scheme
+            .test_plans
+            .iter()
+            .map(|plan| (plan.reference.as_str(), plan.is_default))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            plans,
+            [
+                ("container:MyApp.xctestplan", true),
+                ("container:Smoke.xctestplan", false)
+            ]
+        );
+        assert!(scheme.has_test_action());
+    }
+
+    #[test]
+    fn a_scheme_without_test_action_has_nothing_to_test() {
+        let scheme = parse_scheme("Widget", NO_TEST_ACTION).unwrap();
+
+        assert!(scheme.testables.is_empty() && scheme.test_plans.is_empty());
+        assert!(!scheme.has_test_action());
+        assert_eq!(scheme.test_configuration, None);
+        // An empty buildConfiguration counts as unset.
+        assert_eq!(scheme.launch_configuration, None);
+        assert_eq!(scheme.archive_target().unwrap().blueprint_name, "Widget");
+        assert!(parse_scheme("Broken", "<Workspace/>").is_err());
+    }
+
+    #[test]
+    fn shared_schemes_shadow_user_schemes_of_the_same_name() {
+        let project = TestDir::with_files(&[
+            ("MyApp.xcodeproj/xcshareddata/xcschemes/MyApp Dev.xcscheme", FIXTURE),
+            (
+                "MyApp.xcodeproj/xcuserdata/me.xcuserdatad/xcschemes/MyApp Dev.xcscheme",
+                NO_TEST_ACTION,
+            ),
+            (
+                "MyApp.xcodeproj/xcuserdata/me.xcuserdatad/xcschemes/Widget.xcscheme",
+                NO_TEST_ACTION,
+            ),
+            (
+                "MyApp.xcodeproj/xcuserdata/me.xcuserdatad/xcschemes/Broken.xcscheme",
+                "<Scheme",
+            ),
+        ]);
+
+        let schemes = discover_schemes(&project.join("MyApp.xcodeproj"));
+        let found = schemes
+            .iter()
+            .map(|scheme| (scheme.name.as_str(), scheme.shared))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [("MyApp Dev", true), ("Widget", false)]);
+        assert!(schemes[0].has_test_action());
+    }
+}
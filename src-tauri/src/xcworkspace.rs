@@ -0,0 +1,376 @@
+use roxmltree::{Document, Node};
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directory names whose containers are vendored or sample code rather than the app itself.
+const SECONDARY_DIR_NAMES: &[&str] = &[
+    "example",
+    "examples",
+    "sample",
+    "samples",
+    "demo",
+    "demos",
+    "test",
+    "tests",
+    "vendor",
+    "thirdparty",
+    "third_party",
+    "pods",
+    "carthage",
+];
+
+const SKIPPED_DIR_NAMES: &[&str] = &["Pods", "Carthage", "node_modules", "DerivedData", "build"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContainerKind {
+    Workspace,
+    Project,
+}
+
+/// Paths are relative to the root when the file lives inside it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerCandidate {
+    pub kind: ContainerKind,
+    pub path: String,
+    pub projects: Vec<String>,
+    pub schemes: Vec<String>,
+}
+
+impl ContainerCandidate {
+    pub fn workspace(&self) -> Option<&str> {
+        match self.kind {
+            ContainerKind::Workspace => Some(&self.path),
+            ContainerKind::Project => None,
+        }
+    }
+
+    /// The project that holds the app targets: the container itself, or the
+    /// first workspace member that is not the CocoaPods project.
+    pub fn primary_project(&self) -> Option<&str> {
+        self.projects
+            .iter()
+            .find(|project| !is_pods_project(project))
+            .or_else(|| self.projects.first())
+            .map(String::as_str)
+    }
+}
+
+pub fn workspace_projects(workspace: &Path) -> Result<Vec<PathBuf>, String> {
+    let data = workspace.join("contents.xcworkspacedata");
+    let content = fs::read_to_string(&data).map_err(|e| format!("Read {} failed: {}", data.display(), e))?;
+    parse_workspace_data(workspace, &content)
+}
+
+pub fn parse_workspace_data(workspace: &Path, content: &str) -> Result<Vec<PathBuf>, String> {
+    let doc = Document::parse(content).map_err(|e| format!("Parse {} failed: {}", workspace.display(), e))?;
+    let container_dir = workspace.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let mut projects = Vec::new();
+    collect_file_refs(
+        doc.root_element(),
+        workspace,
+        &container_dir,
+        &container_dir,
+        &mut projects,
+    );
+    Ok(projects)
+}
+
+fn collect_file_refs(
+    node: Node,
+    workspace: &Path,
+    container_dir: &Path,
+    group_dir: &Path,
+    projects: &mut Vec<PathBuf>,
+) {
+    for child in node.children().filter(Node::is_element) {
+        let location = child.attribute("location").unwrap_or_default();
+        let resolved = resolve_location(location, workspace, container_dir, group_dir);
+        if child.has_tag_name("Group") {
+            let dir = resolved.unwrap_or_else(|| group_dir.to_path_buf());
+            collect_file_refs(child, workspace, container_dir, &dir, projects);
+        } else if child.has_tag_name("FileRef") {
+            if let Some(path) = resolved {
+                if path.extension().and_then(OsStr::to_str) == Some("xcodeproj") && !projects.contains(&path) {
+                    projects.push(path);
+                }
+            }
+        }
+    }
+}
+
+fn resolve_location(location: &str, workspace: &Path, container_dir: &Path, group_dir: &Path) -> Option<PathBuf> {
+    let (kind, value) = location.split_once(':')?;
+    let path = match kind {
+        "group" => group_dir.join(value),
+        "container" => container_dir.join(value),
+        "absolute" => PathBuf::from(value),
+        // `self:` is used by the workspace embedded in a .xcodeproj bundle.
+        "self" => workspace.parent()?.to_path_buf(),
+        _ => return None,
+    };
+    Some(normalize_lexically(&path))
+}
+
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+pub fn is_pods_project(path: impl AsRef<Path>) -> bool {
+    path.as_ref().file_name().and_then(OsStr::to_str) == Some("Pods.xcodeproj")
+}
+
+/// Finds every workspace and project under `root` (depth 4) and ranks them so
+/// the app's own container comes first: workspaces over projects, workspaces
+/// that integrate CocoaPods first, shallow paths over nested ones, and
+/// sample/vendored or workspace-member projects last.
+pub fn find_containers(root: &Path) -> Vec<ContainerCandidate> {
+    let mut candidates = Vec::new();
+    let walker = WalkDir::new(root)
+        .max_depth(4)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_str().unwrap_or_default();
+            entry.depth() == 0 || !(name.starts_with('.') || SKIPPED_DIR_NAMES.contains(&name))
+        });
+    for entry in walker.filter_map(Result::ok) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let kind = match path.extension().and_then(OsStr::to_str) {
+            Some("xcworkspace") => ContainerKind::Workspace,
+            Some("xcodeproj") => ContainerKind::Project,
+            _ => continue,
+        };
+        if is_inside_bundle(root, path) {
+            continue;
+        }
+
+        let projects = match kind {
+            ContainerKind::Workspace => workspace_projects(path)
+                .unwrap_or_default()
+                .iter()
+                .map(|project| relative_to(root, project))
+                .collect(),
+            ContainerKind::Project => vec![relative_to(root, path)],
+        };
+        candidates.push(ContainerCandidate {
+            kind,
+            path: relative_to(root, path),
+            projects,
+            schemes: vec![],
+        });
+    }
+
+    let workspace_members = candidates
+        .iter()
+        .filter(|c| c.kind == ContainerKind::Workspace)
+        .flat_map(|c| c.projects.clone())
+        .collect::<Vec<_>>();
+    let mut scored = candidates
+        .into_iter()
+        .map(|candidate| {
+            let score = rank_score(&candidate, workspace_members.contains(&candidate.path));
+            (score, candidate)
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then_with(|| a.path.cmp(&b.path)));
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+fn rank_score(candidate: &ContainerCandidate, member_of_workspace: bool) -> i32 {
+    let path = Path::new(&candidate.path);
+    let depth = path.components().count().saturating_sub(1) as i32;
+    let mut score = -10 * depth;
+
+    match candidate.kind {
+        ContainerKind::Workspace => {
+            score += 100;
+            if candidate.projects.iter().any(is_pods_project) {
+                score += 50;
+            }
+        }
+        ContainerKind::Project => {
+            if member_of_workspace {
+                score -= 60;
+            }
+            if is_pods_project(&candidate.path) {
+                score -= 200;
+            }
+        }
+    }
+
+    let secondary = path
+        .parent()
+        .map(|parent| {
+            parent.components().any(|c| {
+                let name = c.as_os_str().to_string_lossy().to_lowercase();
+                SECONDARY_DIR_NAMES.contains(&name.as_str())
+            })
+        })
+        .unwrap_or(false);
+    if secondary {
+        score -= 80;
+    }
+    score
+}
+
+/// Ignores internal workspace metadata such as Foo.xcodeproj/project.xcworkspace.
+fn is_inside_bundle(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .ok()
+        .and_then(Path::parent)
+        .map(|parent| {
+            parent.components().any(|component| {
+                let name = component.as_os_str().to_string_lossy();
+                name.ends_with(".xcodeproj") || name.ends_with(".xcworkspace")
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn relative_to(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+
+    fn workspace_data(projects: &[&str]) -> String {
+        let refs = projects
+            .iter()
+            .map(|project| format!("   <FileRef\n      location = \"group:{}\">\n   </FileRef>\n", project))
+            .collect::<String>();
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Workspace\n   version = \"1.0\">\n{}</Workspace>\n",
+            refs
+        )
+    }
+
+    #[test]
+    fn the_root_workspace_with_pods_ranks_first() {
+        let app = workspace_data(&["MyApp.xcodeproj", "Pods/Pods.xcodeproj"]);
+        let example = workspace_data(&["Example.xcodeproj", "Pods/Pods.xcodeproj"]);
+        let project = TestDir::with_files(&[
+            ("MyApp.xcworkspace/contents.xcworkspacedata", &app),
+            ("MyApp.xcodeproj/project.pbxproj", ""),
+            (
+                "MyApp.xcodeproj/project.xcworkspace/contents.xcworkspacedata",
+                &workspace_data(&[]),
+            ),
+            ("Pods/Pods.xcodeproj/project.pbxproj", ""),
+            (
+                "Legacy.xcworkspace/contents.xcworkspacedata",
+                &workspace_data(&["MyApp.xcodeproj"]),
+            ),
+            ("Example/Example.xcworkspace/contents.xcworkspacedata", &example),
+            ("Example/Example.xcodeproj/project.pbxproj", ""),
+            ("Tools/Tools.xcodeproj/project.pbxproj", ""),
+        ]);
+
+        let containers = find_containers(&project);
+        let paths = containers.iter().map(|c| c.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "MyApp.xcworkspace",
+                "Legacy.xcworkspace",
+                "Example/Example.xcworkspace",
+                "Tools/Tools.xcodeproj",
+                "MyApp.xcodeproj",
+                "Example/Example.xcodeproj",
+            ]
+        );
+        assert_eq!(containers[0].projects, ["MyApp.xcodeproj", "Pods/Pods.xcodeproj"]);
+        assert_eq!(containers[0].workspace(), Some("MyApp.xcworkspace"));
+        assert_eq!(containers[0].primary_project(), Some("MyApp.xcodeproj"));
+        assert_eq!(containers[3].workspace(), None);
+    }
+
+    #[test]
+    fn pods_projects_rank_below_everything_else() {
+        let pods = ContainerCandidate {
+            kind: ContainerKind::Project,
+            path: "Pods.xcodeproj".to_string(),
+            projects: vec!["Pods.xcodeproj".to_string()],
+            schemes: vec![],
+        };
+        let sample = ContainerCandidate {
+            path: "Samples/Demo/Demo.xcodeproj".to_string(),
+            projects: vec!["Samples/Demo/Demo.xcodeproj".to_string()],
+            ..pods.clone()
+        };
+        assert!(rank_score(&pods, false) < rank_score(&sample, true));
+        assert_eq!(rank_score(&sample, false), -20 - 80);
+        assert_eq!(pods.primary_project(), Some("Pods.xcodeproj"));
+    }
+
+    #[test]
+    fn locations_resolve_against_their_groups() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Workspace version = "1.0">
+   <FileRef location = "group:App/MyApp.xcodeproj"></FileRef>
+   <FileRef location = "container:Pods/Pods.xcodeproj"></FileRef>
+   <FileRef location = "absolute:/Users/me/Shared/Kit.xcodeproj"></FileRef>
+   <FileRef location = "group:README.md"></FileRef>
+   <Group location = "group:Modules" name = "Modules">
+      <FileRef location = "group:Core/Core.xcodeproj"></FileRef>
+      <Group location = "group:../Features" name = "Features">
+         <FileRef location = "group:./Login.xcodeproj"></FileRef>
+      </Group>
+      <Group name = "Unlocated">
+         <FileRef location = "group:Net.xcodeproj"></FileRef>
+      </Group>
+      <FileRef location = "container:Tools/Tools.xcodeproj"></FileRef>
+   </Group>
+   <FileRef location = "group:App/MyApp.xcodeproj"></FileRef>
+</Workspace>
+"#;
+        let projects = parse_workspace_data(Path::new("/work/MyApp.xcworkspace"), content).unwrap();
+        assert_eq!(
+            projects,
+            [
+                "/work/App/MyApp.xcodeproj",
+                "/work/Pods/Pods.xcodeproj",
+                "/Users/me/Shared/Kit.xcodeproj",
+                "/work/Modules/Core/Core.xcodeproj",
+                "/work/Features/Login.xcodeproj",
+                "/work/Modules/Net.xcodeproj",
+                "/work/Tools/Tools.xcodeproj",
+            ]
+            .map(PathBuf::from)
+        );
+
+        let embedded = parse_workspace_data(
+            Path::new("/work/MyApp.xcodeproj/project.xcworkspace"),
+            r#"<Workspace version = "1.0"><FileRef location = "self:"></FileRef></Workspace>"#,
+        )
+        .unwrap();
+        assert_eq!(embedded, [PathBuf::from("/work/MyApp.xcodeproj")]);
+    }
+}
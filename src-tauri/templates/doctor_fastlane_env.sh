@@ -0,0 +1,38 @@
+#!/usr/bin/env bash
+# Checks the local toolchain for {{PROJECT_NAME}}'s fastlane lanes.
+# Written for the bash 3.2 that ships with macOS.
+set -u
+
+cd "$(dirname "$0")/.."
+
+failures=0
+
+check() {
+  local name="$1"
+  shift
+  if output="$("$@" 2>&1)"; then
+    printf '[ok]   %s: %s\n' "$name" "$(printf '%s' "$output" | head -n 1)"
+  else
+    printf '[fail] %s: %s\n' "$name" "$(printf '%s' "$output" | head -n 1)"
+    failures=$((failures + 1))
+  fi
+}
+
+check "Xcode" xcode-select -p
+check "xcodebuild" xcodebuild -version
+check "Ruby" ruby -v
+check "Bundler" bundle -v
+check "Gemfile" test -f Gemfile
+check "Gems" bundle check
+check "fastlane" bundle exec fastlane --version
+
+is_ci="$(printf '%s' "${CI:-false}" | tr '[:upper:]' '[:lower:]')"
+if [ "$is_ci" = "true" ] || [ "$is_ci" = "1" ]; then
+  check "Keychain" security list-keychains
+fi
+
+if [ "$failures" -gt 0 ]; then
+  printf '%s check(s) failed.\n' "$failures"
+  exit 1
+fi
+printf 'Environment ready.\n'
@@ -0,0 +1,23 @@
+#!/usr/bin/env bash
+# Usage: [FASTLANE_ENVIRONMENT=<name>] scripts/fastlane_run.sh <lane> [key:value ...]
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+if [ "$#" -lt 1 ]; then
+  printf 'Usage: %s <lane> [key:value ...]\n' "$0" >&2
+  exit 64
+fi
+
+lane="$1"
+shift
+
+export FASTLANE_SKIP_UPDATE_CHECK=1
+bundle check >/dev/null 2>&1 || bundle install
+envs=""
+[ -f fastlane/.env.fastlane ] && envs="fastlane"
+if [ -n "${FASTLANE_ENVIRONMENT:-}" ]; then
+  envs="${envs:+$envs,}fastlane.$FASTLANE_ENVIRONMENT"
+fi
+
+exec bundle exec fastlane ios "$lane" ${envs:+--env "$envs"} "$@"
@@ -1,38 +1,111 @@
-import { useMemo, useState } from "react";
+import { useEffect, useMemo, useState } from "react";
 import { defaultConfig } from "./lib/defaultConfig";
 import {
+  applyGeneration,
+  addSecret,
   bundleInstallAndValidate,
+  cancelLane,
+  deleteRuns,
+  deleteSecret,
+  diffBackup,
   doctorCheck,
+  effectiveLaneEnv,
+  formatError,
   generateFastlaneFiles,
+  getAppSettings,
+  getRun,
+  isAppError,
+  listBackups,
+  listLanes,
+  listProvisioningProfiles,
+  listRuns,
+  listSecrets,
   loadProfile,
+  lockSecretStore,
+  newRunId,
+  onLaneOutput,
+  previewGeneration,
+  readFastfileConstants,
+  refreshLoginEnv,
   resolveIdentity,
+  restoreBackup,
+  rotateSecret,
   runLane,
+  saveAppSettings,
   saveProfile,
   selectProjectPath,
-  scanProject
+  scanProject,
+  unlockSecretStore,
+  updateFastfileConstants
 } from "./lib/tauri";
-import type { DoctorReport, GenerateResult, ProjectConfig, ScanResult } from "./types";
-
-const laneButtons = [
-  "prepare",
-  "quality_gate",
-  "versioning",
-  "certificates",
-  "profiles",
-  "validate_config",
-  "dev",
-  "dis",
-  "staging",
-  "prod",
-  "release_testflight",
-  "release_appstore",
-  "snapshot_capture",
-  "metadata_sync",
-  "ci_setup",
-  "ci_build_dev",
-  "ci_build_dis",
-  "clean_builds"
-];
+import type {
+  AppSettings,
+  BackupSummary,
+  ContainerCandidate,
+  DoctorReport,
+  EnvConflict,
+  FastfileLanes,
+  GenerateResult,
+  GenerationPreview,
+  IdentitySource,
+  Lane,
+  LaneEnvironment,
+  LaneOptionValue,
+  LaneRunResult,
+  LoginEnvSummary,
+  ProjectConfig,
+  ProvisioningProfile,
+  ResolvedSetting,
+  RunSummary,
+  ScanResult,
+  SecretList,
+  SecretRef
+} from "./types";
+
+function describeSetting(setting: ResolvedSetting, indent = "  "): string[] {
+  const { layer, file, line, condition } = setting.source;
+  const where = file ? `${file}:${line ?? "?"}` : layer;
+  const lines = [`${indent}${setting.key}${condition ?? ""} = ${setting.value || "(empty)"}  <- ${where}`];
+  for (const reference of setting.references) {
+    lines.push(...describeSetting(reference, `${indent}  `));
+  }
+  return lines;
+}
+
+function describeIdentitySources(sources: IdentitySource[]): string {
+  return sources
+    .flatMap((source) => [
+      `[${source.scheme}] ${source.key} = ${source.value}`,
+      ...(source.setting ? describeSetting(source.setting) : ["  <- xcodebuild -showBuildSettings"])
+    ])
+    .join("\n");
+}
+
+/** Parses `key:value` pairs separated by spaces; values may be double-quoted. */
+function parseLaneOptions(text: string): Record<string, LaneOptionValue> {
+  const options: Record<string, LaneOptionValue> = {};
+  const token = /\s*([^\s:"]+):("(?:[^"\\]|\\.)*"|\S*)/y;
+  let index = 0;
+  while (text.slice(index).trim()) {
+    token.lastIndex = index;
+    const match = token.exec(text);
+    if (!match) {
+      throw new Error(`Expected key:value at "${text.slice(index).trim()}"`);
+    }
+    const [, key, raw] = match;
+    if (raw.startsWith('"')) {
+      options[key] = JSON.parse(raw) as string;
+    } else if (raw === "true" || raw === "false") {
+      options[key] = raw === "true";
+    } else if (/^-?\d+$/.test(raw)) {
+      options[key] = Number(raw);
+    } else {
+      options[key] = raw;
+    }
+    index = token.lastIndex;
+  }
+  return options;
+}
 
 function App() {
   const [config, setConfig] = useState<ProjectConfig>(defaultConfig);
@@ -45,7 +118,30 @@ function App() {
   const [doctorReport, setDoctorReport] = useState<DoctorReport | null>(null);
   const [skillTemplateReady, setSkillTemplateReady] = useState(false);
   const [generateResult, setGenerateResult] = useState<GenerateResult | null>(null);
+  const [generationPreview, setGenerationPreview] = useState<GenerationPreview | null>(null);
   const [copyLogMessage, setCopyLogMessage] = useState("");
+  const [activeRunId, setActiveRunId] = useState<string | null>(null);
+  const [runHistory, setRunHistory] = useState<RunSummary[]>([]);
+  const [backups, setBackups] = useState<BackupSummary[]>([]);
+  const [fastfileLanes, setFastfileLanes] = useState<FastfileLanes | null>(null);
+  const [laneOptions, setLaneOptions] = useState("");
+  const [laneEnvironment, setLaneEnvironment] = useState("");
+  const [provisioningProfiles, setProvisioningProfiles] = useState<ProvisioningProfile[] | null>(null);
+  const [appSettings, setAppSettings] = useState<AppSettings>({ loginShell: "", bootstrapScriptPath: "" });
+  const [loginEnv, setLoginEnv] = useState<LoginEnvSummary | null>(null);
+  const [secretList, setSecretList] = useState<SecretList | null>(null);
+  const [secretPassphrase, setSecretPassphrase] = useState("");
+  const [secretName, setSecretName] = useState("");
+  const [secretValue, setSecretValue] = useState("");
+
+  useEffect(() => {
+    getAppSettings()
+      .then(setAppSettings)
+      .catch((error) => setLog(`Load settings failed: ${formatError(error)}`));
+    listSecrets()
+      .then(setSecretList)
+      .catch((error) => setLog(`Load secrets failed: ${formatError(error)}`));
+  }, []);
 
   const generatedPreview = useMemo(() => {
     return [
@@ -79,7 +175,8 @@ function App() {
       `ciBundleInstall=${config.ciBundleInstall}`,
       `ciCocoapodsDeployment=${config.ciCocoapodsDeployment}`,
       `bootstrapMode=${config.bootstrapMode}`,
-      `bootstrapConfigPath=${config.bootstrapConfigPath || "<empty>"}`
+      `bootstrapConfigPath=${config.bootstrapConfigPath || "<empty>"}`,
+      `environments=${config.environments.map((environment) => environment.name).join(",") || "<none>"}`
     ].join("\n");
   }, [config]);
 
@@ -99,7 +196,7 @@ function App() {
     const reasons: string[] = [];
 
     if (generateResult.status !== "success") {
-      reasons.push("Bootstrap script returned failed status.");
+      reasons.push("Generation returned failed status.");
     }
     if (generateResult.mode === "dryRun") {
       reasons.push("Dry-run mode does not write output files.");
@@ -110,6 +207,9 @@ function App() {
     if (missingFiles.length > 0) {
       reasons.push(`Missing generated files: ${missingFiles.length}.`);
     }
+    if (generateResult.envConflicts.length > 0) {
+      reasons.push(`Hand-edited env keys kept: ${generateResult.envConflicts.length}.`);
+    }
 
     if (reasons.length === 0) {
       return {
@@ -141,6 +241,20 @@ function App() {
     setConfig((prev) => ({ ...prev, [key]: value }));
   }
 
+  function patchEnvironment(index: number, environment: LaneEnvironment) {
+    patch(
+      "environments",
+      config.environments.map((existing, i) => (i === index ? environment : existing))
+    );
+  }
+
+  function patchSecretRef(index: number, secretRef: SecretRef) {
+    patch(
+      "secretRefs",
+      config.secretRefs.map((existing, i) => (i === index ? secretRef : existing))
+    );
+  }
+
   function pickSuggestedSchemes(schemes: string[]) {
     if (schemes.length === 0) {
       return { dev: "", dis: "" };
@@ -189,6 +303,22 @@ function App() {
     return schemes[0] || "";
   }
 
+  function containerPaths(candidate?: ContainerCandidate) {
+    if (!candidate) return { workspace: "", xcodeproj: "" };
+    const appProject = candidate.projects.find((p) => !p.endsWith("Pods.xcodeproj")) ?? candidate.projects[0] ?? "";
+    return {
+      workspace: candidate.kind === "workspace" ? candidate.path : "",
+      xcodeproj: appProject
+    };
+  }
+
+  function onSelectContainer(path: string) {
+    const candidate = scanResult?.containers.find((c) => c.path === path);
+    const paths = containerPaths(candidate);
+    patch("workspace", paths.workspace);
+    patch("xcodeproj", paths.xcodeproj);
+  }
+
   async function onScan() {
     if (!config.projectPath.trim()) {
       setLog("Please input projectPath first.");
@@ -202,16 +332,18 @@ function App() {
       const suggestedMain = suggestMainScheme(result.schemes, result.projectName);
       setMainScheme(suggestedMain);
       setIdentityDiff([]);
-      patch("workspace", result.workspace ?? "");
-      patch("xcodeproj", result.xcodeproj ?? "");
+      const selected = containerPaths(result.containers[0]);
+      patch("workspace", selected.workspace);
+      patch("xcodeproj", selected.xcodeproj);
       patch("schemeDev", suggested.dev);
       patch("schemeDis", suggested.dis);
       patch("bundleIdDev", result.bundleIdDev ?? "");
       patch("bundleIdDis", result.bundleIdDis ?? "");
       patch("teamId", result.teamId ?? "");
       setLog(`Scan complete for ${result.projectName}.`);
+      void refreshLanes();
     } catch (error) {
-      setLog(`Scan failed: ${String(error)}`);
+      setLog(`Scan failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
     }
@@ -255,9 +387,9 @@ function App() {
         diff.push(`teamId: ${prevTeamId || "-"} -> ${identity.teamId || "-"}`);
       }
       setIdentityDiff(diff);
-      setLog("Identity applied from selected schemes.");
+      setLog(`Identity applied from selected schemes.\n${describeIdentitySources(identity.sources)}`);
     } catch (error) {
-      setLog(`Resolve identity failed: ${String(error)}`);
+      setLog(`Resolve identity failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
     }
@@ -287,7 +419,7 @@ function App() {
         setLog("Project path selection canceled.");
       }
     } catch (error) {
-      setLog(`Open folder failed: ${String(error)}`);
+      setLog(`Open folder failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
     }
@@ -303,17 +435,313 @@ function App() {
         result.mode !== "dryRun" &&
         result.files.every((f) => f.exists)
       );
+      void refreshLanes();
       setLog(
         `[${result.status}] generate (mode=${result.mode})\n` +
         `runtimeEnvWritten=${result.runtimeEnvWritten}\n` +
+        result.envConflicts.map((conflict) => `[env] ${envConflictText(conflict)}\n`).join("") +
+        (result.redactions ? `[redact] ${result.redactions} secret(s) masked in the output.\n` : "") +
         `${result.stdout}\n${result.stderr}`
       );
     } catch (error) {
       setGenerateResult(null);
       setSkillTemplateReady(false);
-      setLog(`Generate failed: ${String(error)}`);
+      setLog(`Generate failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+    }
+  }
+
+  async function onPreviewGeneration() {
+    setBusy(true);
+    try {
+      const preview = await previewGeneration(config);
+      setGenerationPreview(preview);
+      const count = (change: string) => preview.files.filter((f) => f.change === change).length;
+      setLog(
+        `[preview] ${preview.projectPath}\n` +
+        `new=${count("new")} changed=${count("changed")} unchanged=${count("unchanged")}\n` +
+        "Review the diffs in Preview, then Apply Changes to write them."
+      );
+    } catch (error) {
+      setGenerationPreview(null);
+      setLog(`Preview failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+    }
+  }
+
+  async function onApplyGeneration() {
+    if (!generationPreview) return;
+    setBusy(true);
+    try {
+      const result = await applyGeneration(generationPreview.previewId);
+      setGenerationPreview(null);
+      setGenerateResult(result);
+      setSkillTemplateReady(result.status === "success" && result.files.every((f) => f.exists));
+      setLog(`[${result.status}] apply preview\n${result.stdout || "Nothing to write, files are up to date."}`);
+      void refreshLanes();
+    } catch (error) {
+      if (isAppError(error) && (error.kind === "previewStale" || error.kind === "previewNotFound")) {
+        setGenerationPreview(null);
+      }
+      setLog(`Apply failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+    }
+  }
+
+  async function onUpdateFastfile() {
+    setBusy(true);
+    try {
+      const before = await readFastfileConstants(config.projectPath.trim());
+      const result = await updateFastfileConstants(config);
+      const after = await readFastfileConstants(config.projectPath.trim());
+      const valueOf = (constants: typeof before, name: string) =>
+        constants.find((constant) => constant.name === name)?.value ?? "";
+      const changes = result.updated.map(
+        (name) => `${name}: "${valueOf(before, name)}" -> "${valueOf(after, name)}"`
+      );
+      setLog(
+        `[fastfile] ${result.fastfilePath}\n` +
+        (changes.length ? changes.join("\n") : "Fastfile constants already match the config.") +
+        (result.missing.length ? `\nNot assigned with a literal value: ${result.missing.join(", ")}` : "") +
+        (result.backupId ? `\nPrevious Fastfile saved as backup ${result.backupId}.` : "")
+      );
+    } catch (error) {
+      setLog(`Update Fastfile failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+    }
+  }
+
+  async function runWithLiveLog(title: string, start: (runId: string) => Promise<LaneRunResult>) {
+    const runId = newRunId();
+    setLog(`[running] ${title}\n\n`);
+    const unlisten = await onLaneOutput((event) => {
+      if (event.runId !== runId) return;
+      setLog((prev) => `${prev}${event.line}\n`);
+    });
+    setActiveRunId(runId);
+    try {
+      const result = await start(runId);
+      setLog(
+        (prev) =>
+          `${prev}\n[${result.status}] ${title} (exit=${result.exitCode})` +
+          (result.redactions ? `\n[redact] ${result.redactions} secret(s) masked in the output.` : "")
+      );
+    } finally {
+      setActiveRunId(null);
+      unlisten();
+      void refreshRunHistory();
+    }
+  }
+
+  async function onLoadProvisioningProfiles() {
+    setBusy(true);
+    try {
+      const profiles = await listProvisioningProfiles();
+      setProvisioningProfiles(profiles);
+      const lines = profiles.map(
+        (profile) =>
+          `${profile.expired ? "[expired] " : ""}${profile.name} (${profile.profileType}) ` +
+          `team=${profile.teamIdentifier ?? "-"} bundleId=${profile.bundleId ?? "-"} ` +
+          `expires=${profile.expirationDate ?? "-"} devices=${profile.provisionedDevices.length}`
+      );
+      setLog(`Found ${profiles.length} provisioning profiles.\n${lines.join("\n")}`);
+    } catch (error) {
+      setLog(`Load provisioning profiles failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+    }
+  }
+
+  function profileWarning(name: string): string {
+    if (!provisioningProfiles || !name.trim()) return "";
+    const matches = provisioningProfiles.filter((profile) => profile.name === name.trim() || profile.uuid === name.trim());
+    if (!matches.length) return "Not installed on this Mac.";
+    if (matches.every((profile) => profile.expired)) return "Expired.";
+    return "";
+  }
+
+  async function refreshRunHistory() {
+    if (!config.projectPath.trim()) return;
+    try {
+      setRunHistory(await listRuns(config.projectPath.trim()));
+    } catch (error) {
+      setLog((prev) => `${prev}\n[history] Load run history failed: ${formatError(error)}\n`);
+    }
+  }
+
+  async function onOpenRun(run: RunSummary) {
+    try {
+      const record = await getRun(config.projectPath.trim(), run.runId);
+      setLog(
+        `[${record.status}] ${record.lane} (exit=${record.exitCode}) ` +
+        `${new Date(record.startedAtMs).toLocaleString()} ` +
+        `duration=${Math.round(record.durationMs / 1000)}s ` +
+        `git=${record.gitHead?.slice(0, 10) ?? "-"} ` +
+        `redactions=${record.redactions}\n\n${record.output}`
+      );
+    } catch (error) {
+      setLog(`Open run failed: ${formatError(error)}`);
+    }
+  }
+
+  async function onClearRunHistory() {
+    if (!config.projectPath.trim()) return;
+    try {
+      const deleted = await deleteRuns(config.projectPath.trim(), []);
+      setRunHistory([]);
+      setLog(`Deleted ${deleted} run(s) from history.`);
+    } catch (error) {
+      setLog(`Delete run history failed: ${formatError(error)}`);
+    }
+  }
+
+  async function refreshLanes() {
+    if (!config.projectPath.trim()) return;
+    try {
+      setFastfileLanes(await listLanes(config.projectPath.trim()));
+    } catch (error) {
+      setFastfileLanes(null);
+      setLog((prev) => `${prev}\n[lanes] Read Fastfile lanes failed: ${formatError(error)}\n`);
+    }
+  }
+
+  function envConflictText(conflict: EnvConflict): string {
+    return `${conflict.path}: ${conflict.key} was edited by hand and kept ("${conflict.fileValue}", app value "${conflict.appValue}")`;
+  }
+
+  function laneTitle(lane: Lane): string {
+    return [
+      lane.description ?? "",
+      lane.options.length ? `options: ${lane.options.join(", ")}` : "",
+      lane.takesOptions ? "" : "takes no options",
+      `${lane.file}:${lane.line}`
+    ]
+      .filter(Boolean)
+      .join("\n");
+  }
+
+  async function refreshBackups() {
+    if (!config.projectPath.trim()) return;
+    try {
+      setBackups(await listBackups(config.projectPath.trim()));
+    } catch (error) {
+      setLog(`Load backups failed: ${formatError(error)}`);
+    }
+  }
+
+  async function onDiffBackup(backup: BackupSummary) {
+    try {
+      const files = await diffBackup(config.projectPath.trim(), backup.backupId, config);
+      const changes = files.filter((file) => file.change !== "unchanged");
+      setLog(
+        `[backup] ${backup.backupId} (${backup.reason}, ${new Date(backup.createdAtMs).toLocaleString()})\n` +
+        `Restoring would change ${changes.length} of ${files.length} file(s).\n\n` +
+        (changes.map((file) => file.diff).join("\n") || "Current files match this backup.")
+      );
+    } catch (error) {
+      setLog(`Diff backup failed: ${formatError(error)}`);
+    }
+  }
+
+  async function onRestoreBackup(backup: BackupSummary) {
+    setBusy(true);
+    try {
+      const undo = await restoreBackup(config.projectPath.trim(), backup.backupId);
+      setLog(
+        `[restore] ${backup.backupId}: ${backup.files.map((file) => file.path).join(", ")}\n` +
+        `Previous files saved as backup ${undo.backupId}.`
+      );
+      setGenerationPreview(null);
+      void refreshLanes();
+    } catch (error) {
+      setLog(`Restore backup failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+      void refreshBackups();
+    }
+  }
+
+  async function refreshSecrets() {
+    try {
+      setSecretList(await listSecrets());
+    } catch (error) {
+      setLog(`Load secrets failed: ${formatError(error)}`);
+    }
+  }
+
+  async function onUnlockSecrets() {
+    setBusy(true);
+    try {
+      const secrets = await unlockSecretStore(secretPassphrase);
+      setSecretPassphrase("");
+      setLog(`Secret store unlocked (${secrets.length} secret(s)).`);
+    } catch (error) {
+      setLog(`Unlock secret store failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
+      void refreshSecrets();
+    }
+  }
+
+  async function onLockSecrets() {
+    try {
+      await lockSecretStore();
+      setLog("Secret store locked.");
+    } catch (error) {
+      setLog(`Lock secret store failed: ${formatError(error)}`);
+    } finally {
+      void refreshSecrets();
+    }
+  }
+
+  async function onSaveSecret(rotate: boolean) {
+    if (!secretName.trim() || !secretValue) {
+      setLog("Secret name and value are required.");
+      return;
+    }
+    setBusy(true);
+    try {
+      const saved = rotate ? await rotateSecret(secretName, secretValue) : await addSecret(secretName, secretValue);
+      setSecretValue("");
+      setLog(`Secret ${saved.name} ${rotate ? "rotated" : "added"}. Generate Files to update .env.fastlane.`);
+    } catch (error) {
+      setLog(`${rotate ? "Rotate" : "Add"} secret failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+      void refreshSecrets();
+    }
+  }
+
+  async function onDeleteSecret(name: string) {
+    setBusy(true);
+    try {
+      await deleteSecret(name);
+      const stillReferenced = config.secretRefs.filter((secretRef) => secretRef.secret === name);
+      setLog(
+        `Secret ${name} deleted.` +
+        (stillReferenced.length
+          ? ` Still referenced by ${stillReferenced.map((secretRef) => secretRef.key).join(", ")}.`
+          : "")
+      );
+    } catch (error) {
+      setLog(`Delete secret failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+      void refreshSecrets();
+    }
+  }
+
+  async function onCancelRun() {
+    if (!activeRunId) return;
+    try {
+      await cancelLane(activeRunId);
+      setLog((prev) => `${prev}\n[cancel] Cancellation requested.\n`);
+    } catch (error) {
+      setLog((prev) => `${prev}\n[cancel] Cancel failed: ${formatError(error)}\n`);
     }
   }
 
@@ -324,10 +752,11 @@ function App() {
     }
     setBusy(true);
     try {
-      const result = await bundleInstallAndValidate(config.projectPath.trim());
-      setLog(`[${result.status}] bundle install + validate_config (exit=${result.exitCode})\n\n${result.output}`);
+      await runWithLiveLog("bundle install + validate_config", (runId) =>
+        bundleInstallAndValidate(config.projectPath.trim(), runId, config)
+      );
     } catch (error) {
-      setLog(`bundle install + validate_config failed: ${String(error)}`);
+      setLog(`bundle install + validate_config failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
     }
@@ -343,7 +772,7 @@ function App() {
       const output = await saveProfile(config);
       setLog(output);
     } catch (error) {
-      setLog(`Save profile failed: ${String(error)}`);
+      setLog(`Save profile failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
     }
@@ -360,8 +789,13 @@ function App() {
       setConfig(loaded);
       setSkillTemplateReady(loaded.bootstrapMode !== "dryRun");
       setLog("Profile loaded.");
+      void refreshLanes();
     } catch (error) {
-      setLog(`Load profile failed: ${String(error)}`);
+      if (isAppError(error) && error.kind === "profileNotFound") {
+        setLog("No saved profile for this project yet. Fill in the form and click Save Profile.");
+        return;
+      }
+      setLog(`Load profile failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
     }
@@ -372,26 +806,99 @@ function App() {
       setLog("projectPath is required to run lane.");
       return;
     }
+    let options: Record<string, LaneOptionValue>;
+    try {
+      options = parseLaneOptions(laneOptions);
+    } catch (error) {
+      setLog(`Invalid lane options: ${error instanceof Error ? error.message : String(error)}`);
+      return;
+    }
     setBusy(true);
     try {
-      const result = await runLane(config.projectPath.trim(), lane);
-      setLog(`[${result.status}] ${lane} (exit=${result.exitCode})\n\n${result.output}`);
+      await runWithLiveLog(lane, (runId) =>
+        runLane(config.projectPath.trim(), lane, runId, config, options, laneEnvironment)
+      );
     } catch (error) {
-      setLog(`Lane run failed: ${String(error)}`);
+      if (isAppError(error) && error.kind === "invalidContainerConfig") {
+        // The generated Fastfile points at missing containers; require a new Generate.
+        setSkillTemplateReady(false);
+      }
+      setLog(`Lane run failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
     }
   }
 
+  async function onShowEffectiveEnv() {
+    if (!config.projectPath.trim()) {
+      setLog("projectPath is required to show the lane environment.");
+      return;
+    }
+    try {
+      const effective = await effectiveLaneEnv(config.projectPath.trim(), laneEnvironment, config);
+      setLog(
+        [
+          `[env] --env ${effective.envArg ?? "<none>"}`,
+          ...effective.files.map(
+            (file) => `[env] ${file.path} ${file.exists ? (file.overrides ? "overrides" : "loaded") : "missing"}`
+          ),
+          ...effective.values.map(
+            (value) =>
+              `${value.key}=${value.value}  (${value.source}${
+                value.overridden.length ? `, overrides ${value.overridden.join(", ")}` : ""
+              })`
+          )
+        ].join("\n")
+      );
+    } catch (error) {
+      setLog(`Show effective env failed: ${formatError(error)}`);
+    }
+  }
+
   async function onDoctorCheck() {
     setBusy(true);
     try {
-      const report = await doctorCheck(config.projectPath.trim() || undefined);
+      const report = await doctorCheck(
+        config.projectPath.trim() || undefined,
+        config.appStoreConnectApiKeyPath.trim() || undefined
+      );
       setDoctorReport(report);
       const passCount = report.checks.filter((c) => c.status === "pass").length;
       setLog(`Doctor completed: ${passCount}/${report.checks.length} checks passed.`);
     } catch (error) {
-      setLog(`Doctor failed: ${String(error)}`);
+      setLog(`Doctor failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+    }
+  }
+
+  async function onSaveAppSettings() {
+    setBusy(true);
+    try {
+      const saved = await saveAppSettings(appSettings);
+      setAppSettings(saved);
+      setLog(`Settings saved. Login shell: ${saved.loginShell || "$SHELL (default)"}`);
+    } catch (error) {
+      setLog(`Save settings failed: ${formatError(error)}`);
+    } finally {
+      setBusy(false);
+    }
+  }
+
+  async function onRefreshLoginEnv() {
+    setBusy(true);
+    try {
+      const summary = await refreshLoginEnv();
+      setLoginEnv(summary);
+      setLog(
+        [
+          `Login environment refreshed from ${summary.shell} (${summary.variableCount} variables).`,
+          ...(summary.captured ? [] : ["Shell could not be run; using the app's own environment."]),
+          ...Object.entries(summary.variables).map(([key, value]) => `${key}=${value}`)
+        ].join("\n")
+      );
+    } catch (error) {
+      setLog(`Refresh environment failed: ${formatError(error)}`);
     } finally {
       setBusy(false);
     }
@@ -402,7 +909,7 @@ function App() {
       await navigator.clipboard.writeText(log);
       setCopyLogMessage("Copied.");
     } catch (error) {
-      setCopyLogMessage(`Copy failed: ${String(error)}`);
+      setCopyLogMessage(`Copy failed: ${formatError(error)}`);
     }
   }
 
@@ -430,6 +937,8 @@ function App() {
           <div className="inline">
             <button disabled={busy} onClick={onScan}>Scan</button>
             <button disabled={busy} onClick={onGenerate}>Generate Files</button>
+            <button disabled={busy} onClick={onPreviewGeneration}>Preview Changes</button>
+            <button disabled={busy || !config.projectPath.trim()} onClick={onUpdateFastfile}>Update Fastfile</button>
             <button disabled={busy || !skillTemplateReady} onClick={onBundleInstallAndValidate}>Bundle Install + Validate</button>
             <button disabled={busy} onClick={onSaveProfile}>Save Profile</button>
             <button disabled={busy} onClick={onLoadProfile}>Load Profile</button>
@@ -454,6 +963,11 @@ function App() {
               <div>mode: {generateResult.mode}</div>
               <div>runtime env: {generateResult.runtimeEnvWritten ? "written" : "not written"}</div>
               {generateResult.runtimeEnvPath && <div>runtime env path: {generateResult.runtimeEnvPath}</div>}
+              {generateResult.envConflicts.map((conflict) => (
+                <div key={`${conflict.path}:${conflict.key}`} className="doctor-tip">
+                  {envConflictText(conflict)}
+                </div>
+              ))}
               <div className="file-status-list">
                 {generateResult.files.map((file) => {
                   const fileLevel = file.exists ? "success" : "error";
@@ -472,6 +986,22 @@ function App() {
           )}
 
           <h3>Build Identity</h3>
+          {scanResult && scanResult.containers.length > 1 && (
+            <label>
+              Detected Container
+              <select
+                value={scanResult.containers.find((c) => c.path === (config.workspace || config.xcodeproj))?.path ?? ""}
+                onChange={(e) => onSelectContainer(e.target.value)}
+              >
+                <option value="" disabled>Choose container</option>
+                {scanResult.containers.map((candidate) => (
+                  <option key={candidate.path} value={candidate.path}>
+                    {candidate.path} ({candidate.kind})
+                  </option>
+                ))}
+              </select>
+            </label>
+          )}
           <label>
             Workspace
             <input value={config.workspace} onChange={(e) => patch("workspace", e.target.value)} />
@@ -553,12 +1083,34 @@ function App() {
           </label>
           <label>
             Profile Dev
-            <input value={config.profileDev} onChange={(e) => patch("profileDev", e.target.value)} placeholder="myapp_dev" />
+            <input
+              value={config.profileDev}
+              onChange={(e) => patch("profileDev", e.target.value)}
+              placeholder="myapp_dev"
+              list="provisioning-profiles"
+            />
+            {profileWarning(config.profileDev) && <span className="doctor-tip">{profileWarning(config.profileDev)}</span>}
           </label>
           <label>
             Profile Dis
-            <input value={config.profileDis} onChange={(e) => patch("profileDis", e.target.value)} placeholder="myapp_dis" />
+            <input
+              value={config.profileDis}
+              onChange={(e) => patch("profileDis", e.target.value)}
+              placeholder="myapp_dis"
+              list="provisioning-profiles"
+            />
+            {profileWarning(config.profileDis) && <span className="doctor-tip">{profileWarning(config.profileDis)}</span>}
           </label>
+          <datalist id="provisioning-profiles">
+            {(provisioningProfiles ?? [])
+              .filter((profile) => !profile.expired)
+              .map((profile) => (
+                <option key={profile.uuid} value={profile.name}>
+                  {profile.profileType} {profile.bundleId ?? ""}
+                </option>
+              ))}
+          </datalist>
+          <button disabled={busy} onClick={onLoadProvisioningProfiles}>Load Provisioning Profiles</button>
           <label>
             Signing Style
             <select value={config.signingStyle} onChange={(e) => patch("signingStyle", e.target.value as ProjectConfig["signingStyle"])}>
@@ -612,8 +1164,8 @@ function App() {
             >
               <option value="standard">standard</option>
               <option value="dryRun">dry-run</option>
-              <option value="configFile">config file</option>
-              <option value="interactive">interactive</option>
+              <option value="configFile">config file (skill script)</option>
+              <option value="interactive">interactive (skill script)</option>
             </select>
           </label>
           {config.bootstrapMode === "configFile" && (
@@ -640,6 +1192,160 @@ function App() {
             <label><input type="checkbox" checked={config.ciBundleInstall} onChange={(e) => patch("ciBundleInstall", e.target.checked)} />CI bundle install</label>
             <label><input type="checkbox" checked={config.ciCocoapodsDeployment} onChange={(e) => patch("ciCocoapodsDeployment", e.target.checked)} />CI cocoapods deployment</label>
           </div>
+
+          <h2>Environments</h2>
+          {config.environments.map((environment, index) => (
+            <div key={index} className="environment">
+              <div className="inline">
+                <input
+                  value={environment.name}
+                  onChange={(e) => patchEnvironment(index, { ...environment, name: e.target.value })}
+                  placeholder="staging (default extends .env.fastlane)"
+                />
+                <button
+                  disabled={busy}
+                  onClick={() =>
+                    patchEnvironment(index, { ...environment, values: [...environment.values, { key: "", value: "" }] })
+                  }
+                >
+                  Add Variable
+                </button>
+                <button
+                  disabled={busy}
+                  onClick={() => patch("environments", config.environments.filter((_, i) => i !== index))}
+                >
+                  Remove
+                </button>
+              </div>
+              {environment.values.map((entry, valueIndex) => (
+                <div key={valueIndex} className="inline">
+                  <input
+                    value={entry.key}
+                    onChange={(e) =>
+                      patchEnvironment(index, {
+                        ...environment,
+                        values: environment.values.map((v, i) => (i === valueIndex ? { ...v, key: e.target.value } : v))
+                      })
+                    }
+                    placeholder="KEY"
+                  />
+                  <input
+                    value={entry.value}
+                    onChange={(e) =>
+                      patchEnvironment(index, {
+                        ...environment,
+                        values: environment.values.map((v, i) => (i === valueIndex ? { ...v, value: e.target.value } : v))
+                      })
+                    }
+                    placeholder="value"
+                  />
+                  <button
+                    disabled={busy}
+                    onClick={() =>
+                      patchEnvironment(index, {
+                        ...environment,
+                        values: environment.values.filter((_, i) => i !== valueIndex)
+                      })
+                    }
+                  >
+                    Remove
+                  </button>
+                </div>
+              ))}
+            </div>
+          ))}
+          <div className="inline">
+            <button
+              disabled={busy}
+              onClick={() => patch("environments", [...config.environments, { name: "", values: [] }])}
+            >
+              Add Environment
+            </button>
+          </div>
+
+          <h2>Secrets</h2>
+          <div className="inline">
+            <input
+              type="password"
+              value={secretPassphrase}
+              onChange={(e) => setSecretPassphrase(e.target.value)}
+              placeholder={secretList?.exists ? "Store passphrase" : "New store passphrase (8+ characters)"}
+            />
+            <button disabled={busy || !secretPassphrase} onClick={onUnlockSecrets}>
+              {secretList?.exists ? "Unlock" : "Create Store"}
+            </button>
+            <button disabled={busy || !secretList?.unlocked} onClick={onLockSecrets}>Lock</button>
+            {secretList && (
+              <span className={`status-badge status-${secretList.unlocked ? "success" : "error"}`}>
+                {secretList.unlocked ? "UNLOCKED" : "LOCKED"}
+              </span>
+            )}
+          </div>
+          {secretList && secretList.secrets.length > 0 && (
+            <div className="file-status-list">
+              {secretList.secrets.map((secret) => (
+                <div key={secret.name} className="file-status-row file-success" onClick={() => setSecretName(secret.name)}>
+                  <span>{secret.name}</span>
+                  <span>updated {new Date(secret.updatedAtMs).toLocaleString()}</span>
+                  <span className="inline">
+                    <button disabled={busy || !secretList.unlocked} onClick={() => onDeleteSecret(secret.name)}>Delete</button>
+                  </span>
+                </div>
+              ))}
+            </div>
+          )}
+          <div className="inline">
+            <input value={secretName} onChange={(e) => setSecretName(e.target.value)} placeholder="pgyer-api-key" />
+            <input
+              type="password"
+              value={secretValue}
+              onChange={(e) => setSecretValue(e.target.value)}
+              placeholder="value"
+            />
+            <button disabled={busy || !secretList?.unlocked} onClick={() => onSaveSecret(false)}>Add Secret</button>
+            <button disabled={busy || !secretList?.unlocked} onClick={() => onSaveSecret(true)}>Rotate Secret</button>
+          </div>
+          {config.secretRefs.map((secretRef, index) => (
+            <div key={index} className="inline">
+              <input
+                value={secretRef.key}
+                onChange={(e) => patchSecretRef(index, { ...secretRef, key: e.target.value })}
+                placeholder="PGYER_API_KEY"
+              />
+              <select
+                value={secretRef.secret}
+                onChange={(e) => patchSecretRef(index, { ...secretRef, secret: e.target.value })}
+              >
+                <option value="">Select secret</option>
+                {[
+                  ...new Set([...(secretList?.secrets.map((secret) => secret.name) ?? []), secretRef.secret])
+                ]
+                  .filter(Boolean)
+                  .map((name) => (
+                    <option key={name} value={name}>{name}</option>
+                  ))}
+              </select>
+              <button
+                disabled={busy}
+                onClick={() => patch("secretRefs", config.secretRefs.filter((_, i) => i !== index))}
+              >
+                Remove
+              </button>
+            </div>
+          ))}
+          <div className="inline">
+            <button
+              disabled={busy}
+              onClick={() => patch("secretRefs", [...config.secretRefs, { key: "", secret: "" }])}
+            >
+              Add Secret Reference
+            </button>
+          </div>
+          {config.secretRefs.some((secretRef) => ["PGYER_API_KEY", "MATCH_GIT_URL"].includes(secretRef.key)) && (
+            <span className="doctor-tip">
+              Referenced fields are left out of the saved profile; their values come from the secret store.
+            </span>
+          )}
         </section>
 
         <section className="panel">
@@ -647,6 +1353,31 @@ function App() {
           <div className="inline">
             <button disabled={busy} onClick={onDoctorCheck}>Run Doctor</button>
           </div>
+          <label>
+            Login Shell
+            <input
+              value={appSettings.loginShell}
+              onChange={(e) => setAppSettings({ ...appSettings, loginShell: e.target.value })}
+              placeholder="$SHELL (e.g. /bin/zsh, /bin/bash, /opt/homebrew/bin/fish)"
+            />
+          </label>
+          <label>
+            Bootstrap Script (config file / interactive modes)
+            <input
+              value={appSettings.bootstrapScriptPath}
+              onChange={(e) => setAppSettings({ ...appSettings, bootstrapScriptPath: e.target.value })}
+              placeholder="~/.codex/skills/ios-fastlane-skill/scripts/bootstrap_fastlane.sh"
+            />
+          </label>
+          <div className="inline">
+            <button disabled={busy} onClick={onSaveAppSettings}>Save Settings</button>
+            <button disabled={busy} onClick={onRefreshLoginEnv}>Refresh Environment</button>
+          </div>
+          {loginEnv && !loginEnv.captured && (
+            <div className="doctor-tip">
+              Could not capture the login environment from {loginEnv.shell}; tools run with the app's environment.
+            </div>
+          )}
           {doctorReport && (
             <div className="doctor-list">
               {doctorReport.checks.map((check) => (
@@ -663,7 +1394,40 @@ function App() {
           )}
 
           <h2>Preview</h2>
-          <pre>{generatedPreview}</pre>
+          {generationPreview ? (
+            <>
+              <div className="file-status-list">
+                {generationPreview.files.map((file) => (
+                  <div
+                    key={file.path}
+                    className={`file-status-row file-${file.change === "unchanged" ? "success" : "error"}`}
+                  >
+                    <span className={`status-badge status-${file.change === "unchanged" ? "success" : "warn"}`}>
+                      {file.change.toUpperCase()}
+                    </span>
+                    <span>{file.path}</span>
+                  </div>
+                ))}
+              </div>
+              {generationPreview.envConflicts.map((conflict) => (
+                <div key={`${conflict.path}:${conflict.key}`} className="doctor-tip">
+                  {envConflictText(conflict)}
+                </div>
+              ))}
+              <div className="inline">
+                <button disabled={busy} onClick={onApplyGeneration}>Apply Changes</button>
+                <button disabled={busy} onClick={() => setGenerationPreview(null)}>Discard Preview</button>
+              </div>
+              <pre>
+                {generationPreview.files
+                  .filter((file) => file.change !== "unchanged")
+                  .map((file) => file.diff)
+                  .join("\n") || "All files are up to date."}
+              </pre>
+            </>
+          ) : (
+            <pre>{generatedPreview}</pre>
+          )}
 
           <h2>Lane Runner</h2>
           {!skillTemplateReady && (
@@ -671,12 +1435,97 @@ function App() {
               Skill templates are not ready yet. Run Generate Files first, or use dry-run only for preview.
             </div>
           )}
+          <label>
+            Lane Options
+            <input
+              value={laneOptions}
+              onChange={(e) => setLaneOptions(e.target.value)}
+              placeholder='build_number:42 skip_upload:true note:"two words"'
+            />
+          </label>
+          <label>
+            Environment
+            <select value={laneEnvironment} onChange={(e) => setLaneEnvironment(e.target.value)}>
+              <option value="">.env.fastlane only</option>
+              {config.environments
+                .filter((environment) => environment.name && environment.name !== "default")
+                .map((environment) => (
+                  <option key={environment.name} value={environment.name}>
+                    {environment.name}
+                  </option>
+                ))}
+            </select>
+          </label>
           <div className="lane-grid">
-            {laneButtons.map((lane) => (
-              <button key={lane} disabled={busy} onClick={() => onRunLane(lane)}>{lane}</button>
-            ))}
+            {fastfileLanes?.lanes
+              .filter((lane) => !lane.private)
+              .map((lane) => (
+                <button
+                  key={`${lane.platform ?? ""}:${lane.name}`}
+                  disabled={busy}
+                  title={laneTitle(lane)}
+                  onClick={() => onRunLane(lane.name)}
+                >
+                  {lane.platform && lane.platform !== "ios" ? `${lane.platform} ${lane.name}` : lane.name}
+                </button>
+              ))}
+          </div>
+          {fastfileLanes && !fastfileLanes.lanes.some((lane) => !lane.private) && (
+            <div className="doctor-tip">No public lanes found in fastlane/Fastfile.</div>
+          )}
+          {fastfileLanes && fastfileLanes.unresolvedImports.length > 0 && (
+            <div className="doctor-tip">
+              Lanes from these imports are not listed: {fastfileLanes.unresolvedImports.join(", ")}
+            </div>
+          )}
+          <div className="inline">
+            <button disabled={busy} onClick={refreshLanes}>Refresh Lanes</button>
+            <button disabled={busy} onClick={onShowEffectiveEnv}>Show Effective Env</button>
+            <button disabled={!activeRunId} onClick={onCancelRun}>Cancel Running Lane</button>
           </div>
 
+          <h2>Run History</h2>
+          <div className="inline">
+            <button disabled={busy} onClick={refreshRunHistory}>Refresh History</button>
+            <button disabled={busy || runHistory.length === 0} onClick={onClearRunHistory}>Clear History</button>
+          </div>
+          {runHistory.length > 0 && (
+            <div className="file-status-list">
+              {runHistory.map((run) => (
+                <div
+                  key={run.runId}
+                  className={`file-status-row file-${run.status === "success" ? "success" : "error"}`}
+                  onClick={() => onOpenRun(run)}
+                >
+                  <span className={`status-badge status-${run.status === "success" ? "success" : "error"}`}>
+                    {run.status.toUpperCase()}
+                  </span>
+                  <span>{run.lane}</span>
+                  <span>{new Date(run.startedAtMs).toLocaleString()} ({Math.round(run.durationMs / 1000)}s)</span>
+                </div>
+              ))}
+            </div>
+          )}
+
+          <h2>Backups</h2>
+          <div className="inline">
+            <button disabled={busy} onClick={refreshBackups}>Refresh Backups</button>
+          </div>
+          {backups.length > 0 && (
+            <div className="file-status-list">
+              {backups.map((backup) => (
+                <div key={backup.backupId} className="file-status-row file-success">
+                  <span className="status-badge status-success">{backup.reason.toUpperCase()}</span>
+                  <span>{new Date(backup.createdAtMs).toLocaleString()} ({backup.files.length} files)</span>
+                  <span className="inline">
+                    <button disabled={busy} onClick={() => onDiffBackup(backup)}>Diff</button>
+                    <button disabled={busy} onClick={() => onRestoreBackup(backup)}>Restore</button>
+                  </span>
+                </div>
+              ))}
+            </div>
+          )}
+
           <h2>Execution Log</h2>
           <div className="inline">
             <button disabled={busy} onClick={onCopyExecutionLog}>Copy Execution Log</button>
@@ -688,9 +1537,24 @@ function App() {
             <div className="scan-card">
               <strong>Detected:</strong>
               <div>project: {scanResult.projectName}</div>
-              <div>workspace: {scanResult.workspace ?? "-"}</div>
-              <div>xcodeproj: {scanResult.xcodeproj ?? "-"}</div>
+              <div>containers:</div>
+              {scanResult.containers.map((candidate, index) => (
+                <div key={candidate.path}>
+                  {index + 1}. {candidate.path} ({candidate.kind})
+                  {candidate.kind === "workspace" ? ` projects=${candidate.projects.join(", ") || "-"}` : ""}
+                  {" "}schemes={candidate.schemes.join(", ") || "-"}
+                </div>
+              ))}
               <div>schemes: {scanResult.schemes.join(", ") || "-"}</div>
+              {scanResult.schemeDetails.map((scheme) => (
+                <div key={scheme.path}>
+                  - {scheme.name}{scheme.shared ? "" : " (user)"}: tests=
+                  {scheme.testables.some((t) => !t.skipped) || scheme.testPlans.length > 0 ? "yes" : "no"}
+                  {" "}launch={scheme.launchConfiguration ?? "-"}
+                  {" "}test={scheme.testConfiguration ?? "-"}
+                  {" "}archive={scheme.archiveConfiguration ?? "-"}
+                </div>
+              ))}
             </div>
           )}
         </section>
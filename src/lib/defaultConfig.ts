@@ -33,5 +33,7 @@ export const defaultConfig: ProjectConfig = {
   ciBundleInstall: true,
   ciCocoapodsDeployment: true,
   bootstrapMode: "standard",
-  bootstrapConfigPath: ""
+  bootstrapConfigPath: "",
+  environments: [],
+  secretRefs: []
 };
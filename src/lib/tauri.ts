@@ -1,14 +1,56 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
 import type {
+  AppError,
+  AppSettings,
+  AscToken,
+  BackupSummary,
   DoctorReport,
+  EffectiveEnv,
+  FastfileConstant,
+  FastfileConstantsUpdate,
+  FastfileLanes,
+  FilePreview,
   GenerateResult,
+  GenerationPreview,
   IdentityResult,
+  LaneFinishedEvent,
+  LaneOptionValue,
+  LaneOutputEvent,
   LaneRunResult,
+  LoginEnvSummary,
   ProjectConfig,
-  ScanResult
+  ProvisioningProfile,
+  RunRecord,
+  RunSummary,
+  ScanResult,
+  SecretInfo,
+  SecretList
 } from "../types";
 
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === "object" && error !== null && "kind" in error && "message" in error;
+}
+
+/** Renders a command error for the log, with its hint and path when present. */
+export function formatError(error: unknown): string {
+  if (!isAppError(error)) {
+    return String(error);
+  }
+  const lines = [`[${error.kind}] ${error.message}`];
+  if (error.path) {
+    lines.push(`path: ${error.path}`);
+  }
+  if (error.command) {
+    lines.push(`command: ${error.command}`);
+  }
+  if (error.hint) {
+    lines.push(`hint: ${error.hint}`);
+  }
+  return lines.join("\n");
+}
+
 export async function selectProjectPath(): Promise<string | null> {
   const selected = await open({
     directory: true,
@@ -22,8 +64,15 @@ export async function scanProject(projectPath: string): Promise<ScanResult> {
   return invoke("scan_project", { projectPath });
 }
 
-export async function doctorCheck(projectPath?: string): Promise<DoctorReport> {
-  return invoke("doctor_check", { projectPath: projectPath || null });
+export async function doctorCheck(projectPath?: string, appStoreConnectApiKeyPath?: string): Promise<DoctorReport> {
+  return invoke("doctor_check", {
+    projectPath: projectPath || null,
+    appStoreConnectApiKeyPath: appStoreConnectApiKeyPath || null
+  });
+}
+
+export async function appStoreConnectToken(projectPath: string, keyPath: string): Promise<AscToken> {
+  return invoke("app_store_connect_token", { projectPath, keyPath });
 }
 
 export async function resolveIdentity(
@@ -46,12 +95,123 @@ export async function generateFastlaneFiles(config: ProjectConfig): Promise<Gene
   return invoke("generate_fastlane_files", { config });
 }
 
-export async function runLane(projectPath: string, lane: string): Promise<LaneRunResult> {
-  return invoke("run_lane", { projectPath, lane });
+export async function previewGeneration(config: ProjectConfig): Promise<GenerationPreview> {
+  return invoke("preview_generation", { config });
+}
+
+export async function applyGeneration(previewId: string): Promise<GenerateResult> {
+  return invoke("apply_generation", { previewId });
+}
+
+export async function readFastfileConstants(projectPath: string): Promise<FastfileConstant[]> {
+  return invoke("read_fastfile_constants", { projectPath });
+}
+
+export async function updateFastfileConstants(config: ProjectConfig): Promise<FastfileConstantsUpdate> {
+  return invoke("update_fastfile_constants", { config });
+}
+
+export async function listLanes(projectPath: string): Promise<FastfileLanes> {
+  return invoke("list_lanes", { projectPath });
+}
+
+export async function runLane(
+  projectPath: string,
+  lane: string,
+  runId: string,
+  config: ProjectConfig,
+  options: Record<string, LaneOptionValue> = {},
+  environment = ""
+): Promise<LaneRunResult> {
+  return invoke("run_lane", { projectPath, lane, runId, config, options, environment });
+}
+
+export async function effectiveLaneEnv(
+  projectPath: string,
+  environment = "",
+  config?: ProjectConfig
+): Promise<EffectiveEnv> {
+  return invoke("effective_lane_env", { projectPath, environment, config });
+}
+
+export async function cancelLane(runId: string): Promise<void> {
+  return invoke("cancel_lane", { runId });
+}
+
+export async function bundleInstallAndValidate(
+  projectPath: string,
+  runId: string,
+  config: ProjectConfig
+): Promise<LaneRunResult> {
+  return invoke("bundle_install_and_validate", { projectPath, runId, config });
+}
+
+export async function listRuns(projectPath: string): Promise<RunSummary[]> {
+  return invoke("list_runs", { projectPath });
+}
+
+export async function getRun(projectPath: string, runId: string): Promise<RunRecord> {
+  return invoke("get_run", { projectPath, runId });
+}
+
+export async function deleteRuns(projectPath: string, runIds: string[]): Promise<number> {
+  return invoke("delete_runs", { projectPath, runIds });
 }
 
-export async function bundleInstallAndValidate(projectPath: string): Promise<LaneRunResult> {
-  return invoke("bundle_install_and_validate", { projectPath });
+export async function listBackups(projectPath: string): Promise<BackupSummary[]> {
+  return invoke("list_backups", { projectPath });
+}
+
+export async function diffBackup(
+  projectPath: string,
+  backupId: string,
+  config: ProjectConfig
+): Promise<FilePreview[]> {
+  return invoke("diff_backup", { projectPath, backupId, config });
+}
+
+export async function restoreBackup(projectPath: string, backupId: string): Promise<BackupSummary> {
+  return invoke("restore_backup", { projectPath, backupId });
+}
+
+export async function unlockSecretStore(passphrase: string): Promise<SecretInfo[]> {
+  return invoke("unlock_secret_store", { passphrase });
+}
+
+export async function lockSecretStore(): Promise<void> {
+  return invoke("lock_secret_store");
+}
+
+export async function listSecrets(): Promise<SecretList> {
+  return invoke("list_secrets");
+}
+
+export async function addSecret(name: string, value: string): Promise<SecretInfo> {
+  return invoke("add_secret", { name, value });
+}
+
+export async function rotateSecret(name: string, value: string): Promise<SecretInfo> {
+  return invoke("rotate_secret", { name, value });
+}
+
+export async function deleteSecret(name: string): Promise<void> {
+  return invoke("delete_secret", { name });
+}
+
+export async function listProvisioningProfiles(dir?: string): Promise<ProvisioningProfile[]> {
+  return invoke("list_provisioning_profiles", { dir: dir ?? null });
+}
+
+export function newRunId(): string {
+  return crypto.randomUUID();
+}
+
+export async function onLaneOutput(handler: (event: LaneOutputEvent) => void): Promise<UnlistenFn> {
+  return listen<LaneOutputEvent>("lane-output", (event) => handler(event.payload));
+}
+
+export async function onLaneFinished(handler: (event: LaneFinishedEvent) => void): Promise<UnlistenFn> {
+  return listen<LaneFinishedEvent>("lane-finished", (event) => handler(event.payload));
 }
 
 export async function saveProfile(config: ProjectConfig): Promise<string> {
@@ -61,3 +221,15 @@ export async function saveProfile(config: ProjectConfig): Promise<string> {
 export async function loadProfile(projectPath: string): Promise<ProjectConfig> {
   return invoke("load_profile", { projectPath });
 }
+
+export async function getAppSettings(): Promise<AppSettings> {
+  return invoke("get_app_settings");
+}
+
+export async function saveAppSettings(settings: AppSettings): Promise<AppSettings> {
+  return invoke("save_app_settings", { settings });
+}
+
+export async function refreshLoginEnv(): Promise<LoginEnvSummary> {
+  return invoke("refresh_login_env");
+}
@@ -35,23 +35,119 @@ export interface ProjectConfig {
   ciCocoapodsDeployment: boolean;
   bootstrapMode: BootstrapMode;
   bootstrapConfigPath: string;
+  environments: LaneEnvironment[];
+  secretRefs: SecretRef[];
+}
+
+export interface EnvValue {
+  key: string;
+  value: string;
+}
+
+export interface LaneEnvironment {
+  name: string;
+  values: EnvValue[];
+}
+
+/** A variable whose value comes from the encrypted secret store. */
+export interface SecretRef {
+  key: string;
+  secret: string;
+}
+
+export interface SecretInfo {
+  name: string;
+  createdAtMs: number;
+  updatedAtMs: number;
+}
+
+export interface SecretList {
+  exists: boolean;
+  unlocked: boolean;
+  secrets: SecretInfo[];
+}
+
+export interface BuildableReference {
+  blueprintIdentifier: string;
+  blueprintName: string;
+  buildableName: string;
+  referencedContainer: string;
+}
+
+export interface SchemeDetail {
+  name: string;
+  path: string;
+  shared: boolean;
+  buildEntries: {
+    target: BuildableReference;
+    buildForRunning: boolean;
+    buildForTesting: boolean;
+    buildForArchiving: boolean;
+  }[];
+  testables: { target: BuildableReference; skipped: boolean }[];
+  testPlans: { reference: string; isDefault: boolean }[];
+  launchConfiguration?: string;
+  testConfiguration?: string;
+  archiveConfiguration?: string;
+}
+
+export interface ContainerCandidate {
+  kind: "workspace" | "project";
+  path: string;
+  projects: string[];
+  schemes: string[];
 }
 
 export interface ScanResult {
   projectName: string;
-  workspace?: string;
-  xcodeproj?: string;
+  containers: ContainerCandidate[];
   schemes: string[];
+  schemeDetails: SchemeDetail[];
   bundleIdDev?: string;
   bundleIdDis?: string;
   teamId?: string;
 }
 
 export interface LaneRunResult {
-  status: "success" | "failed";
+  runId: string;
+  status: "success" | "failed" | "cancelled";
   exitCode: number;
   output: string;
+  /** Secrets masked in `output`. */
+  redactions: number;
+  lane: string;
+}
+
+export interface RunSummary {
+  runId: string;
   lane: string;
+  status: LaneRunResult["status"];
+  exitCode: number;
+  startedAtMs: number;
+  finishedAtMs: number;
+  durationMs: number;
+  gitHead?: string;
+  configHash?: string;
+  redactions: number;
+}
+
+export interface RunRecord extends RunSummary {
+  output: string;
+}
+
+export type LaneStream = "stdout" | "stderr" | "preflight";
+
+export interface LaneOutputEvent {
+  runId: string;
+  stream: LaneStream;
+  line: string;
+}
+
+export interface LaneFinishedEvent {
+  runId: string;
+  lane: string;
+  status: LaneRunResult["status"];
+  exitCode: number;
 }
 
 export interface GeneratedFileStatus {
@@ -65,15 +161,128 @@ export interface GenerateResult {
   mode: string;
   runtimeEnvPath?: string;
   runtimeEnvWritten: boolean;
+  envConflicts: EnvConflict[];
   files: GeneratedFileStatus[];
   stdout: string;
   stderr: string;
+  redactions: number;
+}
+
+export type FileChange = "new" | "changed" | "unchanged" | "deleted";
+
+export interface FilePreview {
+  path: string;
+  change: FileChange;
+  diff: string;
+}
+
+export interface GenerationPreview {
+  previewId: string;
+  projectPath: string;
+  files: FilePreview[];
+  envConflicts: EnvConflict[];
+}
+
+export interface EnvConflict {
+  path: string;
+  key: string;
+  fileValue: string;
+  appValue: string;
+}
+
+export interface FastfileConstant {
+  name: string;
+  value: string;
+  envVar?: string;
+  line: number;
+}
+
+export interface FastfileConstantsUpdate {
+  fastfilePath: string;
+  updated: string[];
+  missing: string[];
+  backupId?: string;
+}
+
+export interface Lane {
+  platform?: string;
+  name: string;
+  private: boolean;
+  description?: string;
+  options: string[];
+  takesOptions: boolean;
+  forwardsOptions: boolean;
+  file: string;
+  line: number;
+  usesContainer: boolean;
+  runsTests: boolean;
+  schemeConstant?: string;
+}
+
+export type LaneOptionValue = string | number | boolean;
+
+export interface EnvFileStatus {
+  path: string;
+  exists: boolean;
+  overrides: boolean;
+}
+
+export interface EffectiveValue {
+  key: string;
+  value: string;
+  source: string;
+  overridden: string[];
+}
+
+export interface EffectiveEnv {
+  envArg?: string;
+  files: EnvFileStatus[];
+  values: EffectiveValue[];
+}
+
+export interface FastfileLanes {
+  lanes: Lane[];
+  unresolvedImports: string[];
+}
+
+export interface BackupFile {
+  path: string;
+  existed: boolean;
+}
+
+export interface BackupSummary {
+  backupId: string;
+  reason: string;
+  createdAtMs: number;
+  files: BackupFile[];
+}
+
+export type SettingLayer = "default" | "projectXcconfig" | "project" | "targetXcconfig" | "target";
+
+export interface ResolvedSetting {
+  key: string;
+  value: string;
+  source: {
+    layer: SettingLayer;
+    file?: string;
+    line?: number;
+    condition?: string;
+  };
+  references: ResolvedSetting[];
+}
+
+export interface IdentitySource {
+  scheme: string;
+  key: string;
+  value: string;
+  setting?: ResolvedSetting;
 }
 
 export interface IdentityResult {
   bundleIdDev?: string;
   bundleIdDis?: string;
   teamId?: string;
+  sources: IdentitySource[];
 }
 
 export interface DoctorCheck {
@@ -83,6 +292,73 @@ export interface DoctorCheck {
   suggestion?: string;
 }
 
+export interface AscToken {
+  token: string;
+  keyId: string;
+  issuerId?: string;
+  expiresAt: number;
+}
+
 export interface DoctorReport {
   checks: DoctorCheck[];
 }
+
+export type ProvisioningProfileType = "development" | "adHoc" | "appStore" | "enterprise";
+
+export interface ProvisioningProfile {
+  path: string;
+  uuid: string;
+  name: string;
+  appIdName?: string;
+  teamIdentifier?: string;
+  teamName?: string;
+  applicationIdentifier?: string;
+  bundleId?: string;
+  creationDate?: string;
+  expirationDate?: string;
+  expired: boolean;
+  profileType: ProvisioningProfileType;
+  entitlements: Record<string, unknown>;
+  provisionedDevices: string[];
+}
+
+export type ErrorKind =
+  | "projectNotFound"
+  | "containerNotFound"
+  | "invalidContainerConfig"
+  | "bootstrapScriptNotFound"
+  | "bootstrapScriptIncompatible"
+  | "profileNotFound"
+  | "runNotFound"
+  | "laneNotRunning"
+  | "invalidApiKey"
+  | "previewNotFound"
+  | "previewStale"
+  | "backupNotFound"
+  | "secretStoreLocked"
+  | "secretNotFound"
+  | "invalidInput"
+  | "io"
+  | "parse"
+  | "commandFailed"
+  | "internal";
+
+export interface AppError {
+  kind: ErrorKind;
+  message: string;
+  hint?: string;
+  path?: string;
+  command?: string;
+}
+
+export interface AppSettings {
+  loginShell: string;
+  bootstrapScriptPath: string;
+}
+
+export interface LoginEnvSummary {
+  shell: string;
+  captured: boolean;
+  variableCount: number;
+  variables: Record<string, string>;
+}